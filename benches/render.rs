@@ -0,0 +1,79 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use placeholder::{render, Renderer, Template};
+use std::collections::HashMap;
+
+// About 64KiB of text without any placeholders
+fn plain_text() -> String {
+    "The quick brown fox jumps over the lazy dog.\n".repeat(1_500)
+}
+
+// A template with `count` distinct placeholders, along with a value for each of them
+fn distinct(count: usize) -> (String, HashMap<String, String>) {
+    let template = (0..count)
+        .map(|i| format!("<td>{{field{}}}</td>", i))
+        .collect::<String>();
+
+    let values = (0..count)
+        .map(|i| (format!("field{}", i), format!("value {}", i)))
+        .collect();
+
+    (template, values)
+}
+
+fn placeholder_free(c: &mut Criterion) {
+    let template = plain_text();
+    let values: HashMap<String, String> = HashMap::new();
+
+    c.bench_function("placeholder_free", |b| {
+        b.iter(|| render(black_box(&template), &values))
+    });
+}
+
+fn many_distinct(c: &mut Criterion) {
+    let (template, values) = distinct(1_000);
+
+    c.bench_function("many_distinct", |b| {
+        b.iter(|| render(black_box(&template), &values))
+    });
+}
+
+fn one_repeated(c: &mut Criterion) {
+    let template = "<li>{name}</li>".repeat(5_000);
+    let values = [("name", "Homer")];
+
+    c.bench_function("one_repeated", |b| {
+        b.iter(|| render(black_box(&template), &values))
+    });
+}
+
+fn precompiled(c: &mut Criterion) {
+    let (template, values) = distinct(1_000);
+    let mut group = c.benchmark_group("precompiled");
+
+    group.bench_function("parse_each_time", |b| {
+        b.iter(|| Renderer::new().render(black_box(&template), &values))
+    });
+
+    let parsed = Template::parse(&template);
+
+    group.bench_function("template", |b| {
+        b.iter(|| black_box(&parsed).render(&values))
+    });
+
+    let compiled = Renderer::new().compile(&template).unwrap();
+
+    group.bench_function("compiled", |b| {
+        b.iter(|| black_box(&compiled).render(&values))
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    placeholder_free,
+    many_distinct,
+    one_repeated,
+    precompiled
+);
+criterion_main!(benches);
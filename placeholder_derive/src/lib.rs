@@ -0,0 +1,89 @@
+//! Placeholder Derive - Render Placeholder templates from struct fields
+//!
+//! This crate is used through the `derive` feature of the `placeholder` crate, which re-exports
+//! the `Placeholders` derive macro.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+/// Derive `placeholder::Lookup` for a struct, so that it can be rendered with `{field}`
+/// placeholders
+///
+/// Each named field becomes a placeholder of the same name, with its value being the field
+/// formatted with `Display`. Any other placeholder is missing.
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{render_struct, Placeholders};
+///
+/// #[derive(Placeholders)]
+/// struct Customer {
+///   name: String,
+///   donuts: u32,
+///   member: bool,
+/// }
+///
+/// fn main() {
+///   let customer = Customer { name: String::from("Homer"), donuts: 42, member: true };
+///
+///   assert!(render_struct("{name} ate {donuts} donuts (member: {member})", &customer)
+///     == Ok(String::from("Homer ate 42 donuts (member: true)")));
+///
+///   assert!(render_struct("{name} {surname}", &customer) == Err(String::from("surname")));
+/// }
+/// ```
+#[proc_macro_derive(Placeholders)]
+pub fn derive_placeholders(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return syn::Error::new_spanned(
+                    &input.ident,
+                    "Placeholders can only be derived for structs with named fields",
+                )
+                .to_compile_error()
+                .into()
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(
+                &input.ident,
+                "Placeholders can only be derived for structs",
+            )
+            .to_compile_error()
+            .into()
+        }
+    };
+
+    let arms = fields.iter().filter_map(|field| {
+        let ident = field.ident.as_ref()?;
+        let name = ident.to_string();
+        let name = name.strip_prefix("r#").unwrap_or(&name);
+
+        Some(quote! {
+            #name => ::std::option::Option::Some(::std::borrow::Cow::Owned(
+                ::std::string::ToString::to_string(&self.#ident),
+            )),
+        })
+    });
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::placeholder::Lookup for #ident #type_generics #where_clause {
+            fn get(&self, name: &str) -> ::std::option::Option<::std::borrow::Cow<'_, str>> {
+                match name {
+                    #(#arms)*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    }
+    .into()
+}
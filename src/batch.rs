@@ -0,0 +1,298 @@
+use crate::renderer::{resolve_from, Collect, TooManyMissing};
+use crate::{AsValues, OnMissing, Renderer, Template};
+use std::collections::BTreeSet;
+
+/// A summary of rendering a batch of rows with `render_many_metrics()`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchMetrics {
+    /// The number of placeholder values substituted across the rows that rendered, including
+    /// repeats. The names of blocks aren't placeholders, so they aren't counted
+    pub substitutions: usize,
+
+    /// The number of bytes output across the rows that rendered
+    pub bytes: usize,
+
+    /// The number of rows that failed to render
+    pub failed: usize,
+
+    /// The names of the placeholders missing from any row
+    pub missing: BTreeSet<String>,
+}
+
+/// Render the template once per row of placeholder values, along with a summary of the batch
+///
+/// The template is parsed once for the whole batch. Each row is rendered just as `render()`
+/// would, while the summary gives an overview of the run, such as for an operator checking a
+/// mail merge. Every placeholder missing from a row is reported in the summary, not just the
+/// first one.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `rows` is each row's placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `(results, metrics)` is the result of rendering each row, in order, along with the summary of
+/// the batch. Each result is either `Ok(output)`, or `Err(name)` with the name of the first
+/// placeholder missing from the row
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_many_metrics;
+///
+/// fn main() {
+///   let rows = [
+///     &[("greet", "Hello"), ("name", "Homer")][..],
+///     &[("greet", "Hi")][..],
+///   ];
+///
+///   let (results, metrics) = render_many_metrics("{greet} {name}", rows);
+///
+///   assert!(results == vec![Ok(String::from("Hello Homer")), Err(String::from("name"))]);
+///   assert!(metrics.substitutions == 2);
+///   assert!(metrics.bytes == 11);
+///   assert!(metrics.failed == 1);
+///   assert!(metrics.missing.iter().eq(["name"]));
+/// }
+/// ```
+pub fn render_many_metrics<I, T>(
+    template: &str,
+    rows: I,
+) -> (Vec<Result<String, String>>, BatchMetrics)
+where
+    I: IntoIterator<Item = T>,
+    T: AsValues,
+{
+    let template = Template::parse(template);
+
+    // Missing values are rendered as nothing, so rendering carries on to find every one of them
+    let renderer = Renderer::new().on_missing(OnMissing::Empty);
+
+    let mut results = Vec::new();
+    let mut metrics = BatchMetrics::default();
+
+    for row in rows {
+        let mut collect = Collect::new(usize::MAX);
+
+        let result = match renderer.render_template_to(&template, resolve_from(&row), &mut collect)
+        {
+            Ok(Ok(())) if collect.names.is_empty() => {
+                metrics.substitutions += collect.substitutions;
+                metrics.bytes += collect.output.len();
+                Ok(collect.output)
+            }
+            Ok(Err(error)) if collect.names.is_empty() => Err(error.to_string()),
+            Ok(_) | Err(TooManyMissing) => Err(collect.names[0].clone()),
+        };
+
+        if result.is_err() {
+            metrics.failed += 1;
+        }
+
+        metrics.missing.extend(collect.names);
+        results.push(result);
+    }
+
+    (results, metrics)
+}
+
+/// Render the template once per row of placeholder values, joining the rows into one output
+///
+/// This suits generating the body of a table, with a template for a single row. The template is
+/// parsed once for the whole batch, and rendering stops at the first row that fails.
+///
+/// # Parameters
+///
+/// `row_template` is the template text for a single row, containing placeholders in the form
+/// `{name}`
+///
+/// `rows` is each row's placeholder values to replace within `row_template`, such as a HashMap
+/// (see `AsValues`)
+///
+/// `separator` is the text to put between each rendered row
+///
+/// # Returns
+///
+/// `Ok(output)` is every row rendered, in order, joined by `separator`
+///
+/// `Err((index, name))` is the index of the first row that failed to render, counting from zero,
+/// along with the name of the first placeholder missing from that row
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_rows;
+///
+/// fn main() {
+///   let rows = [
+///     &[("name", "Homer"), ("food", "Donuts")][..],
+///     &[("name", "Marge"), ("food", "Pretzels")][..],
+///   ];
+///
+///   assert!(render_rows("<tr><td>{name}</td><td>{food}</td></tr>", rows, "\n")
+///     == Ok(String::from(
+///       "<tr><td>Homer</td><td>Donuts</td></tr>\n<tr><td>Marge</td><td>Pretzels</td></tr>",
+///     )));
+/// }
+/// ```
+pub fn render_rows<I, T>(
+    row_template: &str,
+    rows: I,
+    separator: &str,
+) -> Result<String, (usize, String)>
+where
+    I: IntoIterator<Item = T>,
+    T: AsValues,
+{
+    let template = Template::parse(row_template);
+    let renderer = Renderer::new();
+    let mut output = String::new();
+
+    for (index, row) in rows.into_iter().enumerate() {
+        if index > 0 {
+            output.push_str(separator);
+        }
+
+        match renderer.render_template(&template, row) {
+            Ok(rendered) => output.push_str(&rendered),
+            Err(error) => return Err((index, error.to_string())),
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RenderError;
+    use std::collections::HashMap;
+
+    #[test]
+    fn mixed_rows() {
+        let before = String::from("{greet} {name}, {greet}! {food|pad(7)}");
+
+        let mut homer = HashMap::new();
+        homer.insert("greet", "Hello");
+        homer.insert("name", "Homer");
+        homer.insert("food", "Donuts");
+
+        let mut marge = HashMap::new();
+        marge.insert("greet", "Hi");
+        marge.insert("name", "Marge");
+
+        let mut bart = HashMap::new();
+        bart.insert("name", "Bart");
+
+        let mut lisa = HashMap::new();
+        lisa.insert("greet", "Hey");
+        lisa.insert("name", "Lisa");
+        lisa.insert("food", "Tofu");
+
+        let (results, metrics) = render_many_metrics(&before, [&homer, &marge, &bart, &lisa]);
+
+        assert!(
+            results
+                == vec![
+                    Ok(String::from("Hello Homer, Hello!  Donuts")),
+                    Err(String::from("food")),
+                    Err(String::from("greet")),
+                    Ok(String::from("Hey Lisa, Hey!    Tofu")),
+                ]
+        );
+
+        assert!(
+            metrics
+                == BatchMetrics {
+                    substitutions: 8,
+                    bytes: 27 + 22,
+                    failed: 2,
+                    missing: BTreeSet::from([String::from("food"), String::from("greet")]),
+                }
+        );
+    }
+
+    #[test]
+    fn blocks_not_substitutions() {
+        let rows = [
+            &[("a", "yes"), ("b", "Hello"), ("items", "")][..],
+            &[("a", ""), ("items", "x\ny\nz")][..],
+        ];
+        let (results, metrics) =
+            render_many_metrics("{#if a}{b}{/if}{#each items}{items}{/each}", rows);
+
+        assert!(results == vec![Ok(String::from("Hello")), Ok(String::from("xyz"))]);
+        assert!(metrics.substitutions == 4);
+    }
+
+    #[test]
+    fn optional_and_defaults_not_missing() {
+        let rows = [&[("name", "Homer")][..], &[][..]];
+        let (results, metrics) = render_many_metrics("{title=Mr} {name}{suffix?}", rows);
+
+        assert!(results == vec![Ok(String::from("Mr Homer")), Err(String::from("name"))]);
+        assert!(metrics.substitutions == 1);
+        assert!(metrics.missing.iter().eq(["name"]));
+    }
+
+    #[test]
+    fn other_errors() {
+        let rows = [&[("active", "maybe")][..], &[("active", "yes")][..]];
+        let (results, metrics) = render_many_metrics("{active|yesno}", rows);
+
+        assert!(
+            results
+                == vec![
+                    Err(RenderError::InvalidBoolean {
+                        value: String::from("maybe")
+                    }
+                    .to_string()),
+                    Ok(String::from("Yes")),
+                ]
+        );
+
+        assert!(metrics.failed == 1);
+        assert!(metrics.missing.is_empty());
+    }
+
+    #[test]
+    fn table_rows() {
+        let mut homer = HashMap::new();
+        homer.insert(String::from("name"), String::from("Homer"));
+        homer.insert(String::from("age"), String::from("39"));
+
+        let mut marge = homer.clone();
+        marge.insert(String::from("name"), String::from("Marge"));
+        marge.insert(String::from("age"), String::from("36"));
+
+        let mut bart = homer.clone();
+        bart.insert(String::from("name"), String::from("Bart"));
+        bart.insert(String::from("age"), String::from("10"));
+
+        let rows = vec![homer, marge, bart];
+
+        assert!(
+            render_rows("| {name|rpad(5)} | {age} |", &rows, "\n")
+                == Ok(String::from(
+                    "| Homer | 39 |\n| Marge | 36 |\n| Bart  | 10 |"
+                ))
+        );
+
+        let mut rows = rows;
+        rows[2].remove("age");
+
+        assert!(render_rows("{name}: {age}", &rows, "\n") == Err((2, String::from("age"))));
+        assert!(render_rows("{name}", &rows[..0], "\n") == Ok(String::new()));
+    }
+
+    #[test]
+    fn no_rows() {
+        let rows: [&[(&str, &str)]; 0] = [];
+
+        assert!(render_many_metrics("{name}", rows) == (Vec::new(), BatchMetrics::default()));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::template::{visit, BlockKind, Filter, Segment, Visitor};
+use crate::{AsValues, Template};
+
+/// Which blocks rendering a template would include, as found by `analyze_blocks()`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BlockReport {
+    /// Each block reached, in the order rendering would reach them. Blocks within a block that's
+    /// left out aren't reached, while those within an `each` are reached once per iteration
+    pub blocks: Vec<BlockOutcome>,
+}
+
+/// What rendering would do with a block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockOutcome {
+    /// An `{#if name}` block, with whether its body would be included
+    If { name: String, included: bool },
+
+    /// An `{#each name}` block, with how many times its body would be included
+    Each { name: String, iterations: usize },
+}
+
+/// Report which blocks rendering the template would include, without rendering it
+///
+/// This helps debug why a section of the output is missing. Nothing is rendered, so no filters
+/// are applied and a missing placeholder isn't an error, while a block whose value is missing is
+/// reported as left out.
+///
+/// # Parameters
+///
+/// `template` is the template text containing blocks in the form `{#if name}...{/if}` or
+/// `{#each name}...{/each}`
+///
+/// `values` is the placeholder values, such as a HashMap (see `AsValues`)
+///
+/// # Returns
+///
+/// The blocks rendering would reach, and what it would do with each
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{analyze_blocks, BlockOutcome};
+///
+/// fn main() {
+///   let template = String::from("{#if member}Welcome back!{/if}{#each items}* {items}\n{/each}");
+///   let values = [("member", ""), ("items", "Donuts\nDuff")];
+///
+///   assert!(analyze_blocks(&template, &values).blocks
+///     == vec![
+///       BlockOutcome::If { name: String::from("member"), included: false },
+///       BlockOutcome::Each { name: String::from("items"), iterations: 2 },
+///     ]);
+/// }
+/// ```
+pub fn analyze_blocks<T: AsValues>(template: &str, values: T) -> BlockReport {
+    let mut analyze = Analyze {
+        report: BlockReport::default(),
+    };
+
+    visit(
+        Template::parse(template).segments(),
+        &|name| values.get_value(name).map(|value| value.into_owned()),
+        &mut analyze,
+    );
+
+    analyze.report
+}
+
+// Reports what rendering would do with each block it reaches
+struct Analyze {
+    report: BlockReport,
+}
+
+impl Visitor for Analyze {
+    fn block(&mut self, kind: BlockKind, name: &str, iterations: usize) {
+        self.report.blocks.push(match kind {
+            BlockKind::If => BlockOutcome::If {
+                name: name.to_string(),
+                included: iterations > 0,
+            },
+            BlockKind::Each => BlockOutcome::Each {
+                name: name.to_string(),
+                iterations,
+            },
+        });
+    }
+
+    fn defaulted(
+        &mut self,
+        _name: &str,
+        _filters: &[Filter],
+        _source: &str,
+        _start: usize,
+        default: &[Segment],
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) {
+        visit(default, lookup, self);
+    }
+
+    // A block whose value is missing is left out, just like one whose value is empty
+    fn missing_block(
+        &mut self,
+        kind: BlockKind,
+        name: &str,
+        _body: &[Segment],
+        _start: usize,
+        _lookup: &dyn Fn(&str) -> Option<String>,
+    ) {
+        self.block(kind, name, 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+
+    #[test]
+    fn if_and_each() {
+        let before = String::from(
+            "{#if member}Welcome back {name}!\n{/if}\
+             {#if banned}Go away!\n{/if}\
+             {#each items}* {items}\n{/each}",
+        );
+
+        let values = [
+            ("name", "Homer"),
+            ("member", "yes"),
+            ("banned", ""),
+            ("items", "Donuts\nDuff\nPork chops"),
+        ];
+
+        assert!(
+            analyze_blocks(&before, &values)
+                == BlockReport {
+                    blocks: vec![
+                        BlockOutcome::If {
+                            name: String::from("member"),
+                            included: true,
+                        },
+                        BlockOutcome::If {
+                            name: String::from("banned"),
+                            included: false,
+                        },
+                        BlockOutcome::Each {
+                            name: String::from("items"),
+                            iterations: 3,
+                        },
+                    ]
+                }
+        );
+
+        assert!(
+            render(&before, &values)
+                == Ok(String::from(
+                    "Welcome back Homer!\n* Donuts\n* Duff\n* Pork chops\n"
+                ))
+        );
+    }
+
+    #[test]
+    fn nested_blocks() {
+        let before = String::from(
+            "{#each items}{#if items}[{items}]{/if}{/each}{#if missing}{#if items}x{/if}{/if}",
+        );
+
+        let values = [("items", "a\n\nb")];
+
+        assert!(
+            analyze_blocks(&before, &values).blocks
+                == vec![
+                    BlockOutcome::Each {
+                        name: String::from("items"),
+                        iterations: 3,
+                    },
+                    BlockOutcome::If {
+                        name: String::from("items"),
+                        included: true,
+                    },
+                    BlockOutcome::If {
+                        name: String::from("items"),
+                        included: false,
+                    },
+                    BlockOutcome::If {
+                        name: String::from("items"),
+                        included: true,
+                    },
+                    BlockOutcome::If {
+                        name: String::from("missing"),
+                        included: false,
+                    },
+                ]
+        );
+    }
+
+    #[test]
+    fn without_blocks() {
+        assert!(analyze_blocks("{name}", &[("name", "Homer")]) == BlockReport::default());
+    }
+}
@@ -0,0 +1,263 @@
+use crate::template::{visit, BlockKind, Filter, Segment, Visitor};
+use crate::{filters, RenderError, Renderer, Template};
+use std::collections::BTreeMap;
+
+/// Everything found to be wrong while rendering a template with `render_diagnose()`
+///
+/// Each list is sorted, with each entry listed once.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diagnostics {
+    /// The names of the placeholders missing from the placeholder values
+    pub missing: Vec<String>,
+
+    /// The names of the placeholder values that no placeholder uses
+    pub unused: Vec<String>,
+
+    /// The placeholders, as written in the template, with an unknown filter or invalid filter
+    /// arguments
+    pub malformed: Vec<String>,
+
+    /// The placeholders, as written in the template, whose value a filter doesn't allow, such as
+    /// `yesno` with a value that isn't a boolean
+    pub disallowed: Vec<String>,
+}
+
+impl Diagnostics {
+    /// Whether nothing was found to be wrong, other than unused values
+    pub fn is_renderable(&self) -> bool {
+        self.missing.is_empty() && self.malformed.is_empty() && self.disallowed.is_empty()
+    }
+}
+
+/// Render the template, reporting everything wrong with it and its placeholder values at once
+///
+/// This suits a template playground, where it's more useful to see every problem after each edit
+/// than to fix them one at a time. The template is parsed and rendered once, carrying on past
+/// each problem to find the rest.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, as `(name, value)` pairs such
+/// as a HashMap. When a name appears more than once, the last one wins
+///
+/// # Returns
+///
+/// `(Some(output), diagnostics)` is the template text with all its placeholders replaced with
+/// their corresponding placeholder values, along with any unused values
+///
+/// `(None, diagnostics)` is everything that stopped the template from rendering, along with any
+/// unused values
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{render_diagnose, Diagnostics};
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name|shout}</h1><p>{surname}: {member|yesno}</p>");
+///   let values = [("greet", "Hello"), ("name", "Homer"), ("member", "maybe"), ("food", "Donuts")];
+///
+///   assert!(render_diagnose(&template, values)
+///     == (None, Diagnostics {
+///       missing: vec![String::from("surname")],
+///       unused: vec![String::from("food")],
+///       malformed: vec![String::from("{name|shout}")],
+///       disallowed: vec![String::from("{member|yesno}")],
+///     }));
+/// }
+/// ```
+pub fn render_diagnose<I, K, V>(template: &str, values: I) -> (Option<String>, Diagnostics)
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let values: BTreeMap<String, String> = values
+        .into_iter()
+        .map(|(name, value)| (name.as_ref().to_string(), value.as_ref().to_string()))
+        .collect();
+
+    let template = Template::parse(template);
+
+    let mut diagnose = Diagnose {
+        renderer: &Renderer::new(),
+        output: String::new(),
+        diagnostics: Diagnostics::default(),
+    };
+
+    visit(
+        template.segments(),
+        &|name| values.get(name).cloned(),
+        &mut diagnose,
+    );
+
+    let Diagnose {
+        output,
+        mut diagnostics,
+        ..
+    } = diagnose;
+
+    diagnostics.unused = values
+        .into_keys()
+        .filter(|name| !template.required_keys().contains(name))
+        .collect();
+
+    for found in [
+        &mut diagnostics.missing,
+        &mut diagnostics.malformed,
+        &mut diagnostics.disallowed,
+    ] {
+        found.sort();
+        found.dedup();
+    }
+
+    if diagnostics.is_renderable() {
+        (Some(output), diagnostics)
+    } else {
+        (None, diagnostics)
+    }
+}
+
+// Renders what it can of a template, while gathering what's wrong with it and its values
+struct Diagnose<'r> {
+    renderer: &'r Renderer,
+    output: String,
+    diagnostics: Diagnostics,
+}
+
+impl Visitor for Diagnose<'_> {
+    fn literal(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn placeholder(
+        &mut self,
+        name: &str,
+        filters: &[Filter],
+        source: &str,
+        _start: usize,
+        value: Option<String>,
+    ) {
+        let value = match value {
+            Some(value) => value,
+            None => {
+                self.diagnostics.missing.push(name.to_string());
+                return;
+            }
+        };
+
+        let value = filters.iter().try_fold(value, |value, filter| {
+            filters::apply(self.renderer, name, filter, &value)
+        });
+
+        match value {
+            Ok(value) => self.output.push_str(&value),
+            Err(RenderError::InvalidBoolean { .. }) => {
+                self.diagnostics.disallowed.push(source.to_string())
+            }
+            Err(_) => self.diagnostics.malformed.push(source.to_string()),
+        }
+    }
+
+    // The default is the value, while anything wrong within it is gathered too
+    fn defaulted(
+        &mut self,
+        name: &str,
+        filters: &[Filter],
+        source: &str,
+        start: usize,
+        default: &[Segment],
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) {
+        let mut diagnose = Diagnose {
+            renderer: self.renderer,
+            output: String::new(),
+            diagnostics: Diagnostics::default(),
+        };
+
+        visit(default, lookup, &mut diagnose);
+
+        let Diagnostics {
+            missing,
+            malformed,
+            disallowed,
+            ..
+        } = diagnose.diagnostics;
+
+        self.diagnostics.missing.extend(missing);
+        self.diagnostics.malformed.extend(malformed);
+        self.diagnostics.disallowed.extend(disallowed);
+        self.placeholder(name, filters, source, start, Some(diagnose.output));
+    }
+
+    fn missing_block(
+        &mut self,
+        _kind: BlockKind,
+        name: &str,
+        _body: &[Segment],
+        _start: usize,
+        _lookup: &dyn Fn(&str) -> Option<String>,
+    ) {
+        self.diagnostics.missing.push(name.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn everything_at_once() {
+        let before = String::from(
+            "{greet} {name} {name|shout} {code|pad(x)} {member|yesno} {member|yesno} {missing}",
+        );
+
+        let mut values = HashMap::new();
+        values.insert(String::from("greet"), String::from("Hello"));
+        values.insert(String::from("code"), String::from("42"));
+        values.insert(String::from("member"), String::from("maybe"));
+        values.insert(String::from("food"), String::from("Donuts"));
+        values.insert(String::from("drink"), String::from("Duff"));
+
+        assert!(
+            render_diagnose(&before, &values)
+                == (
+                    None,
+                    Diagnostics {
+                        missing: vec![String::from("missing"), String::from("name")],
+                        unused: vec![String::from("drink"), String::from("food")],
+                        malformed: vec![String::from("{code|pad(x)}")],
+                        disallowed: vec![String::from("{member|yesno}")],
+                    }
+                )
+        );
+    }
+
+    #[test]
+    fn renders_with_unused_values() {
+        let values = [("greet", "Hello"), ("name", "Homer"), ("food", "Donuts")];
+
+        assert!(
+            render_diagnose("{greet} {name|pad(6)}", values)
+                == (
+                    Some(String::from("Hello  Homer")),
+                    Diagnostics {
+                        unused: vec![String::from("food")],
+                        ..Diagnostics::default()
+                    }
+                )
+        );
+    }
+
+    #[test]
+    fn renders_cleanly() {
+        let (output, diagnostics) = render_diagnose("{a}{b}", [("a", "1"), ("b", "2")]);
+
+        assert!(output == Some(String::from("12")));
+        assert!(diagnostics == Diagnostics::default());
+        assert!(diagnostics.is_renderable());
+    }
+}
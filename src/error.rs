@@ -0,0 +1,142 @@
+use std::error::Error;
+use std::fmt;
+
+/// Errors returned while rendering a template
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenderError {
+    /// A placeholder in the template had no corresponding value
+    ///
+    /// `name` is the name of the placeholder missing from the values
+    ///
+    /// `available` is the sorted list of keys that were present in the values. It is only
+    /// populated by `render_debug()` so that key names aren't leaked by default
+    MissingPlaceholder {
+        name: String,
+        available: Option<Vec<String>>,
+    },
+
+    /// The placeholder values gave more than one value for the same placeholder, with the
+    /// `Renderer`'s `reject_duplicates()` on
+    ///
+    /// `name` is the name of the first placeholder given more than one value
+    DuplicateValue { name: String },
+
+    /// A template was requested by name from a `TemplateSet` that doesn't contain it
+    ///
+    /// `name` is the name of the missing template
+    UnknownTemplate { name: String },
+
+    /// A placeholder's value exists but can't be rendered as text, such as a JSON object or array
+    ///
+    /// `name` is the name of the placeholder with the unrenderable value
+    UnrenderableValue { name: String },
+
+    /// A placeholder's name is longer than the `Renderer`'s `max_name_len()`
+    ///
+    /// `name` is the name of the placeholder that is too long
+    NameTooLong { name: String },
+
+    /// A template has more placeholders than the `Renderer`'s `max_placeholders()`
+    ///
+    /// `max` is the most placeholders allowed
+    TooManyPlaceholders { max: usize },
+
+    /// A placeholder's value, once filtered, is longer than the `Renderer`'s `max_value_len()`
+    ///
+    /// `name` is the name of the placeholder whose value is too long
+    ValueTooLong { name: String },
+
+    /// A placeholder's filter chain used a filter that doesn't exist
+    ///
+    /// `name` is the name of the unknown filter
+    UnknownFilter { name: String },
+
+    /// A filter was given the wrong number of arguments, or an argument it couldn't understand
+    ///
+    /// `name` is the name of the filter
+    InvalidFilterArguments { name: String },
+
+    /// The `yesno` filter was given a value it doesn't recognise as a boolean
+    ///
+    /// `value` is the unrecognised value
+    InvalidBoolean { value: String },
+
+    /// The `date` filter was given a value it can't parse as a date and time
+    ///
+    /// `value` is the unparseable value
+    InvalidDate { value: String },
+
+    /// A recursive render found a value still containing placeholders at the `Renderer`'s maximum
+    /// depth
+    ///
+    /// `name` is the name of the placeholder whose value couldn't be expanded any further
+    RecursionLimit { name: String },
+
+    /// A recursive render took longer than the `Renderer`'s `timeout()`
+    Timeout,
+
+    /// A template was required to be dynamic by `render_require_dynamic()`, but it has no
+    /// placeholders at all
+    NoPlaceholders,
+
+    /// A Python-style template given to `render_python()` used a conversion other than
+    /// `%(name)s`
+    ///
+    /// `spec` is the unsupported conversion as written in the template, such as `%(age)d`
+    UnsupportedConversion { spec: String },
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::MissingPlaceholder {
+                name,
+                available: None,
+            } => write!(f, "{}", name),
+            RenderError::MissingPlaceholder {
+                name,
+                available: Some(available),
+            } => write!(f, "{} (available: {})", name, available.join(", ")),
+            RenderError::DuplicateValue { name } => {
+                write!(f, "duplicate value for placeholder: {}", name)
+            }
+            RenderError::UnknownTemplate { name } => write!(f, "unknown template: {}", name),
+            RenderError::UnrenderableValue { name } => {
+                write!(f, "unrenderable value for placeholder: {}", name)
+            }
+            RenderError::NameTooLong { name } => write!(f, "placeholder name too long: {}", name),
+            RenderError::TooManyPlaceholders { max } => {
+                write!(f, "template has more than {} placeholders", max)
+            }
+            RenderError::ValueTooLong { name } => {
+                write!(f, "placeholder value too long: {}", name)
+            }
+            RenderError::UnknownFilter { name } => write!(f, "unknown filter: {}", name),
+            RenderError::InvalidFilterArguments { name } => {
+                write!(f, "invalid arguments for filter: {}", name)
+            }
+            RenderError::InvalidBoolean { value } => write!(f, "invalid boolean: {}", value),
+            RenderError::InvalidDate { value } => write!(f, "invalid date: {}", value),
+            RenderError::RecursionLimit { name } => {
+                write!(f, "recursion limit reached for placeholder: {}", name)
+            }
+            RenderError::Timeout => write!(f, "render timed out"),
+            RenderError::NoPlaceholders => write!(f, "template has no placeholders"),
+            RenderError::UnsupportedConversion { spec } => {
+                write!(f, "unsupported conversion: {}", spec)
+            }
+        }
+    }
+}
+
+impl Error for RenderError {}
+
+/// Errors returned while rendering a template, collecting every missing placeholder
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CollectError<T> {
+    /// Placeholders in the template had no corresponding values, such as their names
+    Missing(T),
+
+    /// Rendering failed for any other reason, such as an unknown filter, which stops rendering
+    Render(RenderError),
+}
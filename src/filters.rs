@@ -0,0 +1,653 @@
+use crate::template::Filter;
+use crate::{RenderError, Renderer, UnknownFilter};
+
+#[cfg(test)]
+thread_local! {
+    // How many times a filter has been applied, so tests can check filtered values are reused
+    pub(crate) static RUNS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+// Apply a filter from a placeholder's filter chain, such as `pad(8)`, to the value of the
+// placeholder `name`, with any options for the filter taken from `renderer`
+pub(crate) fn apply(
+    renderer: &Renderer,
+    name: &str,
+    filter: &Filter,
+    value: &str,
+) -> Result<String, RenderError> {
+    #[cfg(test)]
+    RUNS.with(|runs| runs.set(runs.get() + 1));
+
+    match (filter.name.as_str(), filter.args.as_slice()) {
+        ("pad", [width]) => pad(renderer, name, filter, value, width, " ", true),
+        ("pad", [width, fill]) => pad(renderer, name, filter, value, width, fill, true),
+        ("rpad", [width]) => pad(renderer, name, filter, value, width, " ", false),
+        ("rpad", [width, fill]) => pad(renderer, name, filter, value, width, fill, false),
+        ("center", [width]) => center(renderer, name, filter, value, width, " "),
+        ("center", [width, fill]) => center(renderer, name, filter, value, width, fill),
+        ("truncate", [width]) => truncate(filter, value, width, ""),
+        ("truncate", [width, ellipsis]) => truncate(filter, value, width, ellipsis),
+        ("yesno", []) => {
+            let (yes, no) = renderer.bool_labels();
+
+            match value.to_ascii_lowercase().as_str() {
+                "true" | "1" | "yes" => Ok(yes.to_string()),
+                "false" | "0" | "no" => Ok(no.to_string()),
+                _ => Err(RenderError::InvalidBoolean {
+                    value: value.to_string(),
+                }),
+            }
+        }
+        #[cfg(feature = "regex-backend")]
+        ("replace", [pattern, replacement]) => match regex::Regex::new(pattern) {
+            Ok(pattern) => Ok(pattern
+                .replace_all(value, replacement.as_str())
+                .into_owned()),
+            Err(_) => Err(invalid_arguments(filter)),
+        },
+        #[cfg(feature = "regex-backend")]
+        ("replace", _) => Err(invalid_arguments(filter)),
+        ("raw", []) => Ok(value.to_string()),
+        #[cfg(feature = "chrono")]
+        ("date", [format]) => date(filter, value, format, None),
+        #[cfg(feature = "chrono")]
+        ("date", [format, input]) => date(filter, value, format, Some(input)),
+        #[cfg(feature = "chrono")]
+        ("date", _) => Err(invalid_arguments(filter)),
+        ("json", []) => Ok(json_escape(value)),
+        ("jsonstr", []) => Ok(format!("\"{}\"", json_escape(value))),
+        ("pad" | "rpad" | "center" | "truncate" | "yesno" | "raw" | "json" | "jsonstr", _) => {
+            Err(invalid_arguments(filter))
+        }
+        _ => match renderer.unknown_filters() {
+            UnknownFilter::Ignore => Ok(value.to_string()),
+            UnknownFilter::Error => Err(RenderError::UnknownFilter {
+                name: filter.name.clone(),
+            }),
+        },
+    }
+}
+
+// Pad the value with `fill` up to `width` characters, on the left or the right. Values already at
+// least `width` characters long are left intact
+fn pad(
+    renderer: &Renderer,
+    name: &str,
+    filter: &Filter,
+    value: &str,
+    width: &str,
+    fill: &str,
+    left: bool,
+) -> Result<String, RenderError> {
+    let width = parse_width(filter, width)?;
+    let fill = parse_fill(filter, fill)?;
+
+    let padding = width.saturating_sub(value.chars().count());
+    let mut padded = padding_buffer(renderer, name, filter, value, width, fill)?;
+
+    if left {
+        padded.extend(std::iter::repeat_n(fill, padding));
+        padded.push_str(value);
+    } else {
+        padded.push_str(value);
+        padded.extend(std::iter::repeat_n(fill, padding));
+    }
+
+    Ok(padded)
+}
+
+// Cut the value down to at most `width` characters, ending with `ellipsis` when anything was cut.
+// The ellipsis counts towards `width`, so values are never split within a character
+fn truncate(
+    filter: &Filter,
+    value: &str,
+    width: &str,
+    ellipsis: &str,
+) -> Result<String, RenderError> {
+    let width = parse_width(filter, width)?;
+
+    if value.chars().nth(width).is_none() {
+        return Ok(value.to_string());
+    }
+
+    let kept = width.saturating_sub(ellipsis.chars().count());
+    Ok(value
+        .chars()
+        .take(kept)
+        .chain(ellipsis.chars())
+        .take(width)
+        .collect())
+}
+
+// Parse the value as a date and time, then format it with the strftime-style `format`. The value
+// is read as `input` when given, which is `unix` for seconds since the epoch, `rfc3339`, or a
+// strftime-style format of its own. Otherwise it can be either of the first two. Unix timestamps
+// are in UTC, while other dates keep their own offset
+#[cfg(feature = "chrono")]
+fn date(
+    filter: &Filter,
+    value: &str,
+    format: &str,
+    input: Option<&String>,
+) -> Result<String, RenderError> {
+    use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+    use std::fmt::Write;
+
+    let unix = || {
+        value
+            .parse::<i64>()
+            .ok()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            .map(|date| date.fixed_offset())
+    };
+
+    let rfc3339 = || DateTime::parse_from_rfc3339(value).ok();
+
+    // A custom input format might not include an offset, or even a time, in which case it's UTC
+    let custom = |input: &str| {
+        DateTime::<FixedOffset>::parse_from_str(value, input)
+            .ok()
+            .or_else(|| {
+                NaiveDateTime::parse_from_str(value, input)
+                    .ok()
+                    .or_else(|| {
+                        NaiveDate::parse_from_str(value, input)
+                            .ok()
+                            .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    })
+                    .map(|date| date.and_utc().fixed_offset())
+            })
+    };
+
+    let parsed = match input.map(String::as_str) {
+        None => unix().or_else(rfc3339),
+        Some("unix") => unix(),
+        Some("rfc3339") => rfc3339(),
+        Some(input) => custom(input),
+    };
+
+    let date = parsed.ok_or_else(|| RenderError::InvalidDate {
+        value: value.to_string(),
+    })?;
+
+    // An invalid format only shows up as an error while formatting
+    let mut formatted = String::new();
+
+    match write!(formatted, "{}", date.format(format)) {
+        Ok(()) => Ok(formatted),
+        Err(_) => Err(invalid_arguments(filter)),
+    }
+}
+
+// Escape the value for use within a JSON string, without the surrounding quotes
+fn json_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            c if c < ' ' => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+// Pad the value with `fill` on both sides up to `width` characters, with any odd character of
+// padding on the right
+fn center(
+    renderer: &Renderer,
+    name: &str,
+    filter: &Filter,
+    value: &str,
+    width: &str,
+    fill: &str,
+) -> Result<String, RenderError> {
+    let width = parse_width(filter, width)?;
+    let fill = parse_fill(filter, fill)?;
+
+    let padding = width.saturating_sub(value.chars().count());
+    let mut centered = padding_buffer(renderer, name, filter, value, width, fill)?;
+
+    centered.extend(std::iter::repeat_n(fill, padding / 2));
+    centered.push_str(value);
+    centered.extend(std::iter::repeat_n(fill, padding - padding / 2));
+
+    Ok(centered)
+}
+
+// An empty string with room for the value padded with `fill` up to `width` characters. A width
+// beyond the renderer's `max_value_len()` is a value too long, and a width too large to allocate
+// is an invalid argument, rather than a panic or running out of memory
+fn padding_buffer(
+    renderer: &Renderer,
+    name: &str,
+    filter: &Filter,
+    value: &str,
+    width: usize,
+    fill: char,
+) -> Result<String, RenderError> {
+    if renderer
+        .value_len_limit()
+        .is_some_and(|max_len| width > max_len)
+    {
+        return Err(RenderError::ValueTooLong {
+            name: name.to_string(),
+        });
+    }
+
+    let len = width
+        .saturating_sub(value.chars().count())
+        .checked_mul(fill.len_utf8())
+        .and_then(|padding| padding.checked_add(value.len()))
+        .ok_or_else(|| invalid_arguments(filter))?;
+
+    let mut buffer = String::new();
+
+    match buffer.try_reserve_exact(len) {
+        Ok(()) => Ok(buffer),
+        Err(_) => Err(invalid_arguments(filter)),
+    }
+}
+
+fn parse_width(filter: &Filter, width: &str) -> Result<usize, RenderError> {
+    width.parse().map_err(|_| invalid_arguments(filter))
+}
+
+fn parse_fill(filter: &Filter, fill: &str) -> Result<char, RenderError> {
+    let mut chars = fill.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(fill), None) => Ok(fill),
+        _ => Err(invalid_arguments(filter)),
+    }
+}
+
+fn invalid_arguments(filter: &Filter) -> RenderError {
+    RenderError::InvalidFilterArguments {
+        name: filter.name.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{render, RenderError, Renderer};
+
+    #[test]
+    fn pad() {
+        let values = [("short", "ab"), ("exact", "abcd"), ("long", "abcdef")];
+
+        assert!(
+            render("[{short|pad(4)}] [{exact|pad(4)}] [{long|pad(4)}]", &values)
+                == Ok(String::from("[  ab] [abcd] [abcdef]"))
+        );
+    }
+
+    #[test]
+    fn rpad() {
+        let values = [("short", "ab"), ("exact", "abcd"), ("long", "abcdef")];
+
+        assert!(
+            render(
+                "[{short|rpad(4)}] [{exact|rpad(4)}] [{long|rpad(4)}]",
+                &values
+            ) == Ok(String::from("[ab  ] [abcd] [abcdef]"))
+        );
+    }
+
+    #[test]
+    fn pad_fill() {
+        let values = [("code", "42"), ("name", "Homer")];
+
+        assert!(
+            render(r#"{code|pad(5, "0")} {name|rpad(8, ".")}"#, &values)
+                == Ok(String::from("00042 Homer..."))
+        );
+    }
+
+    #[test]
+    fn pad_counts_characters() {
+        assert!(render("[{name|pad(5)}]", &[("name", "Zoë")]) == Ok(String::from("[  Zoë]")));
+    }
+
+    #[test]
+    fn center() {
+        let values = [("odd", "abc"), ("even", "ab"), ("long", "abcdef")];
+
+        assert!(
+            render(
+                r#"[{odd|center(6)}] [{even|center(6, "*")}] [{long|center(4)}]"#,
+                &values
+            ) == Ok(String::from("[ abc  ] [**ab**] [abcdef]"))
+        );
+    }
+
+    #[test]
+    fn specs() {
+        let values = [("name", "Homer"), ("long", "Supercalifragilistic")];
+
+        for (before, after) in [
+            ("[{name:>8}]", "[   Homer]"),
+            ("[{name:<8}]", "[Homer   ]"),
+            ("[{name:8}]", "[Homer   ]"),
+            ("[{name:^8}]", "[ Homer  ]"),
+            ("[{name:*>8}]", "[***Homer]"),
+            ("[{name:-^9}]", "[--Homer--]"),
+            ("[{name:>>7}]", "[>>Homer]"),
+            ("[{name::<7}]", "[Homer::]"),
+            ("[{name|truncate(3):.>5}]", "[..Hom]"),
+            ("[{long:>8}]", "[Supercalifragilistic]"),
+            ("[{long:^0}]", "[Supercalifragilistic]"),
+        ] {
+            assert!(render(before, &values) == Ok(String::from(after)));
+        }
+    }
+
+    #[test]
+    fn not_specs() {
+        let values = [("name", "Homer")];
+
+        for before in [
+            "{name:}",
+            "{name:>}",
+            "{name:x}",
+            "{name:>8x}",
+            "{name :>8}",
+        ] {
+            assert!(render(before, &values) == Ok(String::from(before)));
+        }
+    }
+
+    #[test]
+    fn truncate() {
+        let values = [("short", "ab"), ("exact", "abcd"), ("long", "abcdef")];
+
+        assert!(
+            render(
+                "[{short|truncate(4)}] [{exact|truncate(4)}] [{long|truncate(4)}]",
+                &values
+            ) == Ok(String::from("[ab] [abcd] [abcd]"))
+        );
+        assert!(render("[{name|truncate(2)}]", &[("name", "Zoë")]) == Ok(String::from("[Zo]")));
+        assert!(render("[{name|truncate(3)}]", &[("name", "Zoë")]) == Ok(String::from("[Zoë]")));
+    }
+
+    #[test]
+    fn truncate_ellipsis() {
+        let values = [("short", "ab"), ("exact", "abcd"), ("long", "abcdef")];
+
+        assert!(
+            render(
+                r#"[{short|truncate(4, "…")}] [{exact|truncate(4, "…")}] [{long|truncate(4, "…")}]"#,
+                &values
+            ) == Ok(String::from("[ab] [abcd] [abc…]"))
+        );
+        assert!(render(r#"[{long|truncate(5, "...")}]"#, &values) == Ok(String::from("[ab...]")));
+        assert!(render(r#"[{long|truncate(2, "...")}]"#, &values) == Ok(String::from("[..]")));
+    }
+
+    #[test]
+    fn truncate_multibyte_boundary() {
+        let values = [("name", "Zoë Çelik"), ("emoji", "🍩🍩🍩")];
+
+        assert!(render(r#"[{name|truncate(4, "…")}]"#, &values) == Ok(String::from("[Zoë…]")));
+        assert!(render(r#"[{name|truncate(3, "…")}]"#, &values) == Ok(String::from("[Zo…]")));
+        assert!(render(r#"[{emoji|truncate(2, "…")}]"#, &values) == Ok(String::from("[🍩…]")));
+        assert!(render(r#"[{emoji|truncate(3, "…")}]"#, &values) == Ok(String::from("[🍩🍩🍩]")));
+    }
+
+    #[test]
+    fn chained() {
+        assert!(
+            render("[{name|truncate(3)|rpad(5)}]", &[("name", "Homer")])
+                == Ok(String::from("[Hom  ]"))
+        );
+    }
+
+    #[test]
+    fn json() {
+        let values = [
+            ("quote", r#"Say "D'oh""#),
+            ("path", r"C:\Users\Homer"),
+            ("lines", "Donuts\n\tDuff\r\n"),
+            ("control", "\u{0}\u{8}\u{c}\u{1f}"),
+            ("unicode", "Zoë 🍩"),
+        ];
+
+        for (name, after) in [
+            ("quote", r#"Say \"D'oh\""#),
+            ("path", r"C:\\Users\\Homer"),
+            ("lines", r"Donuts\n\tDuff\r\n"),
+            ("control", r"\u0000\b\f\u001f"),
+            ("unicode", "Zoë 🍩"),
+        ] {
+            assert!(render(&format!("{{{}|json}}", name), &values) == Ok(String::from(after)));
+            assert!(
+                render(&format!("{{{}|jsonstr}}", name), &values) == Ok(format!("\"{}\"", after))
+            );
+        }
+    }
+
+    #[test]
+    fn json_in_double_brace_template() {
+        let values = [("name", "Homer \"Max\" Simpson")];
+
+        assert!(
+            crate::render_double_brace(
+                r#"{"name": {{name|jsonstr}}, "raw": "{{name|json}}"}"#,
+                &values
+            ) == Ok(String::from(
+                r#"{"name": "Homer \"Max\" Simpson", "raw": "Homer \"Max\" Simpson"}"#
+            ))
+        );
+    }
+
+    #[cfg(feature = "regex-backend")]
+    #[test]
+    fn replace() {
+        let values = [
+            ("name", "  Mr.   Homer \t J.  Simpson "),
+            ("code", "abc-123-def"),
+        ];
+
+        assert!(
+            render(r#"[{name|replace(\s+, " ")}]"#, &values)
+                == Ok(String::from("[ Mr. Homer J. Simpson ]"))
+        );
+        assert!(
+            render(
+                r#"[{name|replace("^\s*Mr\.\s+", "")|replace("\s+", " ")}]"#,
+                &values
+            ) == Ok(String::from("[Homer J. Simpson ]"))
+        );
+        assert!(
+            render(r#"{code|replace("([a-z]+)-(\d+)", "$2:$1")}"#, &values)
+                == Ok(String::from("123:abc-def"))
+        );
+    }
+
+    #[cfg(feature = "regex-backend")]
+    #[test]
+    fn replace_invalid_arguments() {
+        for template in [r#"{name|replace("(", "")}"#, "{name|replace(x)}"] {
+            assert!(
+                Renderer::new().render(template, &[("name", "Homer")])
+                    == Err(RenderError::InvalidFilterArguments {
+                        name: String::from("replace")
+                    })
+            );
+        }
+    }
+
+    #[cfg(not(feature = "regex-backend"))]
+    #[test]
+    fn replace_needs_regex_backend() {
+        assert!(
+            Renderer::new().render(r#"{name|replace(x, y)}"#, &[("name", "Homer")])
+                == Err(RenderError::UnknownFilter {
+                    name: String::from("replace")
+                })
+        );
+    }
+
+    #[test]
+    fn yesno() {
+        for value in ["true", "TRUE", "1", "yes", "Yes"] {
+            assert!(render("{active|yesno}", &[("active", value)]) == Ok(String::from("Yes")));
+        }
+
+        for value in ["false", "False", "0", "no", "NO"] {
+            assert!(render("{active|yesno}", &[("active", value)]) == Ok(String::from("No")));
+        }
+    }
+
+    #[test]
+    fn yesno_labels() {
+        let renderer = Renderer::new().yes_no_labels("✓", "✗");
+
+        assert!(
+            renderer.render("{a|yesno} {b|yesno}", &[("a", "1"), ("b", "false")])
+                == Ok(String::from("✓ ✗"))
+        );
+    }
+
+    #[test]
+    fn yesno_unrecognized() {
+        for value in ["", "maybe", "2", " true"] {
+            assert!(
+                Renderer::new().render("{active|yesno}", &[("active", value)])
+                    == Err(RenderError::InvalidBoolean {
+                        value: String::from(value)
+                    })
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_filter() {
+        assert!(
+            Renderer::new().render("{name|shout}", &[("name", "Homer")])
+                == Err(RenderError::UnknownFilter {
+                    name: String::from("shout")
+                })
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn date() {
+        let renderer = Renderer::new();
+        let values = [
+            ("epoch", "0"),
+            ("unix", "1700000000"),
+            ("rfc", "2024-02-29T13:45:00+10:00"),
+            ("custom", "29/02/2024 13:45"),
+            ("day", "29/02/2024"),
+            ("bad", "yesterday"),
+        ];
+
+        for (template, after) in [
+            (
+                r#"{epoch|date("%Y-%m-%d %H:%M:%S")}"#,
+                "1970-01-01 00:00:00",
+            ),
+            (r#"{unix|date("%Y-%m-%d", unix)}"#, "2023-11-14"),
+            (
+                r#"{rfc|date("%d %b %Y %H:%M %:z")}"#,
+                "29 Feb 2024 13:45 +10:00",
+            ),
+            (r#"{rfc|date("%s", rfc3339)}"#, "1709178300"),
+            (
+                r#"{custom|date("%Y-%m-%dT%H:%M", "%d/%m/%Y %H:%M")}"#,
+                "2024-02-29T13:45",
+            ),
+            (r#"{day|date("%A", "%d/%m/%Y")}"#, "Thursday"),
+        ] {
+            assert!(renderer.render(template, &values) == Ok(String::from(after)));
+        }
+
+        for (template, value) in [
+            (r#"{bad|date("%Y")}"#, "yesterday"),
+            (r#"{epoch|date("%Y", rfc3339)}"#, "0"),
+            (r#"{rfc|date("%Y", unix)}"#, "2024-02-29T13:45:00+10:00"),
+        ] {
+            assert!(
+                renderer.render(template, &values)
+                    == Err(RenderError::InvalidDate {
+                        value: String::from(value)
+                    })
+            );
+        }
+
+        for template in ["{epoch|date}", r#"{epoch|date("%Q")}"#] {
+            assert!(
+                renderer.render(template, &values)
+                    == Err(RenderError::InvalidFilterArguments {
+                        name: String::from("date")
+                    })
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        for (template, filter) in [
+            ("{name|pad}", "pad"),
+            ("{name|pad(x)}", "pad"),
+            (r#"{name|rpad(4, "ab")}"#, "rpad"),
+            ("{name|truncate(1, 2, 3)}", "truncate"),
+            ("{name|yesno(Y, N)}", "yesno"),
+            ("{name|raw(x)}", "raw"),
+            ("{name|json(x)}", "json"),
+            ("{name|jsonstr(x)}", "jsonstr"),
+            ("{name|center}", "center"),
+            (r#"{name|center(4, "ab")}"#, "center"),
+        ] {
+            assert!(
+                Renderer::new().render(template, &[("name", "Homer")])
+                    == Err(RenderError::InvalidFilterArguments {
+                        name: String::from(filter)
+                    })
+            );
+        }
+    }
+
+    #[test]
+    fn oversized_widths() {
+        for (renderer, template, filter) in [
+            (Renderer::new(), "{name:18446744073709551615}", "rpad"),
+            (Renderer::new(), "{name|pad(9223372036854775807)}", "pad"),
+            (
+                Renderer::new(),
+                "{name|center(4611686018427387904)}",
+                "center",
+            ),
+        ] {
+            assert!(
+                renderer.render(template, &[("name", "Homer")])
+                    == Err(RenderError::InvalidFilterArguments {
+                        name: String::from(filter)
+                    })
+            );
+        }
+
+        let renderer = Renderer::new().max_value_len(8);
+
+        assert!(
+            renderer.render("{name|pad(18446744073709551615)}", &[("name", "Homer")])
+                == Err(RenderError::ValueTooLong {
+                    name: String::from("name")
+                })
+        );
+        assert!(
+            renderer.render("{name|pad(8)}", &[("name", "Homer")]) == Ok(String::from("   Homer"))
+        );
+    }
+}
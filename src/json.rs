@@ -0,0 +1,245 @@
+use crate::renderer::missing;
+use crate::{RenderError, Renderer, Template};
+use serde_json::Value;
+use std::borrow::Cow;
+
+/// Render the template with placeholder values taken from a JSON object
+///
+/// Each placeholder name is looked up as a key of the JSON object `values`. Strings are inserted
+/// as is, while numbers and booleans are inserted as their JSON text. Nulls, arrays, and objects
+/// can't be rendered as text and so are an error.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the JSON object containing placeholder values to replace within `template`. If it
+/// isn't an object, every placeholder is missing
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(RenderError::MissingPlaceholder { name, .. })` is the name of the placeholder missing
+/// from `values`
+///
+/// `Err(RenderError::UnrenderableValue { name })` is the name of the placeholder whose value is a
+/// null, array, or object
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_json;
+/// use serde_json::json;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1><p>Donuts eaten: {donuts}</p>");
+///   let values = json!({ "greet": "Hello", "name": "Homer", "donuts": 42 });
+///
+///   assert!(render_json(&template, &values)
+///     == Ok(String::from("<h1>Hello Homer</h1><p>Donuts eaten: 42</p>")));
+/// }
+/// ```
+pub fn render_json(template: &str, values: &Value) -> Result<String, RenderError> {
+    Renderer::new().render_template_with(&Template::parse(template), |name| {
+        match values.get(name) {
+            None => Err(missing(name)),
+            Some(Value::String(value)) => Ok(Cow::Borrowed(value.as_str())),
+            Some(Value::Number(value)) => Ok(Cow::Owned(value.to_string())),
+            Some(Value::Bool(value)) => Ok(Cow::Owned(value.to_string())),
+            Some(_) => Err(RenderError::UnrenderableValue {
+                name: name.to_string(),
+            }),
+        }
+    })
+}
+
+/// Check that every placeholder in the template is a renderable property in a JSON Schema
+///
+/// This catches drift between a template and the data contract its values come from. A
+/// placeholder is only renderable when it's a property of the schema whose `type` is `string`,
+/// `number`, `integer`, or `boolean`, or a list of only those types, as anything else could be a
+/// value that `render_json()` can't render. Placeholders with a default, or that are optional,
+/// aren't checked, though those within a default are.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `schema` is the JSON Schema of an object, with its properties under `properties`
+///
+/// # Returns
+///
+/// `Ok(())` when every placeholder is a renderable property
+///
+/// `Err(names)` is the sorted names of the placeholders that aren't
+///
+/// # Example
+///
+/// ```
+/// use placeholder::validate_against_schema;
+/// use serde_json::json;
+///
+/// fn main() {
+///   let schema = json!({
+///     "type": "object",
+///     "properties": {
+///       "name": { "type": "string" },
+///       "donuts": { "type": "integer" },
+///       "tags": { "type": "array" },
+///     },
+///   });
+///
+///   assert!(validate_against_schema("{name} ate {donuts} donuts", &schema) == Ok(()));
+///   assert!(validate_against_schema("{name} {surname} {tags}", &schema)
+///     == Err(vec![String::from("surname"), String::from("tags")]));
+/// }
+/// ```
+pub fn validate_against_schema(template: &str, schema: &Value) -> Result<(), Vec<String>> {
+    let invalid: Vec<String> = Template::parse(template)
+        .needed_names()
+        .into_iter()
+        .filter(|name| {
+            !schema
+                .get("properties")
+                .and_then(|properties| properties.get(name))
+                .and_then(|property| property.get("type"))
+                .is_some_and(is_scalar_type)
+        })
+        .map(String::from)
+        .collect();
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid)
+    }
+}
+
+// Whether a JSON Schema `type` only allows values that can be rendered as text
+fn is_scalar_type(kind: &Value) -> bool {
+    match kind {
+        Value::String(kind) => matches!(kind.as_str(), "string" | "number" | "integer" | "boolean"),
+        Value::Array(kinds) => !kinds.is_empty() && kinds.iter().all(is_scalar_type),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn scalars() {
+        let before = String::from("{start} {middle} {end} {count} {ok}");
+        let after = String::from("Hello beautiful world 3.5 true");
+
+        let values = json!({
+            "start": "Hello",
+            "middle": "beautiful",
+            "end": "world",
+            "count": 3.5,
+            "ok": true,
+        });
+
+        assert!(render_json(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn missing_value() {
+        let before = String::from("{start} {middle} world");
+        let values = json!({ "start": "Hello" });
+
+        assert!(
+            render_json(&before, &values)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("middle"),
+                    available: None,
+                })
+        );
+    }
+
+    #[test]
+    fn not_an_object() {
+        let before = String::from("{start} world");
+        let values = json!(["start"]);
+
+        assert!(
+            render_json(&before, &values)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("start"),
+                    available: None,
+                })
+        );
+    }
+
+    #[test]
+    fn unrenderable_values() {
+        let values = json!({ "list": [1, 2], "map": { "a": 1 }, "nothing": null });
+
+        for name in ["list", "map", "nothing"] {
+            assert!(
+                render_json(&format!("{{{}}}", name), &values)
+                    == Err(RenderError::UnrenderableValue {
+                        name: String::from(name),
+                    })
+            );
+        }
+    }
+
+    #[test]
+    fn schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "donuts": { "type": "integer" },
+                "weight": { "type": ["number", "string"] },
+                "member": { "type": "boolean" },
+                "nickname": { "type": ["string", "null"] },
+                "address": { "type": "object" },
+                "anything": {},
+            },
+        });
+
+        assert!(
+            validate_against_schema("{name} {donuts} {weight} {member} {name}", &schema) == Ok(())
+        );
+        assert!(validate_against_schema("Hello world", &schema) == Ok(()));
+        assert!(
+            validate_against_schema("{surname} {nickname} {address} {anything} {name}", &schema)
+                == Err(vec![
+                    String::from("address"),
+                    String::from("anything"),
+                    String::from("nickname"),
+                    String::from("surname"),
+                ])
+        );
+    }
+
+    #[test]
+    fn schema_defaults() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+            },
+        });
+
+        assert!(validate_against_schema("Dear {title=Customer} {name?}", &schema) == Ok(()));
+        assert!(
+            validate_against_schema("{title=Dear {surname}} {name}", &schema)
+                == Err(vec![String::from("surname")])
+        );
+    }
+
+    #[test]
+    fn schema_without_properties() {
+        assert!(
+            validate_against_schema("{name}", &json!({ "type": "object" }))
+                == Err(vec![String::from("name")])
+        );
+    }
+}
@@ -36,31 +36,258 @@
 //!     == Err(String::from("name")));
 //! }
 //! ```
+//!
+//! # Values are inserted verbatim
+//!
+//! Rendering is a single pass over the template, so placeholder values are never themselves
+//! rendered. A value containing placeholder syntax, such as `{b}`, renders as that literal text,
+//! even when there's a value for `b`. Only `Renderer::recursive()` renders values as templates of
+//! their own.
+//!
+//! ```
+//! use placeholder::render;
+//!
+//! fn main() {
+//!   let values = [("a", "{b}"), ("b", "Homer")];
+//!
+//!   assert!(render("{a}{b}", &values) == Ok(String::from("{b}Homer")));
+//! }
+//! ```
+//!
+//! # Escaping
+//!
+//! A placeholder directly after an opening brace, such as `{{name}`, is left as is. A backslash
+//! directly before a placeholder escapes the whole placeholder, filters and all, so `\{name}`
+//! renders as the literal text `{name}`, and `\{name|pad(8)}` as `{name|pad(8)}`. Escaping ends at
+//! the placeholder's closing brace, so whatever follows is unaffected, such as `\{name}{name}`
+//! rendering as `{name}` followed by the value of `name`. As only whole placeholders are escaped, a
+//! backslash before an opening brace that doesn't start a placeholder, such as in `\{{name}` or
+//! `\{`, is literal text. A pair of backslashes is a literal backslash, so `\\{name}` renders as a
+//! backslash followed by the value of `name`. Backslashes anywhere else are literal text, as are
+//! braces that aren't part of a placeholder, such as a lone `{` or `}`. A placeholder name can't be
+//! empty or only whitespace, so `{}`, `{ }`, and even the quoted `{" "}` are left as literal text
+//! rather than looked up.
+//!
+//! With custom delimiters set by `Renderer::delimiters()`, such as `<<` and `>>`, a doubled
+//! opening delimiter is a literal opening delimiter, so `<<<<name>>` renders as the literal text
+//! `<<name>>`.
+//!
+//! # Filters
+//!
+//! A placeholder can be followed by a chain of filters, applied in order to transform its value
+//! before it's inserted, such as `{code|truncate(8)|pad(10, "0")}`. Arguments are separated by
+//! commas, and can be quoted with double quotes to include commas and spaces. A filter that doesn't
+//! exist is an error, unless `Renderer::unknown_filter()` chooses to skip it.
+//!
+//! A placeholder name can be quoted with double quotes to include characters other than letters,
+//! digits, and underscores, such as `{"first name"}`. Quoted names never split on `|`, so
+//! `{"a|b"}` is the placeholder `a|b`, while `{a|b}` is the placeholder `a` with the filter `b`.
+//!
+//! * `pad(width)` or `pad(width, fill)` - pads the value on the left with spaces, or the `fill`
+//!   character, up to `width` characters. Values already that wide are left intact
+//! * `rpad(width)` or `rpad(width, fill)` - the same as `pad`, but pads on the right
+//! * `center(width)` or `center(width, fill)` - the same as `pad`, but pads on both sides, with
+//!   any odd character of padding on the right
+//! * `truncate(width)` or `truncate(width, ellipsis)` - cuts the value down to at most `width`
+//!   characters, ending with `ellipsis`, such as `"…"`, only when anything was cut. The ellipsis
+//!   counts towards `width`
+//! * `replace(pattern, replacement)` - replaces every match of the regular expression `pattern`
+//!   with `replacement`, where `$1` or `${name}` is a capture group. Both are ordinary arguments,
+//!   so they need quoting with double quotes to include commas, spaces, parentheses, or braces,
+//!   such as `{name|replace("\s+", " ")}`. Only available with the `regex-backend` feature
+//! * `date(format)` or `date(format, input)` - parses the value as a date and time, and formats it
+//!   with the strftime-style `format`, such as `{ts|date("%Y-%m-%d")}`. The value is read as
+//!   `input`, which is `unix` for seconds since the epoch, `rfc3339`, or a strftime-style format of
+//!   its own, and can be either of the first two when `input` isn't given. Unix timestamps are in
+//!   UTC. Only available with the `chrono` feature
+//! * `json` - escapes the value for use within a JSON string, escaping quotes, backslashes, and
+//!   control characters, without adding the surrounding quotes
+//! * `jsonstr` - the same as `json`, but with the surrounding quotes, making the value a whole JSON
+//!   string
+//! * `raw` - leaves the value as it is, and opts it out of escaping by `Renderer::auto_escape()`
+//! * `yesno` - renders a boolean value of `true`, `1`, or `yes` as `Yes`, and `false`, `0`, or
+//!   `no` as `No`, ignoring case. The labels can be changed with `Renderer::yes_no_labels()`
+//!
+//! ```
+//! use placeholder::render;
+//!
+//! fn main() {
+//!   let values = [("code", "42"), ("name", "Homer")];
+//!
+//!   assert!(render(r#"|{code|pad(5, "0")}|{name|rpad(8)}|"#, &values)
+//!     == Ok(String::from("|00042|Homer   |")));
+//! }
+//! ```
+//!
+//! # Format specs
+//!
+//! Borrowing from Rust's `format!()`, the filters can be followed by a format spec aligning the
+//! value within a width, such as `{name:>10}`. The spec is `:`, then an optional fill character
+//! other than a brace, which defaults to a space, then an optional alignment, then the width. The
+//! alignment is `<` to align left, which is the default, `>` to align right, or `^` to center, the
+//! same as the `rpad`, `pad`, and `center` filters. Values already that wide are left intact. The
+//! spec is applied after any filters, so `{name|truncate(8):*^10}` truncates the value before
+//! centering it.
+//!
+//! ```
+//! use placeholder::render;
+//!
+//! fn main() {
+//!   let values = [("code", "42"), ("name", "Homer")];
+//!
+//!   assert!(render("|{code:0>5}|{name:<8}|{name:*^9}|", &values)
+//!     == Ok(String::from("|00042|Homer   |**Homer**|")));
+//! }
+//! ```
+//!
+//! # Defaults
+//!
+//! The filters and any format spec can be followed by `=` then a default, which is rendered in
+//! place of the value when it's missing, such as `{title=Customer}`. A `?` instead makes the
+//! placeholder optional, so it renders as nothing when its value is missing, such as
+//! `{middle_name?}`. Filters and format specs apply to the default just as they would to the value,
+//! so `{count:>3=0}` renders `  0` when `count` is missing, while `Renderer::auto_escape()` leaves
+//! the default alone, as it's template text. A placeholder with a default, or an optional one, is
+//! never missing, so it's never an error and never reported by `render_collect()` and the like.
+//! Defaults can't contain lone braces, so they end at the placeholder's closing brace. The `=`
+//! comes straight after the name, filters, or format spec, so a `:` is always a format spec and
+//! text such as JSON's `{"a": 1}` or CSS's `{color: red}` is left as literal text.
+//!
+//! A default can contain placeholders of its own, such as `{greeting=Dear {name}}`, which are only
+//! looked up when the default is used. They're rendered just as any other placeholder, so a missing
+//! value within a default is an error unless `Renderer::on_missing()` allows it, and
+//! `render_collect()` reports it. Placeholders within a default are always written with single
+//! braces, whatever the template's own syntax. They can have defaults of their own, but only
+//! without braces, such as `{greeting={name=Customer}}`, so defaults expand at most two levels
+//! deep.
+//!
+//! ```
+//! use placeholder::render;
+//!
+//! fn main() {
+//!   let values = [("name", "Homer")];
+//!
+//!   assert!(render("{title=Mr} {first_name?}{name|rpad(6)=nobody}|{count:>3=0}", &values)
+//!     == Ok(String::from("Mr Homer |  0")));
+//!   assert!(render("{greeting=Dear {name}}", &values) == Ok(String::from("Dear Homer")));
+//! }
+//! ```
+//!
+//! # Blocks
+//!
+//! A section of the template can be included depending on a placeholder value, with
+//! `{#if name}...{/if}`, which includes its body when the value of `name` isn't empty. A section can
+//! also be repeated with `{#each name}...{/each}`, which includes its body once per line of the value
+//! of `name`, with `{name}` within the body being the current line. Blocks can be nested. A block
+//! whose value is missing is an error, unless `Renderer::on_missing()` allows missing values, in
+//! which case it's left out. `Renderer::missing_blocks_absent()` leaves such blocks out while other
+//! missing values are still errors. `analyze_blocks()` reports which blocks would be included
+//! without rendering anything.
+//!
+//! Tags without a matching opening or closing tag, and tags directly after an opening brace such as
+//! `{{#if name}`, are left as literal text. A backslash escapes a tag just like a placeholder, so
+//! `\{#if name}` is the literal text `{#if name}`. Blocks are only recognised in templates using
+//! the `{name}` syntax.
+//!
+//! ```
+//! use placeholder::render;
+//!
+//! fn main() {
+//!   let values = [("name", "Homer"), ("member", "yes"), ("items", "Donuts\nDuff")];
+//!
+//!   assert!(render("{#if member}Hi {name}! {/if}{#each items}[{items}]{/each}", &values)
+//!     == Ok(String::from("Hi Homer! [Donuts][Duff]")));
+//! }
+//! ```
+//!
+//! # Performance
+//!
+//! Rendering takes time linear in the length of the template plus the length of the output:
+//!
+//! * A template without any opening braces can't contain placeholders, so it's returned without
+//!   being parsed
+//! * The patterns used to find placeholders are compiled once per process, never per render
+//! * Each distinct placeholder is looked up once per render, however many times it's used, and each
+//!   distinct use of filters, such as `{price|pad(8)}`, runs once per render
+//! * Parsing is usually most of the cost of a render, so a template rendered many times should be
+//!   parsed once with `Template::parse()` or `Renderer::compile()` and rendered from there
+//!
+//! The benchmarks in `benches/` cover each of these, and are run with `cargo bench`.
+//!
+//! # Features
+//!
+//! * `chrono` - adds the `date` filter for formatting dates and times, using the `chrono` crate
+//! * `derive` - adds the `Placeholders` derive macro, for rendering with placeholder values taken
+//!   from a struct's fields with `render_struct()`
+//! * `json` - adds `render_json()` for rendering with placeholder values taken from a
+//!   `serde_json::Value` object, and `validate_against_schema()` for checking a template's
+//!   placeholders against a JSON Schema
+//! * `regex-backend` (default) - finds placeholders with the `regex` crate. Without it, a
+//!   hand-rolled scanner is used instead, so the `regex` and `lazy_static` dependencies can be
+//!   left out with `default-features = false`. Both backends accept exactly the same template
+//!   syntax and pass the same test suite, so switching between them doesn't change any output
+//! * `test-util` - adds `assert_renders()`, a test helper that checks a template renders to the
+//!   expected output, with a diff on mismatch
 
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::collections::HashMap;
+mod batch;
+mod blocks;
+mod diagnostics;
+mod error;
+mod filters;
+#[cfg(feature = "json")]
+mod json;
+mod matcher;
+mod python;
+mod renderer;
+mod template;
+#[cfg(feature = "test-util")]
+mod test_util;
+mod unicode;
+mod values;
 
-lazy_static! {
-    // Compile these once at startup
-    static ref MATCH_START: Regex = Regex::new(r"^[{](\w+)[}]").unwrap();
-    static ref MATCH_OTHER: Regex = Regex::new(r"[^{][{](\w+)[}]").unwrap();
-}
+pub use batch::{render_many_metrics, render_rows, BatchMetrics};
+pub use blocks::{analyze_blocks, BlockOutcome, BlockReport};
+pub use diagnostics::{render_diagnose, Diagnostics};
+pub use error::{CollectError, RenderError};
+#[cfg(feature = "json")]
+pub use json::{render_json, validate_against_schema};
+#[cfg(feature = "derive")]
+pub use placeholder_derive::Placeholders;
+pub use python::render_python;
+pub use renderer::{
+    CompiledTemplate, EscapeMode, Newline, OnMissing, OutputPiece, Renderer, UnknownFilter,
+};
+pub use template::{LongName, Template, TemplateSet, TemplateStats};
+#[cfg(feature = "test-util")]
+pub use test_util::assert_renders;
+pub use values::{AsValues, Lookup};
+
+use renderer::{missing, resolve_from, Collect, IoSink, LenSink, TooManyMissing};
+use template::{BlockKind, BlockTag, Filter, ParseOptions, Segment, Token, Visitor};
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::BuildHasher;
+use std::io;
 
 /// Render the template with placeholder values
 ///
+/// Rendering fails fast, stopping at the first missing placeholder. Use `render_collect()` instead
+/// to find every missing placeholder at once.
+///
 /// # Parameters
 ///
 /// `template` is the template text containing placeholders in the form `{name}`
 ///
-/// `values` is the HashMap containing placeholder values to replace within `template`
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
 ///
 /// # Returns
 ///
 /// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
 /// placeholder values
 ///
-/// `Err(name)` is the name of the placeholder missing from `values`
+/// `Err(name)` is the name of the first placeholder missing from `values`
 ///
 /// # Example 1
 ///
@@ -98,209 +325,3778 @@ lazy_static! {
 ///     == Err(String::from("name")));
 /// }
 /// ```
-pub fn render(template: &str, values: &HashMap<String, String>) -> Result<String, String> {
-    // Instead of a doing this all within a single regular expression, we split it into two so that
-    // we're not branching per iteration for a possible "start of string" placeholder
-
-    let mut output = match MATCH_START.captures(template) {
-        None => template.to_string(),
-        Some(capture) => match capture.get(1) {
-            None => panic!("at the disco"),
-            Some(key) => match values.get(key.as_str()) {
-                None => return Err(key.as_str().to_string()),
-                Some(value) => Regex::new(format!("^[{{]{}[}}]", key.as_str()).as_str())
-                    .unwrap()
-                    .replace(template, value)
-                    .to_string(),
-            },
-        },
-    };
+pub fn render<T: AsValues>(template: &str, values: T) -> Result<String, String> {
+    render_cow(template, values).map(Cow::into_owned)
+}
 
-    loop {
-        match MATCH_OTHER.captures(&output) {
-            None => break,
-            Some(capture) => match capture.get(1) {
-                None => panic!("at the disco"),
-                Some(key) => match values.get(key.as_str()) {
-                    None => return Err(key.as_str().to_string()),
-                    Some(value) => {
-                        output =
-                            Regex::new(format!("([^{{])[{{]{}[}}]", key.as_str()).as_str())
-                                .unwrap()
-                                .replace(&output, format!("${{1}}{}", value))
-                                .to_string()
-                    }
-                },
-            },
+/// Render the template with placeholder values, failing with a structured error
+///
+/// This renders exactly like `render()`, but fails with a `RenderError` rather than a `String`,
+/// easing a migration to matching on errors. `render()` is left as it is, and its error is always
+/// the structured error's `to_string()`. In particular, a missing placeholder is
+/// `RenderError::MissingPlaceholder { name, .. }`, and its `to_string()` is just `name`.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(error)` is why the template couldn't be rendered, such as
+/// `RenderError::MissingPlaceholder { name, .. }` with the name of the first placeholder missing
+/// from `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{render, render_structured, RenderError};
+///
+/// fn main() {
+///   let values = [("greet", "Hello")];
+///   let error = render_structured("<h1>{greet} {name}</h1>", &values).unwrap_err();
+///
+///   assert!(error
+///     == RenderError::MissingPlaceholder { name: String::from("name"), available: None });
+///   assert!(Err(error.to_string()) == render("<h1>{greet} {name}</h1>", &values));
+/// }
+/// ```
+pub fn render_structured<T: AsValues>(template: &str, values: T) -> Result<String, RenderError> {
+    if !template.contains('{') {
+        return Ok(template.to_string());
+    }
+
+    Template::parse(template).render(values)
+}
+
+/// Render the template with placeholder values, borrowing the template when it's left unchanged
+///
+/// A template without any opening braces can't contain placeholders, so it's returned as is
+/// without being scanned or copied. This suits pipelines where most text is static.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values. It's borrowed from `template` when there aren't any placeholders
+///
+/// `Err(name)` is the name of the first placeholder missing from `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_cow;
+/// use std::borrow::Cow;
+///
+/// fn main() {
+///   assert!(render_cow("<h1>Hello</h1>", &[("name", "Homer")])
+///     == Ok(Cow::Borrowed("<h1>Hello</h1>")));
+///   assert!(render_cow("<h1>{name}</h1>", &[("name", "Homer")])
+///     == Ok(Cow::Owned(String::from("<h1>Homer</h1>"))));
+/// }
+/// ```
+pub fn render_cow<'t, T: AsValues>(template: &'t str, values: T) -> Result<Cow<'t, str>, String> {
+    if !template.contains('{') {
+        return Ok(Cow::Borrowed(template));
+    }
+
+    Template::parse(template)
+        .render(values)
+        .map(Cow::Owned)
+        .map_err(|error| error.to_string())
+}
+
+/// Render the template with placeholder values, collecting every missing placeholder on failure
+///
+/// Unlike `render()`, which fails fast at the first missing placeholder, rendering carries on past
+/// missing placeholders so that the whole template is checked in a single pass. This suits
+/// validating a template against its values, such as reporting every missing field at once.
+///
+/// Only placeholders that are looked up can be missing. Escaped placeholders, and placeholders
+/// within an `{#if name}` block that's left out, are never reported, while the name of a block
+/// whose value is missing is. A placeholder with an inline default, such as `{title=Customer}`, or
+/// that's optional, such as `{middle_name?}`, is never reported, as it's rendered with its default
+/// instead. Use `render_with_defaults()` to fall back to default values given separately.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(CollectError::Missing(names))` is the names of all the placeholders missing from `values`,
+/// each listed once in the order they first appear in `template`
+///
+/// `Err(CollectError::Render(error))` is any other failure, such as
+/// `RenderError::UnknownFilter { name }`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{render_collect, CollectError};
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1><p>Do you like {food}, {name}?</p>");
+///
+///   let mut values = HashMap::new();
+///   values.insert(String::from("greet"), String::from("Hello"));
+///
+///   assert!(render_collect(&template, &values)
+///     == Err(CollectError::Missing(vec![String::from("name"), String::from("food")])));
+/// }
+/// ```
+pub fn render_collect<T: AsValues>(
+    template: &str,
+    values: T,
+) -> Result<String, CollectError<Vec<String>>> {
+    let template = Template::parse(template);
+    let mut collect = Collect::new(usize::MAX);
+
+    // Missing values are rendered as nothing, leaving out any block whose value is missing
+    let output = Renderer::new()
+        .on_missing(OnMissing::Empty)
+        .render_template_to(&template, resolve_from(&values), &mut collect);
+
+    match output {
+        Ok(Ok(())) if collect.names.is_empty() => Ok(collect.output),
+        Ok(Ok(())) | Err(TooManyMissing) => Err(CollectError::Missing(collect.names)),
+        Ok(Err(error)) => Err(CollectError::Render(error)),
+    }
+}
+
+/// Render the template with placeholder values, collecting up to `max_errors` missing placeholders
+/// on failure
+///
+/// This is `render_collect()` with a cap, for huge templates where thousands of missing
+/// placeholders would only be noise. Rendering stops at the first missing placeholder beyond the
+/// cap.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// `max_errors` is the most missing placeholders to collect
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(CollectError::Missing((names, truncated)))` is the names of the placeholders missing from
+/// `values`, each listed once in the order they first appear in `template`, along with whether more
+/// were missing beyond the first `max_errors`
+///
+/// `Err(CollectError::Render(error))` is any other failure, such as
+/// `RenderError::UnknownFilter { name }`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{render_collect_max, CollectError};
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1><p>Do you like {food}, {name}?</p>");
+///
+///   assert!(render_collect_max(&template, &[("greet", "Hello")], 1)
+///     == Err(CollectError::Missing((vec![String::from("name")], true))));
+///   assert!(render_collect_max(&template, &[("greet", "Hello")], 2)
+///     == Err(CollectError::Missing((vec![String::from("name"), String::from("food")], false))));
+/// }
+/// ```
+pub fn render_collect_max<T: AsValues>(
+    template: &str,
+    values: T,
+    max_errors: usize,
+) -> Result<String, CollectError<(Vec<String>, bool)>> {
+    let template = Template::parse(template);
+    let mut collect = Collect::new(max_errors);
+
+    // Missing values are rendered as nothing, leaving out any block whose value is missing
+    let output = Renderer::new()
+        .on_missing(OnMissing::Empty)
+        .render_template_to(&template, resolve_from(&values), &mut collect);
+
+    match output {
+        Ok(Ok(())) if collect.names.is_empty() => Ok(collect.output),
+        Ok(Ok(())) => Err(CollectError::Missing((collect.names, false))),
+        Err(TooManyMissing) => Err(CollectError::Missing((collect.names, true))),
+        Ok(Err(error)) => Err(CollectError::Render(error)),
+    }
+}
+
+/// Render the template with placeholder values from namespaced keys
+///
+/// Each placeholder name has `prefix` prepended before it's looked up, so with a prefix of
+/// `"user."`, `{name}` is replaced with the value of `user.name`. This lets one template render
+/// different kinds of records by swapping the prefix.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`), with namespaced keys
+///
+/// `prefix` is prepended to each placeholder name to give the key of its value
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(name)` is the name of the first placeholder missing from `values`, without the prefix
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_with_prefix_stripping;
+///
+/// fn main() {
+///   let template = String::from("<h1>{name}</h1><p>{email}</p>");
+///   let values = [
+///     ("user.name", "Homer"),
+///     ("user.email", "homer@example.com"),
+///     ("pet.name", "Santa's Little Helper"),
+///   ];
+///
+///   assert!(render_with_prefix_stripping(&template, &values, "user.")
+///     == Ok(String::from("<h1>Homer</h1><p>homer@example.com</p>")));
+/// }
+/// ```
+pub fn render_with_prefix_stripping<T: AsValues>(
+    template: &str,
+    values: T,
+    prefix: &str,
+) -> Result<String, String> {
+    let mut key = String::from(prefix);
+
+    Renderer::new()
+        .render_template_with(&Template::parse(template), |name| {
+            key.truncate(prefix.len());
+            key.push_str(name);
+
+            match values.get_value(&key) {
+                None => Err(missing(name)),
+                Some(value) => Ok(value),
+            }
+        })
+        .map_err(|error| error.to_string())
+}
+
+/// Render the template, indenting every line of the output by `spaces` spaces
+///
+/// This suits embedding the output within a larger indented document, such as YAML or code, as
+/// lines begun by newlines within placeholder values are indented too. Blank lines are left blank
+/// rather than indented, so the output has no trailing whitespace, and nothing is added after a
+/// trailing newline.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// `spaces` is the number of spaces to indent each line by
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values, and each line indented
+///
+/// `Err(name)` is the name of the first placeholder missing from `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_indent;
+///
+/// fn main() {
+///   let template = String::from("name: {name}\nscript: |\n  {script}\n");
+///
+///   assert!(render_indent(&template, &[("name", "build"), ("script", "make\n\n  make test")], 4)
+///     == Ok(String::from("    name: build\n    script: |\n      make\n\n      make test\n")));
+/// }
+/// ```
+pub fn render_indent<T: AsValues>(
+    template: &str,
+    values: T,
+    spaces: usize,
+) -> Result<String, String> {
+    let output = render_cow(template, values)?;
+    let indent = " ".repeat(spaces);
+
+    let mut indented = String::with_capacity(output.len());
+
+    for line in output.split_inclusive('\n') {
+        if line != "\n" && line != "\r\n" {
+            indented.push_str(&indent);
+        }
+
+        indented.push_str(line);
+    }
+
+    Ok(indented)
+}
+
+/// Render the template, wrapping each line of the output at `width` columns
+///
+/// This suits plain text such as emails and reports. Existing newlines are kept, and only lines
+/// longer than `width` characters are wrapped, by breaking them at spaces so that words aren't
+/// split. A wrapped line has its runs of spaces collapsed to one, and loses the spaces it's broken
+/// at. A word longer than `width` by itself is never split, and is put on a line of its own
+/// instead.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// `width` is the number of characters to wrap lines at
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values, and its long lines wrapped
+///
+/// `Err(name)` is the name of the first placeholder missing from `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_wrapped;
+///
+/// fn main() {
+///   let template = String::from("Dear {name},\n{body}\n");
+///   let values = [("name", "Homer"), ("body", "Your donuts are ready for collection")];
+///
+///   assert!(render_wrapped(&template, &values, 16)
+///     == Ok(String::from("Dear Homer,\nYour donuts are\nready for\ncollection\n")));
+/// }
+/// ```
+pub fn render_wrapped<T: AsValues>(
+    template: &str,
+    values: T,
+    width: usize,
+) -> Result<String, String> {
+    let output = render_cow(template, values)?;
+    let mut wrapped = String::with_capacity(output.len());
+
+    for (index, line) in output.split('\n').enumerate() {
+        if index > 0 {
+            wrapped.push('\n');
+        }
+
+        if line.chars().count() <= width {
+            wrapped.push_str(line);
+            continue;
         }
+
+        let mut column = 0;
+
+        for word in line.split(' ').filter(|word| !word.is_empty()) {
+            let len = word.chars().count();
+
+            if column > 0 && column + 1 + len > width {
+                wrapped.push('\n');
+                column = 0;
+            } else if column > 0 {
+                wrapped.push(' ');
+                column += 1;
+            }
+
+            wrapped.push_str(word);
+            column += len;
+        }
+    }
+
+    Ok(wrapped)
+}
+
+/// Render the template with positional and named placeholder values mixed, like Python's
+/// `str.format()`
+///
+/// A placeholder whose name is all ASCII digits, such as `{0}`, is positional and replaced with
+/// that element of `args`, counting from zero. Any other placeholder is named and looked up in
+/// `values`, so a name such as `{2nd}` is still named.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{0}` or `{name}`
+///
+/// `args` is the positional placeholder values
+///
+/// `values` is the named placeholder values, such as a HashMap (see `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(name)` is the name of the first placeholder missing a value, which is the index of a
+/// positional placeholder past the end of `args`, or the name of a named placeholder missing from
+/// `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_mixed;
+///
+/// fn main() {
+///   let template = String::from("{0} {name}, you owe {1} donuts");
+///   let values = [("name", "Homer")];
+///
+///   assert!(render_mixed(&template, &["Hello", "42"], &values)
+///     == Ok(String::from("Hello Homer, you owe 42 donuts")));
+///   assert!(render_mixed(&template, &["Hello"], &values) == Err(String::from("1")));
+/// }
+/// ```
+pub fn render_mixed<T: AsValues>(
+    template: &str,
+    args: &[&str],
+    values: T,
+) -> Result<String, String> {
+    Renderer::new()
+        .render_template_with(&Template::parse(template), |name| {
+            let value = if name.bytes().all(|byte| byte.is_ascii_digit()) {
+                name.parse::<usize>()
+                    .ok()
+                    .and_then(|index| args.get(index))
+                    .map(|arg| Cow::Borrowed(*arg))
+            } else {
+                values.get_value(name)
+            };
+
+            match value {
+                None => Err(missing(name)),
+                Some(value) => Ok(value),
+            }
+        })
+        .map_err(|error| error.to_string())
+}
+
+/// Render the template, resolving placeholders whose names are themselves built from a placeholder
+///
+/// A placeholder such as `{col_{n}}` has its inner placeholder `{n}` resolved first, and the value
+/// of the placeholder named by the result, such as `col_3` when `n` is `3`, is inserted in its
+/// place. Only a single level of nesting is resolved, unlike `Renderer::recursive()`, which renders
+/// placeholders within values: the inner placeholder is a plain name, and the value looked up with
+/// the composed name is inserted verbatim. Nested placeholders are escaped, and can be used within
+/// blocks, just like any other placeholder, while any other placeholders are rendered as with
+/// `render()`.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`, or
+/// `{prefix{name}suffix}` where the placeholder name is composed, with an optional suffix
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(name)` is the name of the first placeholder missing from `values`, which for a nested
+/// placeholder is the composed name when the inner placeholder has a value
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_nested;
+///
+/// fn main() {
+///   let template = String::from("{name}: {col_{n}}");
+///   let values = [("name", "Homer"), ("n", "3"), ("col_1", "Donuts"), ("col_3", "Duff")];
+///
+///   assert!(render_nested(&template, &values) == Ok(String::from("Homer: Duff")));
+///   assert!(render_nested(&template, &[("name", "Homer"), ("n", "2")])
+///     == Err(String::from("col_2")));
+/// }
+/// ```
+pub fn render_nested<T: AsValues>(template: &str, values: T) -> Result<String, String> {
+    let options = ParseOptions {
+        nested: true,
+        ..ParseOptions::default()
+    };
+
+    let template =
+        Template::parse_with_options(template, &options).map_err(|error| error.to_string())?;
+
+    Renderer::new()
+        .render_template_with(&template, resolve_from(&values))
+        .map_err(|error| error.to_string())
+}
+
+/// Render as much of the template as possible, listing the placeholders left unresolved
+///
+/// This suits previewing a template while its values are still being filled in. Each placeholder
+/// missing from `values`, or whose filters fail, is left exactly as it's written in the template,
+/// while a block whose value is missing is left out.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `(output, unresolved)` is the template text with its resolved placeholders replaced with their
+/// corresponding placeholder values, along with the names of the unresolved placeholders, each
+/// listed once in the order they first appear in `template`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_preview;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1><p>Do you like {food}, {name}?</p>");
+///
+///   assert!(render_preview(&template, &[("greet", "Hello")])
+///     == (
+///       String::from("<h1>Hello {name}</h1><p>Do you like {food}, {name}?</p>"),
+///       vec![String::from("name"), String::from("food")],
+///     ));
+/// }
+/// ```
+pub fn render_preview<T: AsValues>(template: &str, values: T) -> (String, Vec<String>) {
+    let mut preview = Preview {
+        renderer: &Renderer::new(),
+        output: String::new(),
+        unresolved: Vec::new(),
+    };
+
+    template::visit(
+        Template::parse(template).segments(),
+        &|name| values.get_value(name).map(Cow::into_owned),
+        &mut preview,
+    );
+
+    (preview.output, preview.unresolved)
+}
+
+// Renders the placeholders and blocks that resolve, keeping the rest as they are
+struct Preview<'r> {
+    renderer: &'r Renderer,
+    output: String,
+    unresolved: Vec<String>,
+}
+
+impl Preview<'_> {
+    fn unresolved(&mut self, name: &str) {
+        if !self.unresolved.iter().any(|unresolved| unresolved == name) {
+            self.unresolved.push(name.to_string());
+        }
+    }
+}
+
+impl Visitor for Preview<'_> {
+    fn literal(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn placeholder(
+        &mut self,
+        name: &str,
+        filters: &[Filter],
+        source: &str,
+        _start: usize,
+        value: Option<String>,
+    ) {
+        let value = value.map(|value| {
+            filters.iter().try_fold(value, |value, filter| {
+                filters::apply(self.renderer, name, filter, &value)
+            })
+        });
+
+        match value {
+            Some(Ok(value)) => self.output.push_str(&value),
+            _ => {
+                self.output.push_str(source);
+                self.unresolved(name);
+            }
+        }
+    }
+
+    // The default is the value when everything within it resolves
+    fn defaulted(
+        &mut self,
+        name: &str,
+        filters: &[Filter],
+        source: &str,
+        start: usize,
+        default: &[Segment],
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) {
+        let mut preview = Preview {
+            renderer: self.renderer,
+            output: String::new(),
+            unresolved: Vec::new(),
+        };
+
+        template::visit(default, lookup, &mut preview);
+
+        if preview.unresolved.is_empty() {
+            self.placeholder(name, filters, source, start, Some(preview.output));
+        } else {
+            self.output.push_str(source);
+
+            for other in preview.unresolved {
+                self.unresolved(&other);
+            }
+        }
+    }
+
+    fn missing_block(
+        &mut self,
+        _kind: BlockKind,
+        name: &str,
+        _body: &[Segment],
+        _start: usize,
+        _lookup: &dyn Fn(&str) -> Option<String>,
+    ) {
+        self.unresolved(name);
+    }
+}
+
+/// Render the template with placeholder values, collecting where every missing placeholder is on
+/// failure
+///
+/// This is `render_collect()` with the position of each missing placeholder, such as for editor
+/// tooling to mark every unfilled placeholder at once. Every occurrence of a missing placeholder is
+/// listed, rather than each distinct name once.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(CollectError::Missing(missing))` is the name and byte offset within `template` of every
+/// occurrence of a placeholder missing from `values`, in the order rendering reaches them. A block
+/// whose value is missing is listed at the offset of its opening tag, while its body is left out.
+/// A placeholder within an `{#each name}` block is listed once for each iteration it's missing in
+///
+/// `Err(CollectError::Render(error))` is any other failure, such as
+/// `RenderError::UnknownFilter { name }`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{render_collect_offsets, CollectError};
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1><p>Bye {name}</p>");
+///
+///   assert!(render_collect_offsets(&template, &[("greet", "Hello")])
+///     == Err(CollectError::Missing(vec![(String::from("name"), 12), (String::from("name"), 30)])));
+/// }
+/// ```
+pub fn render_collect_offsets<T: AsValues>(
+    template: &str,
+    values: T,
+) -> Result<String, CollectError<Vec<(String, usize)>>> {
+    let template = Template::parse(template);
+    let mut collect = Collect::new(usize::MAX);
+
+    // Missing values are rendered as nothing, leaving out any block whose value is missing
+    let output = Renderer::new()
+        .on_missing(OnMissing::Empty)
+        .render_template_to(&template, resolve_from(&values), &mut collect);
+
+    match output {
+        Ok(Ok(())) if collect.missing.is_empty() => Ok(collect.output),
+        Ok(Ok(())) | Err(TooManyMissing) => Err(CollectError::Missing(collect.missing)),
+        Ok(Err(error)) => Err(CollectError::Render(error)),
+    }
+}
+
+/// Render the template with placeholder values, falling back to default values
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// `defaults` is the placeholder values used for placeholders missing from `values`
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values, or default values
+///
+/// `Err(name)` is the name of the first placeholder missing from both `values` and `defaults`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_with_defaults;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1>");
+///
+///   let values = [("name", "Homer")];
+///   let defaults = [("greet", "Hello"), ("name", "you")];
+///
+///   assert!(render_with_defaults(&template, &values, &defaults)
+///     == Ok(String::from("<h1>Hello Homer</h1>")));
+/// }
+/// ```
+pub fn render_with_defaults<T: AsValues, D: AsValues>(
+    template: &str,
+    values: T,
+    defaults: D,
+) -> Result<String, String> {
+    Renderer::new()
+        .render_template_with(&Template::parse(template), |name| {
+            match values.get_value(name).or_else(|| defaults.get_value(name)) {
+                None => Err(missing(name)),
+                Some(value) => Ok(value),
+            }
+        })
+        .map_err(|error| error.to_string())
+}
+
+/// Render the template with placeholder values, falling back to default values that can refer to
+/// other placeholders
+///
+/// This is `render_with_defaults()`, but each default value is a template of its own, so a default
+/// such as `Hello {name}` is rendered with the value of `name` when it's used. References within a
+/// default follow the same rules as the template's own placeholders: they're looked up in
+/// `values` first, then in `defaults`, and it's an error when they're missing from both. Values
+/// taken from `values` are still inserted verbatim. A default can refer to another default, up to
+/// 8 defaults deep, which also stops defaults that refer to each other from expanding forever.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// `defaults` is the templates rendered as the values of placeholders missing from `values`
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values, or rendered default values
+///
+/// `Err(name)` is the name of the first placeholder missing from both `values` and `defaults`,
+/// including within a default, or `RenderError::RecursionLimit`'s message when defaults are
+/// nested more than 8 deep
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_with_nested_defaults;
+///
+/// fn main() {
+///   let defaults = [("title", "{greet} {name}"), ("greet", "Hello")];
+///
+///   assert!(render_with_nested_defaults("<h1>{title}</h1>", &[("name", "Homer")], &defaults)
+///     == Ok(String::from("<h1>Hello Homer</h1>")));
+///   assert!(render_with_nested_defaults("<h1>{title}</h1>", &[("greet", "Hi")], &defaults)
+///     == Err(String::from("name")));
+/// }
+/// ```
+pub fn render_with_nested_defaults<T: AsValues, D: AsValues>(
+    template: &str,
+    values: T,
+    defaults: D,
+) -> Result<String, String> {
+    // The deepest a default is rendered within other defaults
+    const MAX_DEPTH: usize = 8;
+
+    fn resolve<'v, T: AsValues, D: AsValues>(
+        name: &str,
+        values: &'v T,
+        defaults: &'v D,
+        depth: usize,
+    ) -> Result<Cow<'v, str>, RenderError> {
+        if let Some(value) = values.get_value(name) {
+            return Ok(value);
+        }
+
+        let default = defaults.get_value(name).ok_or_else(|| missing(name))?;
+
+        if !default.contains('{') {
+            return Ok(default);
+        }
+
+        if depth == MAX_DEPTH {
+            return Err(RenderError::RecursionLimit {
+                name: name.to_string(),
+            });
+        }
+
+        Renderer::new()
+            .render_template_with(&Template::parse(&default), |other| {
+                resolve(other, values, defaults, depth + 1)
+            })
+            .map(Cow::Owned)
+    }
+
+    Renderer::new()
+        .render_template_with(&Template::parse(template), |name| {
+            resolve(name, &values, &defaults, 0)
+        })
+        .map_err(|error| error.to_string())
+}
+
+/// Render the template with placeholder values, falling back to default values, and report which
+/// placeholders used their default
+///
+/// This is `render_with_defaults()` for analytics, such as finding placeholders that always fall
+/// back to their default and could be removed from the template.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// `defaults` is the placeholder values used for placeholders missing from `values`
+///
+/// # Returns
+///
+/// `Ok((output, defaulted))` is the template text with all its placeholders replaced with their
+/// corresponding placeholder values, or default values, along with the names of the placeholders
+/// that used their default, each listed once in the order they first appear in `template`
+///
+/// `Err(name)` is the name of the first placeholder missing from both `values` and `defaults`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_with_defaults_report;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1>");
+///
+///   let values = [("name", "Homer")];
+///   let defaults = [("greet", "Hello"), ("name", "you")];
+///
+///   assert!(render_with_defaults_report(&template, &values, &defaults)
+///     == Ok((String::from("<h1>Hello Homer</h1>"), vec![String::from("greet")])));
+/// }
+/// ```
+pub fn render_with_defaults_report<T: AsValues, D: AsValues>(
+    template: &str,
+    values: T,
+    defaults: D,
+) -> Result<(String, Vec<String>), String> {
+    let mut defaulted = Vec::<String>::new();
+
+    let output = Renderer::new().render_template_with(&Template::parse(template), |name| {
+        if let Some(value) = values.get_value(name) {
+            return Ok(value);
+        }
+
+        match defaults.get_value(name) {
+            None => Err(missing(name)),
+            Some(value) => {
+                if !defaulted.iter().any(|defaulted| defaulted == name) {
+                    defaulted.push(name.to_string());
+                }
+
+                Ok(value)
+            }
+        }
+    });
+
+    output
+        .map(|output| (output, defaulted))
+        .map_err(|error| error.to_string())
+}
+
+/// Render the template with placeholder values taken from a struct's fields
+///
+/// This is `render()` for a struct deriving `Placeholders` with the `derive` feature, or anything
+/// else implementing `Lookup`, where each `{field}` placeholder is replaced with the field's value.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the struct containing placeholder values to replace within `template`
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(name)` is the name of the first placeholder missing from `values`
+pub fn render_struct<L: Lookup + ?Sized>(template: &str, values: &L) -> Result<String, String> {
+    render(template, values)
+}
+
+/// Render the template with placeholder values taken from layers of sources
+///
+/// Each placeholder's value is taken from the first source that has it, so earlier sources
+/// override later ones. The sources can be of different kinds, such as a `HashMap` of overrides,
+/// then a source reading environment variables, then a closure computing fallbacks (see
+/// `Lookup`).
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `sources` is the sources of placeholder values, in the order they're tried
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(name)` is the name of the first placeholder missing from every source
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_layered;
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///   let mut overrides = HashMap::new();
+///   overrides.insert("name", "Homer");
+///
+///   let defaults = [("greet", "Hello"), ("name", "you")];
+///   let fallback = |name: &str| (name == "food").then(|| String::from("Donuts"));
+///
+///   assert!(render_layered("{greet} {name}, {food}?", &[&overrides, &defaults, &fallback])
+///     == Ok(String::from("Hello Homer, Donuts?")));
+/// }
+/// ```
+pub fn render_layered(template: &str, sources: &[&dyn Lookup]) -> Result<String, String> {
+    render(template, sources)
+}
+
+/// Render the template with placeholder values computed on demand by a closure
+///
+/// Only what the template uses is computed: `resolve` is called exactly once for each distinct
+/// placeholder name in the template, in the order the names first appear, and never for any other
+/// name. Each value is reused for repeats of its placeholder. This makes it cheaper than building
+/// a map of every value that might be needed when values are expensive to compute, such as when
+/// each one is a database query.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `resolve` computes the value of the placeholder called `name`, or `None` when it's missing
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(name)` is the name of the first placeholder `resolve` returned `None` for
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_fn;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1><p>Bye {name}</p>");
+///   let mut calls = Vec::new();
+///
+///   let output = render_fn(&template, |name| {
+///     calls.push(name.to_string());
+///     Some(name.to_uppercase())
+///   });
+///
+///   assert!(output == Ok(String::from("<h1>GREET NAME</h1><p>Bye NAME</p>")));
+///   assert!(calls == vec![String::from("greet"), String::from("name")]);
+/// }
+/// ```
+pub fn render_fn<F>(template: &str, mut resolve: F) -> Result<String, String>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut resolved: HashMap<String, String> = HashMap::new();
+
+    Renderer::new()
+        .render_template_with(&Template::parse(template), |name| {
+            if let Some(value) = resolved.get(name) {
+                return Ok(Cow::Owned(value.clone()));
+            }
+
+            match resolve(name) {
+                None => Err(missing(name)),
+                Some(value) => {
+                    resolved.insert(name.to_string(), value.clone());
+                    Ok(Cow::Owned(value))
+                }
+            }
+        })
+        .map_err(|error| error.to_string())
+}
+
+/// Render the template with placeholder values computed on demand by thunks
+///
+/// This is `render_fn()` with a map of closures rather than a single one: a thunk only runs when
+/// its placeholder appears in the template, and at most once per render, however many times the
+/// placeholder repeats. Thunks for placeholders not in the template never run.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `thunks` is the closures computing each placeholder value, keyed by placeholder name
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(name)` is the name of the first placeholder missing from `thunks`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_thunks;
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///   let template = String::from("<h1>Hello {name}</h1><p>Bye {name}</p>");
+///
+///   let mut thunks: HashMap<String, Box<dyn Fn() -> String>> = HashMap::new();
+///   thunks.insert(String::from("name"), Box::new(|| String::from("Homer")));
+///   thunks.insert(String::from("report"), Box::new(|| unreachable!()));
+///
+///   assert!(render_thunks(&template, &thunks)
+///     == Ok(String::from("<h1>Hello Homer</h1><p>Bye Homer</p>")));
+/// }
+/// ```
+pub fn render_thunks<S: BuildHasher>(
+    template: &str,
+    thunks: &HashMap<String, Box<dyn Fn() -> String>, S>,
+) -> Result<String, String> {
+    render_fn(template, |name| thunks.get(name).map(|thunk| thunk()))
+}
+
+/// Render the template with placeholder values, writing the output as it's rendered
+///
+/// Literal text and placeholder values are written to `writer` as UTF-8 bytes as soon as they're
+/// reached, rather than building up the whole output in memory first. This suits large outputs
+/// streamed to a file or the network. Use `render_atomic()` instead when partial output can't be
+/// tolerated.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// `writer` is where the output is written
+///
+/// # Returns
+///
+/// The outer result is from writing, while the inner result is from rendering:
+///
+/// `Ok(Ok(()))` is when the whole output was written
+///
+/// `Ok(Err(name))` is the name of the placeholder missing from `values`. Any output before the
+/// missing placeholder has already been written
+///
+/// `Err(error)` is the error returned by `writer`, with rendering stopped at that point
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_write;
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1>");
+///
+///   let mut values = HashMap::new();
+///   values.insert(String::from("greet"), String::from("Hello"));
+///   values.insert(String::from("name"), String::from("Homer"));
+///
+///   let mut output = Vec::new();
+///
+///   assert!(render_write(&template, &values, &mut output).unwrap() == Ok(()));
+///   assert!(output == b"<h1>Hello Homer</h1>");
+/// }
+/// ```
+pub fn render_write<T: AsValues, W: io::Write>(
+    template: &str,
+    values: T,
+    writer: W,
+) -> io::Result<Result<(), String>> {
+    let result = Renderer::new().render_template_to(
+        &Template::parse(template),
+        resolve_from(&values),
+        &mut IoSink(writer),
+    )?;
+
+    Ok(result.map_err(|error| error.to_string()))
+}
+
+/// Render the template with placeholder values, writing the output only if rendering succeeds
+///
+/// This is the all or nothing counterpart to `render_write()`. The output is built up in memory
+/// first, and only written to `writer` once every placeholder has been resolved, so `writer` is
+/// left untouched when a placeholder is missing.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// `writer` is where the output is written
+///
+/// # Returns
+///
+/// The outer result is from writing, while the inner result is from rendering:
+///
+/// `Ok(Ok(()))` is when the whole output was written
+///
+/// `Ok(Err(name))` is the name of the placeholder missing from `values`. Nothing has been written
+///
+/// `Err(error)` is the error returned by `writer`. As with any `io::Write`, some of the output may
+/// have been written before the error
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_atomic;
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1>");
+///
+///   let mut values = HashMap::new();
+///   values.insert(String::from("greet"), String::from("Hello"));
+///
+///   let mut output = Vec::new();
+///
+///   assert!(render_atomic(&template, &values, &mut output).unwrap() == Err(String::from("name")));
+///   assert!(output.is_empty());
+/// }
+/// ```
+pub fn render_atomic<T: AsValues, W: io::Write>(
+    template: &str,
+    values: T,
+    mut writer: W,
+) -> io::Result<Result<(), String>> {
+    match render(template, values) {
+        Ok(output) => writer.write_all(output.as_bytes()).map(Ok),
+        Err(name) => Ok(Err(name)),
+    }
+}
+
+/// Work out the length of the rendered template in bytes, without building the output
+///
+/// This suits sizing buffers or setting a `Content-Length` header before rendering. The template is
+/// rendered exactly as `render()` would, escapes included, but only the length of each piece of
+/// output is kept.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(len)` is the length in bytes of the output `render()` would return
+///
+/// `Err(name)` is the name of the first placeholder missing from `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{render, rendered_len};
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1> \\{name}");
+///   let values = [("greet", "Hello"), ("name", "Homer")];
+///
+///   assert!(rendered_len(&template, &values) == Ok(render(&template, &values).unwrap().len()));
+///   assert!(rendered_len("{greet} {name}", &[("greet", "Hello")]) == Err(String::from("name")));
+/// }
+/// ```
+pub fn rendered_len<T: AsValues>(template: &str, values: T) -> Result<usize, String> {
+    let mut len = LenSink(0);
+
+    match Renderer::new().render_template_to(
+        &Template::parse(template),
+        resolve_from(&values),
+        &mut len,
+    ) {
+        Ok(result) => result.map(|()| len.0).map_err(|error| error.to_string()),
+        Err(never) => match never {},
+    }
+}
+
+/// Render the template with placeholder values as a sequence of chunks
+///
+/// Each chunk is a span of literal text or a placeholder value, kept apart rather than joined into
+/// one `String`, so very large outputs don't need repeated reallocation as they grow. The caller
+/// can join the chunks, or stream them elsewhere. Joining them gives the same output as
+/// `render()`.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(chunks)` is the chunks of output, in order, none of them empty
+///
+/// `Err(name)` is the name of the first placeholder missing from `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_chunks;
+///
+/// fn main() {
+///   let chunks = render_chunks("<h1>Hello {name}</h1>", &[("name", "Homer")]).unwrap();
+///
+///   assert!(chunks == vec!["<h1>Hello ", "Homer", "</h1>"]);
+///   assert!(chunks.concat() == "<h1>Hello Homer</h1>");
+/// }
+/// ```
+pub fn render_chunks<T: AsValues>(template: &str, values: T) -> Result<Vec<String>, String> {
+    let mut chunks = Vec::new();
+
+    match Renderer::new().render_template_to(
+        &Template::parse(template),
+        resolve_from(&values),
+        &mut chunks,
+    ) {
+        Ok(result) => result.map(|()| chunks).map_err(|error| error.to_string()),
+        Err(never) => match never {},
+    }
+}
+
+/// Render the template with placeholder values, keeping literal text and values apart
+///
+/// This suits a rich renderer, such as one building a DOM or styled terminal text, that shows
+/// placeholder values differently from the template's literal text. Joining the text of every
+/// piece gives the same output as `render()`. Adjacent literal text is joined into one piece,
+/// while empty values are left out.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(pieces)` is the pieces of output, in order
+///
+/// `Err(name)` is the name of the first placeholder missing from `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{render_pieces, OutputPiece};
+///
+/// fn main() {
+///   assert!(render_pieces("<h1>Hello {name}</h1>", &[("name", "Homer")])
+///     == Ok(vec![
+///       OutputPiece::Literal(String::from("<h1>Hello ")),
+///       OutputPiece::Value { name: String::from("name"), text: String::from("Homer") },
+///       OutputPiece::Literal(String::from("</h1>")),
+///     ]));
+/// }
+/// ```
+pub fn render_pieces<T: AsValues>(template: &str, values: T) -> Result<Vec<OutputPiece>, String> {
+    let mut pieces = Vec::new();
+
+    match Renderer::new().render_template_to(
+        &Template::parse(template),
+        resolve_from(&values),
+        &mut pieces,
+    ) {
+        Ok(result) => result.map(|()| pieces).map_err(|error| error.to_string()),
+        Err(never) => match never {},
+    }
+}
+
+/// The template's literal text, with every placeholder replaced, such as for full-text indexing
+///
+/// This keeps only the static text of the template, so that placeholder values never end up in
+/// a search index. Escapes are applied, so escaped placeholders are kept as literal text. Each
+/// placeholder, and each block's opening tag, is replaced with `replacement`, such as a space to
+/// keep the words either side of a placeholder apart, while the body of each block is included
+/// once. With an empty `replacement`, this is the template's skeleton (see
+/// `Template::skeleton()`).
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `replacement` is the text to put in place of each placeholder
+///
+/// # Returns
+///
+/// The template's literal text
+///
+/// # Example
+///
+/// ```
+/// use placeholder::literal_text;
+///
+/// fn main() {
+///   assert!(literal_text("Hello{name}, you owe \\{amount}", " ")
+///     == String::from("Hello , you owe {amount}"));
+/// }
+/// ```
+pub fn literal_text(template: &str, replacement: &str) -> String {
+    Template::parse(template).literal_text(replacement)
+}
+
+/// Render the template with placeholder values, along with the template's skeleton
+///
+/// The skeleton is the template's literal text with every placeholder removed (see
+/// `Template::skeleton()`), taken from the same parse as the output. It's the same for every set
+/// of placeholder values, so it suits being a cache key for the rendered output.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok((output, skeleton))` is the template text with all its placeholders replaced with their
+/// corresponding placeholder values, along with the template text with all its placeholders
+/// removed
+///
+/// `Err(name)` is the name of the first placeholder missing from `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_with_skeleton;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1>");
+///
+///   assert!(render_with_skeleton(&template, &[("greet", "Hello"), ("name", "Homer")])
+///     == Ok((String::from("<h1>Hello Homer</h1>"), String::from("<h1> </h1>"))));
+/// }
+/// ```
+pub fn render_with_skeleton<T: AsValues>(
+    template: &str,
+    values: T,
+) -> Result<(String, String), String> {
+    let template = Template::parse(template);
+
+    Renderer::new()
+        .render_template(&template, values)
+        .map(|output| (output, template.skeleton()))
+        .map_err(|error| error.to_string())
+}
+
+/// Render the template with `{{name}}` placeholder values
+///
+/// Unlike `render()`, single braces are always literal text. This matches the placeholder style of
+/// Handlebars, GitHub Actions, and friends, and allows templates such as JSON to be written without
+/// escaping.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{{name}}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(name)` is the name of the placeholder missing from `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_double_brace;
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///   let template = String::from(r#"{"greeting": "{{greet}} {{name}}"}"#);
+///
+///   let mut values = HashMap::new();
+///   values.insert(String::from("greet"), String::from("Hello"));
+///   values.insert(String::from("name"), String::from("Homer"));
+///
+///   assert!(render_double_brace(&template, &values)
+///     == Ok(String::from(r#"{"greeting": "Hello Homer"}"#)));
+/// }
+/// ```
+pub fn render_double_brace<T: AsValues>(template: &str, values: T) -> Result<String, String> {
+    Template::parse_double_brace(template)
+        .render(values)
+        .map_err(|error| error.to_string())
+}
+
+/// Render the template with placeholder values, reporting the available keys on failure
+///
+/// This behaves exactly like `render()`, but a missing placeholder also carries the sorted list of
+/// keys that were present in `values`. As this leaks key names into the error, it is intended for
+/// development builds only.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the HashMap containing placeholder values to replace within `template`
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(RenderError::MissingPlaceholder { name, available })` is the name of the placeholder
+/// missing from `values` along with the sorted keys of `values`
+///
+/// `Err(error)` is any other failure, such as `RenderError::UnknownFilter { name }`, as is
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{render_debug, RenderError};
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1>");
+///
+///   let mut values = HashMap::new();
+///   values.insert(String::from("greet"), String::from("Hello"));
+///   values.insert(String::from("nmae"), String::from("Homer"));
+///
+///   assert!(render_debug(&template, &values)
+///     == Err(RenderError::MissingPlaceholder {
+///       name: String::from("name"),
+///       available: Some(vec![String::from("greet"), String::from("nmae")]),
+///     }));
+/// }
+/// ```
+pub fn render_debug(
+    template: &str,
+    values: &HashMap<String, String>,
+) -> Result<String, RenderError> {
+    render_structured(template, values).map_err(|error| match error {
+        RenderError::MissingPlaceholder { name, .. } => {
+            let mut available = values.keys().cloned().collect::<Vec<String>>();
+            available.sort();
+
+            RenderError::MissingPlaceholder {
+                name,
+                available: Some(available),
+            }
+        }
+        error => error,
+    })
+}
+
+/// Render the template with placeholder values, requiring the template to have placeholders
+///
+/// This behaves exactly like `render()`, but a template without any placeholders or blocks is an
+/// error rather than being returned as is. This suits a pipeline guarding against a static file
+/// being used by mistake where a template was expected.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(RenderError::NoPlaceholders)` is when `template` has no placeholders. Escaped placeholders
+/// don't count
+///
+/// `Err(RenderError::MissingPlaceholder { name, .. })` is the name of the first placeholder
+/// missing from `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{render_require_dynamic, RenderError};
+///
+/// fn main() {
+///   let values = [("name", "Homer")];
+///
+///   assert!(render_require_dynamic("<h1>Hello {name}</h1>", &values)
+///     == Ok(String::from("<h1>Hello Homer</h1>")));
+///   assert!(render_require_dynamic("<h1>Hello \\{name}</h1>", &values)
+///     == Err(RenderError::NoPlaceholders));
+/// }
+/// ```
+pub fn render_require_dynamic<T: AsValues>(
+    template: &str,
+    values: T,
+) -> Result<String, RenderError> {
+    let template = Template::parse(template);
+
+    if template.required_keys().is_empty() {
+        return Err(RenderError::NoPlaceholders);
+    }
+
+    Renderer::new().render_template(&template, values)
+}
+
+/// List the placeholders within the template
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// # Returns
+///
+/// The names of the placeholders within `template`, sorted and with each listed once. Escaped
+/// placeholders aren't included. See `placeholder_names_ordered()` for the names in the order
+/// they appear instead
+///
+/// # Example
+///
+/// ```
+/// use placeholder::placeholders;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1><p>Bye {name}</p>");
+///
+///   assert!(placeholders(&template) == vec![String::from("greet"), String::from("name")]);
+/// }
+/// ```
+pub fn placeholders(template: &str) -> Vec<String> {
+    Template::parse(template)
+        .required_keys()
+        .iter()
+        .cloned()
+        .collect()
+}
+
+/// Hash the set of placeholders within the template
+///
+/// Two templates needing the same placeholder values have the same signature, however their
+/// literal text, placeholder order, or repeats differ. This suits caching rendered output keyed by
+/// the template's signature and its values, as a cache entry stays valid across template edits
+/// that don't change the placeholders needed.
+///
+/// The hash is FNV-1a over the sorted placeholder names, so it's stable across runs, platforms,
+/// and releases of this crate. It isn't a cryptographic hash.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// # Returns
+///
+/// The signature of the placeholders within `template`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::signature;
+///
+/// fn main() {
+///   assert!(signature("<h1>{greet} {name}</h1>") == signature("<p>{name}, {greet}!</p>"));
+///   assert!(signature("<h1>{greet} {name}</h1>") != signature("<h1>{greet} {title}</h1>"));
+/// }
+/// ```
+pub fn signature(template: &str) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+    // Each name is followed by a zero byte, which can't be part of a name, so that names can't run
+    // into each other
+    placeholders(template)
+        .iter()
+        .flat_map(|name| name.bytes().chain([0]))
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        })
+}
+
+/// List the placeholders within the template in the order they first appear
+///
+/// This lists the same names as `placeholders()`, but in the order of the template rather than
+/// sorted, such as for laying out a form's fields to match the template.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// # Returns
+///
+/// The names of the placeholders within `template`, each listed once where it first appears.
+/// Escaped placeholders aren't included
+///
+/// # Example
+///
+/// ```
+/// use placeholder::placeholder_names_ordered;
+///
+/// fn main() {
+///   let template = String::from("<h1>{name} says {greet}</h1><p>Bye {name}</p>");
+///
+///   assert!(placeholder_names_ordered(&template)
+///     == vec![String::from("name"), String::from("greet")]);
+/// }
+/// ```
+pub fn placeholder_names_ordered(template: &str) -> Vec<String> {
+    let mut names = placeholders_in_order_with_repeats(template);
+    let mut seen = HashSet::new();
+
+    names.retain(|name| seen.insert(name.clone()));
+    names
+}
+
+/// List every placeholder within the template in the order they appear, including repeats
+///
+/// This is `placeholder_names_ordered()` without removing repeats, so that each placeholder's
+/// position within the list matches its position within the template.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// # Returns
+///
+/// The name of every placeholder within `template`, in order. Escaped placeholders aren't included
+///
+/// # Example
+///
+/// ```
+/// use placeholder::placeholders_in_order_with_repeats;
+///
+/// fn main() {
+///   let template = String::from("<h1>{name} says {greet}</h1><p>Bye {name}</p>");
+///
+///   assert!(placeholders_in_order_with_repeats(&template)
+///     == vec![String::from("name"), String::from("greet"), String::from("name")]);
+/// }
+/// ```
+pub fn placeholders_in_order_with_repeats(template: &str) -> Vec<String> {
+    Template::parse(template)
+        .names()
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+/// List the names of values that themselves contain placeholders
+///
+/// Values are always inserted verbatim, so a value containing `{name}` renders as the literal text
+/// `{name}` rather than the value of `name`, unless the `Renderer` is `recursive()`. This helps
+/// warn when values might be mistaken for nested templates.
+///
+/// # Parameters
+///
+/// `values` is the placeholder names and their values, such as a reference to a HashMap
+///
+/// # Returns
+///
+/// The sorted names of the values containing placeholders, the same as `placeholders()` would
+/// find. Escaped placeholders don't count
+///
+/// # Example
+///
+/// ```
+/// use placeholder::values_contain_placeholders;
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///   let mut values = HashMap::new();
+///   values.insert(String::from("greet"), String::from("Hello {name}"));
+///   values.insert(String::from("name"), String::from("Homer"));
+///
+///   assert!(values_contain_placeholders(&values) == vec![String::from("greet")]);
+/// }
+/// ```
+pub fn values_contain_placeholders<I, K, V>(values: I) -> Vec<String>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let mut names: Vec<String> = values
+        .into_iter()
+        .filter(|(_, value)| {
+            template::scan(value.as_ref())
+                .iter()
+                .any(|token| matches!(token, Token::Placeholder { .. }))
+        })
+        .map(|(name, _)| name.as_ref().to_string())
+        .collect();
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// List the names of values that no placeholder within the template uses
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder names and their values, such as a reference to a HashMap
+///
+/// # Returns
+///
+/// The sorted names of the values not used by `template`, whatever the iteration order of `values`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::unused_values;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1>");
+///
+///   assert!(unused_values(&template, [("title", "Mr"), ("name", "Homer"), ("food", "Donuts")])
+///     == vec![String::from("food"), String::from("title")]);
+/// }
+/// ```
+pub fn unused_values<I, K, V>(template: &str, values: I) -> Vec<String>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let used = placeholders(template);
+
+    let mut names: Vec<String> = values
+        .into_iter()
+        .map(|(name, _)| name.as_ref().to_string())
+        .filter(|name| used.binary_search(name).is_err())
+        .collect();
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// List the placeholders within many templates that can never be provided, such as for failing CI
+/// when a template uses a value the app doesn't have
+///
+/// # Parameters
+///
+/// `templates` is the template texts containing placeholders in the form `{name}`
+///
+/// `provided` is the names of every placeholder value that can be provided
+///
+/// # Returns
+///
+/// The index of each template within `templates` along with the name of a placeholder in it
+/// missing from `provided`, ordered by index then name, with each name listed once per template.
+/// Placeholders with a default, or that are optional, are never listed, though those within a
+/// default are.
+///
+/// # Example
+///
+/// ```
+/// use placeholder::lint_templates;
+/// use std::collections::HashSet;
+///
+/// fn main() {
+///   let provided = HashSet::from([String::from("greet"), String::from("name")]);
+///
+///   assert!(lint_templates(&["<h1>{greet} {name}</h1>", "<p>{name} likes {food}</p>"], &provided)
+///     == vec![(1, String::from("food"))]);
+/// }
+/// ```
+pub fn lint_templates<S: BuildHasher>(
+    templates: &[&str],
+    provided: &HashSet<String, S>,
+) -> Vec<(usize, String)> {
+    templates
+        .iter()
+        .enumerate()
+        .flat_map(|(index, template)| {
+            Template::parse(template)
+                .needed_names()
+                .into_iter()
+                .filter(|name| !provided.contains(*name))
+                .map(|name| (index, String::from(name)))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Compare the placeholders within two versions of a template
+///
+/// This tells whether a template change needs new placeholder values, or leaves some unused.
+///
+/// # Parameters
+///
+/// `old` is the original template text containing placeholders in the form `{name}`
+///
+/// `new` is the changed template text containing placeholders in the form `{name}`
+///
+/// # Returns
+///
+/// `(added, removed)` are the sorted names of the placeholders only within `new`, and only within
+/// `old`, respectively
+///
+/// # Example
+///
+/// ```
+/// use placeholder::placeholder_diff;
+///
+/// fn main() {
+///   let old = String::from("<h1>{greet} {name}</h1>");
+///   let new = String::from("<h1>{greet} {title} {surname}</h1>");
+///
+///   assert!(placeholder_diff(&old, &new)
+///     == (vec![String::from("surname"), String::from("title")], vec![String::from("name")]));
+/// }
+/// ```
+pub fn placeholder_diff(old: &str, new: &str) -> (Vec<String>, Vec<String>) {
+    let old = placeholders(old);
+    let new = placeholders(new);
+
+    let added = new
+        .iter()
+        .filter(|name| old.binary_search(name).is_err())
+        .cloned()
+        .collect();
+
+    let removed = old
+        .iter()
+        .filter(|name| new.binary_search(name).is_err())
+        .cloned()
+        .collect();
+
+    (added, removed)
+}
+
+/// List the placeholders used with more than one distinct filter chain within the template
+///
+/// This is a lint for catching copy and paste mistakes, such as `{name|upper}` in one place and
+/// `{name|lower}` in another. A placeholder without any filters counts as using the empty filter
+/// chain, so `{name}` and `{name|upper}` are also reported.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// # Returns
+///
+/// Each placeholder name used with more than one filter chain, along with the distinct filter
+/// chains it was used with, written as template syntax without the leading `|`. Both are sorted
+///
+/// # Example
+///
+/// ```
+/// use placeholder::conflicting_filters;
+///
+/// fn main() {
+///   let template = String::from("<h1>{name|upper}</h1><p>Bye {name|lower}, {title|lower}</p>");
+///
+///   assert!(conflicting_filters(&template)
+///     == vec![(String::from("name"), vec![String::from("lower"), String::from("upper")])]);
+/// }
+/// ```
+pub fn conflicting_filters(template: &str) -> Vec<(String, Vec<String>)> {
+    filter_chains(template)
+        .into_iter()
+        .filter(|(_, chains)| chains.len() > 1)
+        .map(|(name, chains)| (name, chains.into_iter().collect()))
+        .collect()
+}
+
+/// List every placeholder within the template, along with the filter chains it's used with
+///
+/// This suits generating a reference page for a template, showing which placeholders it needs and
+/// how each is transformed. A placeholder without any filters counts as using the empty filter
+/// chain. The names of blocks aren't included, unless they're also used as placeholders.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// # Returns
+///
+/// Each placeholder name, along with the distinct filter chains it was used with, written as
+/// template syntax without the leading `|`. Both are sorted. Escaped placeholders aren't included
+///
+/// # Example
+///
+/// ```
+/// use placeholder::placeholders_with_filters;
+///
+/// fn main() {
+///   let template = String::from("<h1>{name|pad(8)}</h1><p>Bye {name}, {title}</p>");
+///
+///   assert!(placeholders_with_filters(&template)
+///     == vec![
+///       (String::from("name"), vec![String::new(), String::from("pad(8)")]),
+///       (String::from("title"), vec![String::new()]),
+///     ]);
+/// }
+/// ```
+pub fn placeholders_with_filters(template: &str) -> Vec<(String, Vec<String>)> {
+    filter_chains(template)
+        .into_iter()
+        .map(|(name, chains)| (name, chains.into_iter().collect()))
+        .collect()
+}
+
+// The distinct filter chains each placeholder is used with, keyed by placeholder name
+fn filter_chains(template: &str) -> BTreeMap<String, BTreeSet<String>> {
+    let mut chains: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+    for segment in Template::parse(template).all_segments() {
+        if let Segment::Placeholder { name, filters, .. } = segment {
+            chains
+                .entry(name.clone())
+                .or_default()
+                .insert(template::filter_chain(filters));
+        }
+    }
+
+    chains
+}
+
+/// Rename a placeholder throughout the template
+///
+/// Every `{from}` placeholder is rewritten to `{to}` without consulting any values, as is the name
+/// of every `{#if from}` and `{#each from}` block. Literal text, escaped placeholders and blocks,
+/// and all other placeholders are left untouched, including any backslashes before them.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `from` is the name of the placeholder to rename
+///
+/// `to` is the new name of the placeholder
+///
+/// # Returns
+///
+/// The template text with the placeholder renamed
+///
+/// # Example
+///
+/// ```
+/// use placeholder::rename_placeholder;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1><p>Bye {{name} {name}</p>");
+///
+///   assert!(rename_placeholder(&template, "name", "first_name")
+///     == String::from("<h1>{greet} {first_name}</h1><p>Bye {{name} {first_name}</p>"));
+/// }
+/// ```
+pub fn rename_placeholder(template: &str, from: &str, to: &str) -> String {
+    let mut output = String::new();
+
+    for token in template::scan(template) {
+        match token {
+            Token::Placeholder {
+                start,
+                source,
+                name,
+                filters,
+                default,
+            } if name == from || default.is_some() => {
+                // Where the filters end, just before the default or the closing brace
+                let filters_end = match default {
+                    Some((default_start, _)) => default_start - 1,
+                    None => start + source.len() - 1,
+                };
+
+                if name == from {
+                    output.push('{');
+
+                    if template::needs_quotes(to) {
+                        output.push('"');
+                        output.push_str(to);
+                        output.push('"');
+                    } else {
+                        output.push_str(to);
+                    }
+
+                    output.push_str(filters);
+                } else {
+                    output.push_str(&template[start..filters_end]);
+                }
+
+                // The placeholder is renamed within a default too
+                if let Some((default_start, default)) = default {
+                    output.push_str(&template[filters_end..default_start]);
+                    output.push_str(&rename_placeholder(default, from, to));
+                }
+
+                output.push('}');
+            }
+            Token::Text(text) => {
+                let mut cursor = 0;
+
+                // Block names can't be quoted, so blocks keep their name if the new one needs quotes
+                for (start, tag, len, backslashes) in template::block_tags(text) {
+                    match tag {
+                        BlockTag::Open { name, .. }
+                            if name == from
+                                && backslashes % 2 == 0
+                                && !template::needs_quotes(to) =>
+                        {
+                            // The name ends the tag, just before its closing brace
+                            output.push_str(&text[cursor..start + len - 1 - name.len()]);
+                            output.push_str(to);
+                            cursor = start + len - 1;
+                        }
+                        _ => {}
+                    }
+                }
+
+                output.push_str(&text[cursor..]);
+            }
+            Token::Escape { source, .. } | Token::Placeholder { source, .. } => {
+                output.push_str(source)
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_string() {
+        let before = String::from("");
+        let after = String::from("");
+        let values: HashMap<String, String> = HashMap::new();
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn no_substitution() {
+        let before = String::from("Hello world");
+        let after = String::from("Hello world");
+        let values: HashMap<String, String> = HashMap::new();
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn ignore_escaped() {
+        let before = String::from("Hello {{middle} w{{orld");
+        let after = String::from("Hello {{middle} w{{orld");
+        let values: HashMap<String, String> = HashMap::new();
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn backslash_escaped() {
+        let before = String::from(r"\{start} \{{middle} \{end}");
+        let after = String::from(r"{start} \{{middle} {end}");
+        let values: HashMap<String, String> = HashMap::new();
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn backslash_escapes_whole_placeholder() {
+        let values = [("name", "Homer")];
+
+        for (before, after) in [
+            (r"\{name}{name}", "{name}Homer"),
+            (r"\{name}\{name} {name}", "{name}{name} Homer"),
+            (r"\{name}}", "{name}}"),
+            (r"\{name}name}", "{name}name}"),
+            (r"\{name|pad(7)} {name|pad(7)}", "{name|pad(7)}   Homer"),
+            (r#"\{"first name"}!"#, r#"{"first name"}!"#),
+            (r"\{{name}", r"\{{name}"),
+        ] {
+            assert!(render(before, &values) == Ok(String::from(after)));
+        }
+    }
+
+    #[test]
+    fn backslash_before_value() {
+        let before = String::from(r"C:\Users\\{name}\\\{name}\\\\{name}");
+        let after = String::from(r"C:\Users\Homer\{name}\\Homer");
+
+        let mut values = HashMap::new();
+        values.insert(String::from(r"name"), String::from(r"Homer"));
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn backslash_elsewhere() {
+        let before = String::from(r"\d+ \\ {name} \{ \}");
+        let after = String::from(r"\d+ \\ Homer \{ \}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from(r"name"), String::from(r"Homer"));
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn missing_start_value() {
+        let before = String::from("{start} world");
+        let values: HashMap<String, String> = HashMap::new();
+
+        assert!(render(&before, &values) == Err(String::from("start")));
+    }
+
+    #[test]
+    fn missing_middle_value() {
+        let before = String::from("Hello {middle} world");
+        let values: HashMap<String, String> = HashMap::new();
+
+        assert!(render(&before, &values) == Err(String::from("middle")));
+    }
+    #[test]
+    fn missing_end_value() {
+        let before = String::from("Hello {end}");
+        let values: HashMap<String, String> = HashMap::new();
+
+        assert!(render(&before, &values) == Err(String::from("end")));
+    }
+
+    #[test]
+    fn missing_one_value() {
+        let before = String::from("{start} {middle} world");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+
+        assert!(render(&before, &values) == Err(String::from("middle")));
+    }
+
+    #[test]
+    fn missing_one_value_again() {
+        let before = String::from("{start} {middle} world");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("middle"), String::from("beautiful"));
+
+        assert!(render(&before, &values) == Err(String::from("start")));
+    }
+
+    #[test]
+    fn start() {
+        let before = String::from("{start} world");
+        let after = String::from("Hello world");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn end() {
+        let before = String::from("Hello {end}");
+        let after = String::from("Hello world");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn middle() {
+        let before = String::from("Hello {middle} world");
+        let after = String::from("Hello beautiful world");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("middle"), String::from("beautiful"));
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn hello_beautiful_world() {
+        let before = String::from("{start} {middle} {end}");
+        let after = String::from("Hello beautiful world");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("middle"), String::from("beautiful"));
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn multi_line_hello() {
+        let before = String::from("{start} is a\n{middle} test to see\nif the regex {end}");
+        let after = String::from("This is a\nmulti-line test to see\nif the regex works");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("This"));
+        values.insert(String::from("middle"), String::from("multi-line"));
+        values.insert(String::from("end"), String::from("works"));
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    #[allow(clippy::useless_conversion)]
+    fn a_longer_test() {
+        let before =
+            String::from(format!("{}\n{}\n{}\n{}\n{}",
+            "No society can surely {fourth}e flourishing {first} happy, {third} which {second} far greater part {third} {second}",
+            "mem{fourth}ers are poor {first} misera{fourth}le. It is but equity, besides, that they who feed,",
+            "clothe, {first} lodge {second} whole body {third} {second} people, should have such a share {third} {second}",
+            "produce {third} their own la{fourth}our as to be themselves tolera{fourth}ly well fed, clothed, {first}",
+            "lodged."));
+
+        let after =
+            String::from(format!("{}\n{}\n{}\n{}\n{}",
+            "No society can surely be flourishing and happy, of which the far greater part of the",
+            "members are poor and miserable. It is but equity, besides, that they who feed,",
+            "clothe, and lodge the whole body of the people, should have such a share of the",
+            "produce of their own labour as to be themselves tolerably well fed, clothed, and",
+            "lodged."));
+
+        let mut values = HashMap::new();
+        values.insert(String::from("first"), String::from("and"));
+        values.insert(String::from("second"), String::from("the"));
+        values.insert(String::from("third"), String::from("of"));
+        values.insert(String::from("fourth"), String::from("b"));
+        values.insert(String::from("fifth"), String::from("these"));
+        values.insert(String::from("sixth"), String::from("last"));
+        values.insert(String::from("seventh"), String::from("ones"));
+        values.insert(String::from("eighth"), String::from("do"));
+        values.insert(String::from("ninth"), String::from("not"));
+        values.insert(String::from("tenth"), String::from("exist"));
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn fail_fast_and_collect_error_shapes() {
+        let before = String::from("{start} {middle} {end} {start}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("middle"), String::from("beautiful"));
+
+        assert!(render(&before, &values) == Err(String::from("start")));
+        assert!(
+            render_collect(&before, &values)
+                == Err(CollectError::Missing(vec![
+                    String::from("start"),
+                    String::from("end")
+                ]))
+        );
+
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(render(&before, &values) == Ok(String::from("Hello beautiful world Hello")));
+        assert!(
+            render_collect(&before, &values)
+                == render(&before, &values).map_err(|_| CollectError::Missing(vec![]))
+        );
+    }
+
+    #[test]
+    fn collect_keeps_other_errors() {
+        let values = [("x", "Hello")];
+        let unknown = RenderError::UnknownFilter {
+            name: String::from("bogus"),
+        };
+
+        assert!(render_collect("{x|bogus}", &values) == Err(CollectError::Render(unknown.clone())));
+        assert!(
+            render_collect_max("{x|bogus}", &values, 4)
+                == Err(CollectError::Render(unknown.clone()))
+        );
+        assert!(render_collect_offsets("{x|bogus}", &values) == Err(CollectError::Render(unknown)));
+    }
+
+    #[test]
+    fn collect_max() {
+        let before = String::from("{a} {b} {a} {c} {d} {b} {e}");
+        let values = [("c", "Hello")];
+
+        assert!(
+            render_collect_max(&before, &values, 3)
+                == Err(CollectError::Missing((
+                    vec![String::from("a"), String::from("b"), String::from("d")],
+                    true
+                )))
+        );
+        assert!(
+            render_collect_max(&before, &values, 4)
+                == Err(CollectError::Missing((
+                    vec![
+                        String::from("a"),
+                        String::from("b"),
+                        String::from("d"),
+                        String::from("e")
+                    ],
+                    false
+                )))
+        );
+        assert!(
+            render_collect_max(&before, &values, 100)
+                == render_collect(&before, &values).map_err(|error| match error {
+                    CollectError::Missing(names) => CollectError::Missing((names, false)),
+                    CollectError::Render(error) => CollectError::Render(error),
+                })
+        );
+        assert!(
+            render_collect_max(&before, &values, 0)
+                == Err(CollectError::Missing((Vec::new(), true)))
+        );
+        assert!(render_collect_max("{c}!", &values, 0) == Ok(String::from("Hello!")));
+    }
+
+    #[test]
+    fn collect_optional_and_defaults() {
+        let before = String::from("{greet} {title=Customer} {middle?}{name}{suffix?} #{code={id}}");
+        let values = [("greet", "Hello")];
+
+        assert!(
+            render_collect(&before, &values)
+                == Err(CollectError::Missing(vec![
+                    String::from("name"),
+                    String::from("id")
+                ]))
+        );
+        assert!(
+            render_collect_max(&before, &values, 1)
+                == Err(CollectError::Missing((vec![String::from("name")], true)))
+        );
+        assert!(
+            render_collect_offsets(&before, &values)
+                == Err(CollectError::Missing(vec![
+                    (String::from("name"), 34),
+                    (String::from("id"), 57)
+                ]))
+        );
+
+        let values = [("greet", "Hello"), ("name", "Homer"), ("id", "7")];
+
+        assert!(render_collect(&before, &values) == Ok(String::from("Hello Customer Homer #7")));
+
+        let values = [("greet", "Hello"), ("name", "Homer"), ("code", "A1")];
+
+        assert!(render_collect(&before, &values) == Ok(String::from("Hello Customer Homer #A1")));
+        assert!(
+            render_collect("{a={b}} {c={d}}", &[("b", "Homer")])
+                == Err(CollectError::Missing(vec![String::from("d")]))
+        );
+    }
+
+    #[test]
+    fn collect_looks_up_once() {
+        struct Counted(std::cell::Cell<usize>);
+
+        impl Lookup for Counted {
+            fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+                self.0.set(self.0.get() + 1);
+                (name == "a").then_some(Cow::Borrowed("Hello"))
+            }
+        }
+
+        let values = Counted(std::cell::Cell::new(0));
+
+        assert!(
+            render_collect("{a} {b} {a} {c=x}", &values)
+                == Err(CollectError::Missing(vec![String::from("b")]))
+        );
+        assert!(values.0.get() == 3);
+
+        values.0.set(0);
+
+        assert!(
+            render_collect_offsets("{a} {b} {a}", &values)
+                == Err(CollectError::Missing(vec![(String::from("b"), 4)]))
+        );
+        assert!(values.0.get() == 2);
+
+        values.0.set(0);
+
+        assert!(
+            render_collect_max("{b} {c} {a}", &values, 1)
+                == Err(CollectError::Missing((vec![String::from("b")], true)))
+        );
+        assert!(values.0.get() == 2);
+    }
+
+    #[test]
+    fn collect_only_looked_up() {
+        let before = String::from(
+            "{start} \\{middle} {{middle} {#if member}{end}{/if}{#if extra}{more}{/if}",
+        );
+
+        let values = [("start", "Hello"), ("member", "")];
+
+        assert!(
+            render_collect(&before, &values)
+                == Err(CollectError::Missing(vec![String::from("extra")]))
+        );
+
+        let values = [("start", "Hello"), ("member", "yes"), ("extra", "")];
+
+        assert!(
+            render_collect(&before, &values)
+                == Err(CollectError::Missing(vec![String::from("end")]))
+        );
+
+        let values = [("member", "yes"), ("extra", "yes")];
+
+        assert!(
+            render_collect(&before, &values)
+                == Err(CollectError::Missing(vec![
+                    String::from("start"),
+                    String::from("end"),
+                    String::from("more")
+                ]))
+        );
+    }
+
+    #[test]
+    fn structured_matches_legacy() {
+        let values = [("name", "Homer"), ("active", "maybe")];
+
+        for template in [
+            "Hello {name}",
+            "Hello {nobody}",
+            "Hello {name|frobnicate}",
+            "Hello {name|pad(x)}",
+            "{active|yesno}",
+            "Hello",
+        ] {
+            assert!(
+                render_structured(template, &values).map_err(|error| error.to_string())
+                    == render(template, &values)
+            );
+        }
+
+        assert!(
+            render_structured("Hello {nobody}", &values)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("nobody"),
+                    available: None
+                })
+        );
+    }
+
+    #[test]
+    fn values_not_rendered() {
+        let values = [("a", "{b}"), ("b", "Homer"), ("c", "\\{b}"), ("d", "{a}")];
+
+        assert!(render("{a}{b}", &values) == Ok(String::from("{b}Homer")));
+        assert!(render("{b}{a}{d}{c}", &values) == Ok(String::from("Homer{b}{a}\\{b}")));
+        assert!(render("{a|pad(5)}", &values) == Ok(String::from("  {b}")));
+        assert!(
+            Renderer::new().render("{#each a}{a}{b}{/each}", &values)
+                == Ok(String::from("{b}Homer"))
+        );
+    }
+
+    #[test]
+    fn nested_defaults() {
+        let values = [("b", "Homer"), ("raw", "{b}")];
+        let defaults = [
+            ("a", "{b}"),
+            ("c", "[{a}|{d}]"),
+            ("d", "{raw}"),
+            ("e", "{nobody}"),
+            ("loop", "x{loop}"),
+        ];
+
+        assert!(
+            render_with_nested_defaults("{a}", &values, &defaults) == Ok(String::from("Homer"))
+        );
+        assert!(
+            render_with_nested_defaults("{c} {b}", &values, &defaults)
+                == Ok(String::from("[Homer|{b}] Homer"))
+        );
+        assert!(
+            render_with_nested_defaults("{a}", &[("a", "{b}")], &defaults)
+                == Ok(String::from("{b}"))
+        );
+        assert!(
+            render_with_nested_defaults("{a} {e}", &values, &defaults)
+                == Err(String::from("nobody"))
+        );
+        assert!(
+            render_with_nested_defaults("{loop}", &values, &defaults)
+                == Err(RenderError::RecursionLimit {
+                    name: String::from("loop")
+                }
+                .to_string())
+        );
+    }
+
+    #[test]
+    fn literal_text_for_indexing() {
+        let before = "<h1>Hello{name|pad(8)}</h1>{#if vip}<p>VIP</p>{/if}\\{raw} {{x}}";
+
+        assert!(literal_text(before, " ") == "<h1>Hello </h1> <p>VIP</p>{raw} {{x}}");
+        assert!(literal_text(before, "") == Template::parse(before).skeleton());
+        assert!(literal_text("{a}{b}", "|") == "||");
+        assert!(literal_text("", " ").is_empty());
+    }
+
+    #[test]
+    fn require_dynamic() {
+        let values = [("name", "Homer"), ("member", "yes")];
+
+        for before in ["", "Hello world", "Hello {{name} \\{name} {} {/if}"] {
+            assert!(render_require_dynamic(before, &values) == Err(RenderError::NoPlaceholders));
+        }
+
+        assert!(render_require_dynamic("Hello {name}", &values) == Ok(String::from("Hello Homer")));
+        assert!(
+            render_require_dynamic("Hello{#if member}!{/if}", &values)
+                == Ok(String::from("Hello!"))
+        );
+        assert!(
+            render_require_dynamic("Hello {food}", &values)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("food"),
+                    available: None
+                })
+        );
+    }
+
+    #[test]
+    fn defaults_used_when_missing() {
+        let before = String::from("{start} {middle} {end}");
+        let after = String::from("Hello beautiful world");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+
+        let mut defaults = HashMap::new();
+        defaults.insert(String::from("middle"), String::from("beautiful"));
+        defaults.insert(String::from("end"), String::from("world"));
+
+        assert!(render_with_defaults(&before, &values, &defaults) == Ok(after));
+    }
+
+    #[test]
+    fn defaults_values_take_precedence() {
+        let before = String::from("{start} {middle} {end}");
+        let after = String::from("Hello cruel world");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("middle"), String::from("cruel"));
+
+        let mut defaults = HashMap::new();
+        defaults.insert(String::from("start"), String::from("Goodbye"));
+        defaults.insert(String::from("middle"), String::from("beautiful"));
+        defaults.insert(String::from("end"), String::from("world"));
+
+        assert!(render_with_defaults(&before, &values, &defaults) == Ok(after));
+    }
+
+    #[test]
+    fn defaults_reported() {
+        let before = String::from("{start} {middle} {end} {middle|pad(10)} {start}");
+
+        let values = [("start", "Hello"), ("end", "")];
+        let defaults = [
+            ("start", "Goodbye"),
+            ("middle", "beautiful"),
+            ("end", "world"),
+        ];
+
+        assert!(
+            render_with_defaults_report(&before, &values, &defaults)
+                == Ok((
+                    String::from("Hello beautiful   beautiful Hello"),
+                    vec![String::from("middle")]
+                ))
+        );
+        assert!(
+            render_with_defaults_report(&before, &[("middle", "cruel")], &defaults)
+                == Ok((
+                    String::from("Goodbye cruel world      cruel Goodbye"),
+                    vec![String::from("start"), String::from("end")]
+                ))
+        );
+        assert!(
+            render_with_defaults_report(&before, &defaults, &[("unused", "")])
+                == Ok((
+                    String::from("Goodbye beautiful world  beautiful Goodbye"),
+                    Vec::new()
+                ))
+        );
+        assert!(
+            render_with_defaults_report(&before, &values, &[("end", "world")])
+                == Err(String::from("middle"))
+        );
+    }
+
+    #[test]
+    fn defaults_both_missing() {
+        let before = String::from("{start} {middle} {end}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+
+        let mut defaults = HashMap::new();
+        defaults.insert(String::from("end"), String::from("world"));
+
+        assert!(render_with_defaults(&before, &values, &defaults) == Err(String::from("middle")));
+    }
+
+    #[test]
+    fn lone_closing_brace() {
+        let values = [("a", "Hello")];
+
+        assert!(render("a } b", &values) == Ok(String::from("a } b")));
+        assert!(render("{a} } b}", &values) == Ok(String::from("Hello } b}")));
+    }
+
+    #[test]
+    fn lone_opening_brace() {
+        let values = [("a", "Hello")];
+
+        assert!(render("a { b", &values) == Ok(String::from("a { b")));
+        assert!(render("{a} { {b", &values) == Ok(String::from("Hello { {b")));
+    }
+
+    #[test]
+    fn reversed_braces() {
+        let values = [("a", "Hello")];
+
+        assert!(render("}{", &values) == Ok(String::from("}{")));
+        assert!(render("}a{", &values) == Ok(String::from("}a{")));
+        assert!(render("}{a}{", &values) == Ok(String::from("}Hello{")));
+    }
+
+    #[test]
+    fn preview_mixed() {
+        let before =
+            String::from("{start} {middle|pad(3)} {end|rpad(7)}!\n{end} \\{start} {middle}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(
+            render_preview(&before, &values)
+                == (
+                    String::from("{start} {middle|pad(3)} world  !\nworld {start} {middle}"),
+                    vec![String::from("start"), String::from("middle")]
+                )
+        );
+    }
+
+    #[test]
+    fn preview_resolved() {
+        let before = String::from("{start} {middle} {end}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("middle"), String::from("beautiful"));
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(
+            render_preview(&before, &values) == (String::from("Hello beautiful world"), vec![])
+        );
+    }
+
+    #[test]
+    fn preview_failed_filter() {
+        let before = String::from("{start|shout} {end}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(
+            render_preview(&before, &values)
+                == (
+                    String::from("{start|shout} world"),
+                    vec![String::from("start")]
+                )
+        );
+    }
+
+    #[test]
+    fn quoted_names() {
+        let values = [("a|b", "piped"), ("a", "Hello"), ("first name", "Homer")];
+
+        assert!(
+            render(r#"{"a|b"} {"first name"} {"first name"|rpad(6)}!"#, &values)
+                == Ok(String::from("piped Homer Homer !"))
+        );
+        assert!(render("{a|b}", &values) == Err(String::from("unknown filter: b")));
+    }
+
+    #[test]
+    fn collect_offsets_every_occurrence() {
+        let before =
+            String::from("{start} {middle} {end}\n{end} \\{middle} {middle|pad(4)} {start}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+
+        assert!(
+            render_collect_offsets(&before, &values)
+                == Err(CollectError::Missing(vec![
+                    (String::from("middle"), 8),
+                    (String::from("end"), 17),
+                    (String::from("end"), 23),
+                    (String::from("middle"), 39),
+                ]))
+        );
+    }
+
+    #[test]
+    fn collect_offsets_blocks() {
+        assert!(
+            render_collect_offsets("{#if b}{y}{/if} {z}", &[("y", "Hello")])
+                == Err(CollectError::Missing(vec![
+                    (String::from("b"), 0),
+                    (String::from("z"), 16),
+                ]))
+        );
+        assert!(
+            render_collect_offsets("{#if b}{y}{/if} {z}", &[("b", "yes")])
+                == Err(CollectError::Missing(vec![
+                    (String::from("y"), 7),
+                    (String::from("z"), 16),
+                ]))
+        );
+        assert!(
+            render_collect_offsets(
+                "{a} {#each items}{x}{items}{/each}{y}",
+                &[("items", "1\n2")]
+            ) == Err(CollectError::Missing(vec![
+                (String::from("a"), 0),
+                (String::from("x"), 17),
+                (String::from("x"), 17),
+                (String::from("y"), 34),
+            ]))
+        );
+    }
+
+    #[test]
+    fn collect_offsets_resolved() {
+        let before = String::from("{start} {end}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(render_collect_offsets(&before, &values) == Ok(String::from("Hello world")));
+    }
+
+    #[test]
+    fn static_template_not_scanned() {
+        let before = "Hello world, with no placeholders at all\n".repeat(1000);
+        let values: HashMap<String, String> = HashMap::new();
+
+        let scans = template::SCANS.with(|scans| scans.get());
+
+        assert!(render_cow(&before, &values) == Ok(Cow::Borrowed(before.as_str())));
+        assert!(render(&before, &values) == Ok(before.clone()));
+        assert!(template::SCANS.with(|scans| scans.get()) == scans);
+
+        assert!(render("{start}", &[("start", "Hello")]) == Ok(String::from("Hello")));
+        assert!(template::SCANS.with(|scans| scans.get()) == scans + 1);
+    }
+
+    #[test]
+    fn prefixed_keys() {
+        let before = String::from("{start} {middle} {end}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("en.start"), String::from("Hello"));
+        values.insert(String::from("en.middle"), String::from("beautiful"));
+        values.insert(String::from("en.end"), String::from("world"));
+        values.insert(String::from("fr.start"), String::from("Bonjour"));
+        values.insert(String::from("fr.middle"), String::from("le beau"));
+        values.insert(String::from("fr.end"), String::from("monde"));
+        values.insert(String::from("start"), String::from("Unprefixed"));
+
+        assert!(
+            render_with_prefix_stripping(&before, &values, "en.")
+                == Ok(String::from("Hello beautiful world"))
+        );
+        assert!(
+            render_with_prefix_stripping(&before, &values, "fr.")
+                == Ok(String::from("Bonjour le beau monde"))
+        );
+        assert!(
+            render_with_prefix_stripping(&before, &values, "de.") == Err(String::from("start"))
+        );
+    }
+
+    #[test]
+    fn indent() {
+        let before = String::from("<ul>\n  <li>{first}</li>\n\n  <li>{second}</li>\n</ul>\n");
+        let after = String::from(
+            "    <ul>\n      <li>Hello</li>\n\n      <li>beautiful\n    world</li>\n    </ul>\n",
+        );
+
+        let values = [("first", "Hello"), ("second", "beautiful\nworld")];
+
+        assert!(render_indent(&before, &values, 4) == Ok(after));
+        assert!(render_indent("{first}", &values, 2) == Ok(String::from("  Hello")));
+        assert!(render_indent("", &values, 2) == Ok(String::new()));
+        assert!(render_indent("{third}\n", &values, 2) == Err(String::from("third")));
+    }
+
+    #[test]
+    fn computed_only_where_used() {
+        let before = String::from("{start} {middle|pad(10)} {end} {start} {middle}");
+        let after = String::from("Hello  beautiful world Hello beautiful");
+
+        let mut values = HashMap::new();
+        values.insert("start", "Hello");
+        values.insert("middle", "beautiful");
+        values.insert("end", "world");
+        values.insert("unused", "never computed");
+
+        let mut calls: Vec<String> = Vec::new();
+
+        let output = render_fn(&before, |name| {
+            calls.push(name.to_string());
+            values.get(name).map(|value| value.to_string())
+        });
+
+        assert!(output == Ok(after));
+        assert!(calls == ["start", "middle", "end"]);
+    }
+
+    #[test]
+    fn computed_missing() {
+        let mut calls = 0;
+
+        let output = render_fn("{start} {middle} {end}", |name| {
+            calls += 1;
+            (name == "start").then(|| String::from("Hello"))
+        });
+
+        assert!(output == Err(String::from("middle")));
+        assert!(calls == 2);
+    }
+
+    #[test]
+    fn thunks() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let before = String::from("{start} {end} {start|pad(6)}");
+        let after = String::from("Hello world  Hello");
+
+        let start_calls = Rc::new(Cell::new(0));
+        let unused_calls = Rc::new(Cell::new(0));
+
+        let mut thunks: HashMap<String, Box<dyn Fn() -> String>> = HashMap::new();
+
+        let calls = Rc::clone(&start_calls);
+        thunks.insert(
+            String::from("start"),
+            Box::new(move || {
+                calls.set(calls.get() + 1);
+                String::from("Hello")
+            }),
+        );
+
+        thunks.insert(String::from("end"), Box::new(|| String::from("world")));
+
+        let calls = Rc::clone(&unused_calls);
+        thunks.insert(
+            String::from("unused"),
+            Box::new(move || {
+                calls.set(calls.get() + 1);
+                String::from("never computed")
+            }),
+        );
+
+        assert!(render_thunks(&before, &thunks) == Ok(after));
+        assert!(start_calls.get() == 1);
+        assert!(unused_calls.get() == 0);
+
+        assert!(render_thunks("{start} {middle}", &thunks) == Err(String::from("middle")));
+        assert!(unused_calls.get() == 0);
+    }
+
+    #[test]
+    fn wrapped() {
+        let before = String::from("Hi {name},\n\n{body}\nBye");
+        let after = String::from(
+            "Hi Homer,\n\nDo you\nlike\nDonuts?\nSupercalifragilistic\nis a long\nword\nBye",
+        );
+
+        let values = [
+            ("name", "Homer"),
+            (
+                "body",
+                "Do you  like Donuts? Supercalifragilistic is a long word",
+            ),
+        ];
+
+        assert!(render_wrapped(&before, &values, 10) == Ok(after));
+        assert!(render_wrapped("{name}", &values, 10) == Ok(String::from("Homer")));
+        assert!(render_wrapped("a  b", &values, 4) == Ok(String::from("a  b")));
+        assert!(render_wrapped("{missing}", &values, 10) == Err(String::from("missing")));
+    }
+
+    #[test]
+    fn mixed() {
+        let before = String::from("{0} {name}, {1} {greet} {2}! {2nd}");
+        let after = String::from("Hello Homer, do you like beautiful Donuts! world");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("name"), String::from("Homer"));
+        values.insert(String::from("greet"), String::from("beautiful"));
+        values.insert(String::from("2nd"), String::from("world"));
+        values.insert(String::from("0"), String::from("Named zero"));
+
+        assert!(render_mixed(&before, &["Hello", "do you like", "Donuts"], &values) == Ok(after));
+        assert!(render_mixed(&before, &["Hello"], &values) == Err(String::from("1")));
+
+        values.remove("greet");
+
+        assert!(
+            render_mixed(&before, &["Hello", "do you like Donuts"], &values)
+                == Err(String::from("greet"))
+        );
+        assert!(
+            render_mixed("{99999999999999999999999}", &["Hello"], &values)
+                == Err(String::from("99999999999999999999999"))
+        );
+    }
+
+    #[test]
+    fn nested() {
+        let values = [
+            ("n", "3"),
+            ("kind", "user"),
+            ("col_1", "first"),
+            ("col_3", "third"),
+            ("name_user_label", "Homer"),
+        ];
+
+        assert!(
+            render_nested("{col_{n}} {name_{kind}_label} {n}", &values)
+                == Ok(String::from("third Homer 3"))
+        );
+        assert!(render_nested("<{col_{n}}>", &values) == Ok(String::from("<third>")));
+        assert!(render_nested("{col_{missing}}", &values) == Err(String::from("missing")));
+        assert!(render_nested("{col_{n}} {col_{kind}}", &values) == Err(String::from("col_user")));
+    }
+
+    #[test]
+    fn nested_single_level() {
+        let values = [
+            ("n", "{m}"),
+            ("m", "1"),
+            ("col_{m}", "raw"),
+            ("col_1", "resolved"),
+        ];
+
+        assert!(render_nested("{col_{n}}", &values) == Ok(String::from("raw")));
+        assert!(render_nested("{col_{{n}}}", &values) == Ok(String::from("{col_{{n}}}")));
+        assert!(render_nested("{{col_{n}}}", &values) == Ok(String::from("{{col_{n}}}")));
+        assert!(render_nested(r"\\{col_{n}}", &values) == Ok(String::from(r"\raw")));
+        assert!(render_nested(r"\{col_{n}}", &values) == Ok(String::from("{col_{n}}")));
+    }
+
+    #[test]
+    fn nested_escapes_and_blocks() {
+        let values = [("a", "yes"), ("n", "3"), ("col_3", "C"), ("rows", "1\n3")];
+
+        assert!(render_nested(r"\{col_{n}} {col_{n}}", &values) == Ok(String::from("{col_{n}} C")));
+        assert!(render_nested("{#if a}{col_{n}}{/if}", &values) == Ok(String::from("C")));
+        assert!(render_nested("{#if b}{col_{n}}{/if}", &values) == Err(String::from("b")));
+        assert!(
+            render_nested("{#each rows}[{col_{rows}}]{/each}", &values)
+                == Err(String::from("col_1"))
+        );
+        assert!(
+            render_nested(
+                "{#each rows}[{col_{rows}}]{/each}",
+                &[("rows", "3"), ("col_3", "C")]
+            ) == Ok(String::from("[C]"))
+        );
+    }
+
+    #[test]
+    fn write() {
+        let before = String::from("{start} {middle} {end}");
+        let after = String::from("Hello beautiful world");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("middle"), String::from("beautiful"));
+        values.insert(String::from("end"), String::from("world"));
+
+        let mut output = Vec::new();
+
+        assert!(render_write(&before, &values, &mut output).unwrap() == Ok(()));
+        assert!(output == after.into_bytes());
     }
 
-    Ok(output)
-}
+    #[test]
+    fn write_missing_value() {
+        let before = String::from("{start} {middle} {end}");
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+
+        let mut output = Vec::new();
+
+        assert!(
+            render_write(&before, &values, &mut output).unwrap() == Err(String::from("middle"))
+        );
+        assert!(output == b"Hello ");
+    }
 
     #[test]
-    fn empty_string() {
-        let before = String::from("");
-        let after = String::from("");
-        let values = HashMap::new();
+    fn atomic() {
+        let before = String::from("{start} {middle} {end}");
+        let after = String::from("Hello beautiful world");
 
-        assert!(render(&before, &values) == Ok(after));
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("middle"), String::from("beautiful"));
+        values.insert(String::from("end"), String::from("world"));
+
+        let mut output = Vec::new();
+
+        assert!(render_atomic(&before, &values, &mut output).unwrap() == Ok(()));
+        assert!(output == after.into_bytes());
     }
 
     #[test]
-    fn no_substitution() {
-        let before = String::from("Hello world");
-        let after = String::from("Hello world");
-        let values = HashMap::new();
+    fn atomic_missing_value() {
+        let before = String::from("{start} {middle} {end}");
 
-        assert!(render(&before, &values) == Ok(after));
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+
+        let mut output = b"untouched".to_vec();
+
+        assert!(
+            render_atomic(&before, &values, &mut output).unwrap() == Err(String::from("middle"))
+        );
+        assert!(output == b"untouched");
     }
 
     #[test]
-    fn ignore_escaped() {
-        let before = String::from("Hello {{middle} w{{orld");
-        let after = String::from("Hello {{middle} w{{orld");
-        let values = HashMap::new();
+    fn rendered_length() {
+        let values = [("greet", "Hello"), ("name", "Hömer"), ("empty", "")];
 
-        assert!(render(&before, &values) == Ok(after));
+        for before in [
+            "",
+            "{greet} {name}!",
+            "{{greet}} \\{name} \\\\{name} {empty}",
+            "{ {name|pad(8)} }",
+            "{#if name}{name}{/if}{#each greet}[{greet}]{/each}{#if empty}x{/if}",
+        ] {
+            assert!(rendered_len(before, &values) == Ok(render(before, &values).unwrap().len()));
+        }
+
+        assert!(rendered_len("{greet} {end}", &values) == Err(String::from("end")));
     }
 
     #[test]
-    fn missing_start_value() {
+    fn write_error() {
+        struct Full;
+
+        impl io::Write for Full {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::WriteZero, "full"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
         let before = String::from("{start} world");
-        let values = HashMap::new();
 
-        assert!(render(&before, &values) == Err(String::from("start")));
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+
+        assert!(
+            render_write(&before, &values, Full).map_err(|error| error.kind())
+                == Err(io::ErrorKind::WriteZero)
+        );
     }
 
     #[test]
-    fn missing_middle_value() {
-        let before = String::from("Hello {middle} world");
-        let values = HashMap::new();
+    fn chunks() {
+        let before =
+            String::from("{greet} \\{name} {name|pad(6)}!{empty}{#each items}[{items}]{/each}");
+        let values = [
+            ("greet", "Hello"),
+            ("name", "Homer"),
+            ("empty", ""),
+            ("items", "Donuts\nDuff"),
+        ];
 
-        assert!(render(&before, &values) == Err(String::from("middle")));
+        let chunks = render_chunks(&before, &values).unwrap();
+
+        assert!(chunks.iter().all(|chunk| !chunk.is_empty()));
+        assert!(Ok(chunks.concat()) == render(&before, &values));
+        assert!(render_chunks(&before, &[("greet", "Hello")]) == Err(String::from("name")));
+
+        let before = "{row}\n".repeat(1000);
+        let chunks = render_chunks(&before, &[("row", "Donuts")]).unwrap();
+
+        assert!(chunks.len() == 2000);
+        assert!(Ok(chunks.concat()) == render(&before, &[("row", "Donuts")]));
     }
+
     #[test]
-    fn missing_end_value() {
-        let before = String::from("Hello {end}");
-        let values = HashMap::new();
+    fn pieces() {
+        let before = String::from("{greet} \\{name} {name|pad(6)}!{empty}{#if member} Hi{/if}");
+        let values = [
+            ("greet", "Hello"),
+            ("name", "Homer"),
+            ("empty", ""),
+            ("member", "yes"),
+        ];
 
-        assert!(render(&before, &values) == Err(String::from("end")));
+        let pieces = render_pieces(&before, &values).unwrap();
+
+        assert!(
+            pieces
+                == vec![
+                    OutputPiece::Value {
+                        name: String::from("greet"),
+                        text: String::from("Hello")
+                    },
+                    OutputPiece::Literal(String::from(" {name} ")),
+                    OutputPiece::Value {
+                        name: String::from("name"),
+                        text: String::from(" Homer")
+                    },
+                    OutputPiece::Literal(String::from("! Hi")),
+                ]
+        );
+
+        let joined: String = pieces
+            .iter()
+            .map(|piece| match piece {
+                OutputPiece::Literal(text) | OutputPiece::Value { text, .. } => text.as_str(),
+            })
+            .collect();
+
+        assert!(Ok(joined) == render(&before, &values));
+        assert!(render_pieces(&before, &[("greet", "Hello")]) == Err(String::from("name")));
     }
 
     #[test]
-    fn missing_one_value() {
-        let before = String::from("{start} {middle} world");
+    fn skeleton() {
+        let before = String::from("Dear {name},\n{#if member}Thanks {name|pad(6)}!{/if} \\{name}");
+
+        let homer = render_with_skeleton(&before, &[("name", "Homer"), ("member", "yes")]);
+        let marge = render_with_skeleton(&before, &[("name", "Marge"), ("member", "")]);
+
+        assert!(
+            homer
+                == Ok((
+                    String::from("Dear Homer,\nThanks  Homer! {name}"),
+                    String::from("Dear ,\nThanks ! {name}")
+                ))
+        );
+        assert!(
+            marge
+                == Ok((
+                    String::from("Dear Marge,\n {name}"),
+                    String::from("Dear ,\nThanks ! {name}")
+                ))
+        );
+
+        assert!(render_with_skeleton(&before, &[("member", "")]) == Err(String::from("name")));
+    }
+
+    #[test]
+    fn double_brace() {
+        let before = String::from(r#"{"start": "{{start}}", "end": {"{end}": "{{end}}"}}"#);
+        let after = String::from(r#"{"start": "Hello", "end": {"{end}": "world"}}"#);
 
         let mut values = HashMap::new();
         values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("end"), String::from("world"));
 
-        assert!(render(&before, &values) == Err(String::from("middle")));
+        assert!(render_double_brace(&before, &values) == Ok(after));
     }
 
     #[test]
-    fn missing_one_value_again() {
-        let before = String::from("{start} {middle} world");
+    fn double_brace_missing_value() {
+        let before = String::from(r#"{"start": "{{start}}"}"#);
+        let values: HashMap<String, String> = HashMap::new();
 
-        let mut values = HashMap::new();
-        values.insert(String::from("middle"), String::from("beautiful"));
+        assert!(render_double_brace(&before, &values) == Err(String::from("start")));
+    }
 
-        assert!(render(&before, &values) == Err(String::from("start")));
+    #[test]
+    fn placeholders_sorted_once() {
+        let before = String::from("{start} {middle} {end}\n{end} {{ignored} {middle} {start}");
+
+        assert!(
+            placeholders(&before)
+                == vec![
+                    String::from("end"),
+                    String::from("middle"),
+                    String::from("start"),
+                ]
+        );
     }
 
     #[test]
-    fn start() {
-        let before = String::from("{start} world");
-        let after = String::from("Hello world");
+    fn placeholders_ordered_once() {
+        let before = String::from("{start} {middle} {end}\n{end} {{ignored} {middle} {start}");
 
-        let mut values = HashMap::new();
-        values.insert(String::from("start"), String::from("Hello"));
+        assert!(
+            placeholder_names_ordered(&before)
+                == vec![
+                    String::from("start"),
+                    String::from("middle"),
+                    String::from("end"),
+                ]
+        );
+    }
 
-        assert!(render(&before, &values) == Ok(after));
+    #[test]
+    fn placeholders_ordered_with_repeats() {
+        let before = String::from("{start} {middle} {end}\n{end} \\{ignored} {middle|upper}");
+
+        assert!(
+            placeholders_in_order_with_repeats(&before)
+                == vec![
+                    String::from("start"),
+                    String::from("middle"),
+                    String::from("end"),
+                    String::from("end"),
+                    String::from("middle"),
+                ]
+        );
     }
 
     #[test]
-    fn end() {
-        let before = String::from("Hello {end}");
-        let after = String::from("Hello world");
+    fn signature_ignores_literals() {
+        let before = String::from("{start} {middle} {end}");
+        let after = String::from("{end}, {{ignored} {middle} and {start}\n{start}!");
 
-        let mut values = HashMap::new();
-        values.insert(String::from("end"), String::from("world"));
+        assert!(signature(&before) == signature(&after));
+    }
 
-        assert!(render(&before, &values) == Ok(after));
+    #[test]
+    fn signature_differs_by_placeholders() {
+        let before = String::from("{start} {middle} {end}");
+
+        assert!(signature(&before) != signature("{start} {end}"));
+        assert!(signature(&before) != signature("{start} {middle} {finish}"));
+        assert!(signature("{ab} {c}") != signature("{a} {bc}"));
     }
 
     #[test]
-    fn middle() {
-        let before = String::from("Hello {middle} world");
-        let after = String::from("Hello beautiful world");
+    fn signature_is_stable() {
+        assert!(signature("Hello world") == 0xcbf2_9ce4_8422_2325);
+        assert!(signature("{a}") == 0x089b_e207_b544_f1e4);
+    }
 
+    #[test]
+    fn values_with_placeholders_flagged() {
         let mut values = HashMap::new();
-        values.insert(String::from("middle"), String::from("beautiful"));
+        values.insert(String::from("start"), String::from("Hello {name}"));
+        values.insert(
+            String::from("middle"),
+            String::from("{{ignored} \\{escaped}"),
+        );
+        values.insert(String::from("end"), String::from("{name|upper} world"));
+        values.insert(String::from("name"), String::from("Homer"));
 
-        assert!(render(&before, &values) == Ok(after));
+        assert!(
+            values_contain_placeholders(&values)
+                == vec![String::from("end"), String::from("start")]
+        );
     }
 
     #[test]
-    fn hello_beautiful_world() {
+    fn values_without_placeholders() {
+        let values = [("start", "Hello"), ("end", "{ world }")];
+
+        assert!(values_contain_placeholders(values) == Vec::<String>::new());
+    }
+
+    #[test]
+    fn unused_values_sorted() {
         let before = String::from("{start} {middle} {end}");
-        let after = String::from("Hello beautiful world");
 
         let mut values = HashMap::new();
         values.insert(String::from("start"), String::from("Hello"));
-        values.insert(String::from("middle"), String::from("beautiful"));
+        values.insert(String::from("zebra"), String::from("stripes"));
         values.insert(String::from("end"), String::from("world"));
+        values.insert(String::from("apple"), String::from("pie"));
 
-        assert!(render(&before, &values) == Ok(after));
+        assert!(
+            unused_values(&before, &values) == vec![String::from("apple"), String::from("zebra")]
+        );
+        assert!(unused_values(&before, [("start", "Hello")]) == Vec::<String>::new());
     }
 
     #[test]
-    fn multi_line_hello() {
-        let before = String::from("{start} is a\n{middle} test to see\nif the regex {end}");
-        let after = String::from("This is a\nmulti-line test to see\nif the regex works");
+    fn lint_unprovided() {
+        let templates = [
+            "{start} {middle} {end}",
+            "Nothing to see here",
+            "{end} {zebra} {apple} {zebra} \\{escaped} {#if member}{inner}{/if}",
+            "{start}",
+        ];
 
-        let mut values = HashMap::new();
-        values.insert(String::from("start"), String::from("This"));
-        values.insert(String::from("middle"), String::from("multi-line"));
-        values.insert(String::from("end"), String::from("works"));
+        let provided: HashSet<String> = ["start", "middle", "end"]
+            .into_iter()
+            .map(String::from)
+            .collect();
 
-        assert!(render(&before, &values) == Ok(after));
+        assert!(
+            lint_templates(&templates, &provided)
+                == vec![
+                    (2, String::from("apple")),
+                    (2, String::from("inner")),
+                    (2, String::from("member")),
+                    (2, String::from("zebra")),
+                ]
+        );
+        assert!(lint_templates(&templates[..2], &provided).is_empty());
+        assert!(lint_templates(&[], &provided).is_empty());
     }
 
     #[test]
-    fn a_longer_test() {
+    fn lint_defaults() {
+        let provided: HashSet<String> = HashSet::from([String::from("start")]);
+
+        assert!(lint_templates(&["Dear {title=Customer} {name?}"], &provided).is_empty());
+        assert!(
+            lint_templates(&["{start} {title=Dear {name}} {end?}"], &provided)
+                == vec![(0, String::from("name"))]
+        );
+    }
+
+    #[test]
+    fn diagnostics_deterministic() {
+        let before = String::from("{zulu} {alpha} {mike} {alpha} {yankee}");
+        let names = [
+            "bravo", "yankee", "xray", "charlie", "whiskey", "delta", "victor", "echo",
+        ];
+
+        // Each map is seeded differently, so iterates in a different order
+        for _ in 0..20 {
+            let mut values = HashMap::new();
+
+            for name in names {
+                values.insert(String::from(name), String::from("{value}"));
+            }
+
+            assert!(
+                render_collect(&before, &values)
+                    == Err(CollectError::Missing(vec![
+                        String::from("zulu"),
+                        String::from("alpha"),
+                        String::from("mike"),
+                    ]))
+            );
+
+            assert!(
+                render_collect_offsets(&before, &values)
+                    == Err(CollectError::Missing(vec![
+                        (String::from("zulu"), 0),
+                        (String::from("alpha"), 7),
+                        (String::from("mike"), 15),
+                        (String::from("alpha"), 22),
+                    ]))
+            );
+
+            assert!(
+                render_debug(&before, &values)
+                    == Err(RenderError::MissingPlaceholder {
+                        name: String::from("zulu"),
+                        available: Some(vec![
+                            String::from("bravo"),
+                            String::from("charlie"),
+                            String::from("delta"),
+                            String::from("echo"),
+                            String::from("victor"),
+                            String::from("whiskey"),
+                            String::from("xray"),
+                            String::from("yankee"),
+                        ]),
+                    })
+            );
+
+            assert!(
+                unused_values(&before, &values)
+                    == vec![
+                        String::from("bravo"),
+                        String::from("charlie"),
+                        String::from("delta"),
+                        String::from("echo"),
+                        String::from("victor"),
+                        String::from("whiskey"),
+                        String::from("xray"),
+                    ]
+            );
+
+            assert!(values_contain_placeholders(&values).len() == names.len());
+            assert!(values_contain_placeholders(&values)
+                .windows(2)
+                .all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[test]
+    fn diff_added() {
+        let old = String::from("{start} world");
+        let new = String::from("{start} {middle} {end}");
+
+        assert!(
+            placeholder_diff(&old, &new)
+                == (vec![String::from("end"), String::from("middle")], vec![])
+        );
+    }
+
+    #[test]
+    fn diff_removed() {
+        let old = String::from("{start} {middle} {end}");
+        let new = String::from("{start} world");
+
+        assert!(
+            placeholder_diff(&old, &new)
+                == (vec![], vec![String::from("end"), String::from("middle")])
+        );
+    }
+
+    #[test]
+    fn diff_unchanged() {
+        let old = String::from("{start} {middle} {end}");
+        let new = String::from("{end}, {middle}, {start}!");
+
+        assert!(placeholder_diff(&old, &new) == (vec![], vec![]));
+    }
+
+    #[test]
+    fn diff_added_and_removed() {
+        let old = String::from("{start} {middle} world");
+        let new = String::from("{start} beautiful {end}");
+
+        assert!(
+            placeholder_diff(&old, &new)
+                == (vec![String::from("end")], vec![String::from("middle")])
+        );
+    }
+
+    #[test]
+    fn conflicting_filters_reported() {
+        let before = String::from(concat!(
+            r#"{start|upper} {middle|pad(4, "0")} {end}"#,
+            "\n",
+            r#"{end|truncate(3)} {middle|pad(4,"0")} {start|lower} {start|upper}"#,
+        ));
+
+        assert!(
+            conflicting_filters(&before)
+                == vec![
+                    (
+                        String::from("end"),
+                        vec![String::from(""), String::from("truncate(3)")]
+                    ),
+                    (
+                        String::from("start"),
+                        vec![String::from("lower"), String::from("upper")]
+                    ),
+                ]
+        );
+    }
+
+    #[test]
+    fn conflicting_filters_none() {
         let before =
-            String::from(format!("{}\n{}\n{}\n{}\n{}",
-            "No society can surely {fourth}e flourishing {first} happy, {third} which {second} far greater part {third} {second}",
-            "mem{fourth}ers are poor {first} misera{fourth}le. It is but equity, besides, that they who feed,",
-            "clothe, {first} lodge {second} whole body {third} {second} people, should have such a share {third} {second}",
-            "produce {third} their own la{fourth}our as to be themselves tolera{fourth}ly well fed, clothed, {first}",
-            "lodged."));
+            String::from("{start|upper} {middle} {end|pad(3)|upper}\n{start|upper} {middle}");
 
-        let after =
-            String::from(format!("{}\n{}\n{}\n{}\n{}",
-            "No society can surely be flourishing and happy, of which the far greater part of the",
-            "members are poor and miserable. It is but equity, besides, that they who feed,",
-            "clothe, and lodge the whole body of the people, should have such a share of the",
-            "produce of their own labour as to be themselves tolerably well fed, clothed, and",
-            "lodged."));
+        assert!(conflicting_filters(&before) == vec![]);
+    }
+
+    #[test]
+    fn placeholders_with_their_filters() {
+        let before = String::from(
+            "{x} {y|trim|lower}\n{x|upper} \\{z|upper} {#if w}{x}{/if} {y|trim|lower}",
+        );
+
+        assert!(
+            placeholders_with_filters(&before)
+                == vec![
+                    (
+                        String::from("x"),
+                        vec![String::new(), String::from("upper")]
+                    ),
+                    (String::from("y"), vec![String::from("trim|lower")]),
+                ]
+        );
+        assert!(placeholders_with_filters("No placeholders") == vec![]);
+    }
+
+    #[test]
+    fn rename_multiple() {
+        let before = String::from("{start} {middle} {end}\n{end} {middle} {start}");
+        let after = String::from("{start} {centre} {end}\n{end} {centre} {start}");
+
+        assert!(rename_placeholder(&before, "middle", "centre") == after);
+    }
+
+    #[test]
+    fn rename_ignores_escaped() {
+        let before = String::from("{{middle} {middle} {middle_name} w{{orld");
+        let after = String::from("{{middle} {centre} {middle_name} w{{orld");
+
+        assert!(rename_placeholder(&before, "middle", "centre") == after);
+    }
+
+    #[test]
+    fn rename_ignores_backslash_escaped() {
+        let before = String::from(r"\{middle} \\{middle} \\\{middle}");
+        let after = String::from(r"\{middle} \\{centre} \\\{middle}");
+
+        assert!(rename_placeholder(&before, "middle", "centre") == after);
+    }
+
+    #[test]
+    fn rename_keeps_filters() {
+        let before = String::from(r#"{middle|truncate(4)|pad(6, "-")} {middle}"#);
+        let after = String::from(r#"{centre|truncate(4)|pad(6, "-")} {centre}"#);
+
+        assert!(rename_placeholder(&before, "middle", "centre") == after);
+    }
+
+    #[test]
+    fn rename_quoted() {
+        let before = String::from(r#"{"first name"|upper} {first} {"first"}"#);
+        let after = String::from(r#"{given|upper} {first} {"first"}"#);
+
+        assert!(rename_placeholder(&before, "first name", "given") == after);
+
+        let after = String::from(r#"{"first name"|upper} {"given name"} {"given name"}"#);
+
+        assert!(rename_placeholder(&before, "first", "given name") == after);
+    }
+
+    #[test]
+    fn rename_blocks() {
+        let before = String::from(
+            r"{#if name}{name}{/if} {#each name}{name}{/each} \{#if name} {{#if name} {#if names}",
+        );
+        let after = String::from(
+            r"{#if n}{n}{/if} {#each n}{n}{/each} \{#if name} {{#if name} {#if names}",
+        );
+
+        assert!(rename_placeholder(&before, "name", "n") == after);
+
+        let after = String::from(
+            r#"{#if name}{"a name"}{/if} {#each name}{"a name"}{/each} \{#if name} {{#if name} {#if names}"#,
+        );
+
+        assert!(rename_placeholder(&before, "name", "a name") == after);
+    }
+
+    #[test]
+    fn rename_absent() {
+        let before = String::from("{start} {{middle} {end}");
+        let after = String::from("{start} {{middle} {end}");
+
+        assert!(rename_placeholder(&before, "middle", "centre") == after);
+    }
+
+    #[test]
+    fn debug_lists_available_keys() {
+        let before = String::from("{start} {middle} {end}");
 
         let mut values = HashMap::new();
-        values.insert(String::from("first"), String::from("and"));
-        values.insert(String::from("second"), String::from("the"));
-        values.insert(String::from("third"), String::from("of"));
-        values.insert(String::from("fourth"), String::from("b"));
-        values.insert(String::from("fifth"), String::from("these"));
-        values.insert(String::from("sixth"), String::from("last"));
-        values.insert(String::from("seventh"), String::from("ones"));
-        values.insert(String::from("eighth"), String::from("do"));
-        values.insert(String::from("ninth"), String::from("not"));
-        values.insert(String::from("tenth"), String::from("exist"));
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("end"), String::from("world"));
+        values.insert(String::from("beginning"), String::from("Hi"));
 
-        assert!(render(&before, &values) == Ok(after));
+        assert!(
+            render_debug(&before, &values)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("middle"),
+                    available: Some(vec![
+                        String::from("beginning"),
+                        String::from("end"),
+                        String::from("start"),
+                    ]),
+                })
+        );
+    }
+
+    #[test]
+    fn debug_keeps_other_errors() {
+        let mut values = HashMap::new();
+        values.insert(String::from("name"), String::from("Homer"));
+
+        assert!(
+            render_debug("{name|bogus}", &values)
+                == Err(RenderError::UnknownFilter {
+                    name: String::from("bogus")
+                })
+        );
+        assert!(
+            render_debug("{name|pad(x)}", &values)
+                == Err(RenderError::InvalidFilterArguments {
+                    name: String::from("pad")
+                })
+        );
+    }
+
+    #[test]
+    fn debug_renders_like_render() {
+        let before = String::from("{start} world");
+        let after = String::from("Hello world");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+
+        assert!(render_debug(&before, &values) == Ok(after));
+    }
+
+    // Placeholders and braces at the very start and end of the template, where there's no text
+    // on one side to tell escapes and placeholders apart
+    mod boundaries {
+        use super::*;
+
+        const VALUES: [(&str, &str); 3] = [("a", "1"), ("b", "2"), ("name", "Homer")];
+
+        // Render with each of the free functions, which all share the same scanner, checking
+        // they agree with each other
+        fn check(before: &str, after: &str) {
+            assert!(
+                render(before, &VALUES).as_deref() == Ok(after),
+                "{:?}",
+                before
+            );
+            assert!(
+                render_cow(before, &VALUES).as_deref() == Ok(after),
+                "{:?}",
+                before
+            );
+            assert!(
+                rendered_len(before, &VALUES) == Ok(after.len()),
+                "{:?}",
+                before
+            );
+            assert!(render_preview(before, &VALUES) == (String::from(after), Vec::new()));
+        }
+
+        #[test]
+        fn only_a_placeholder() {
+            check("{name}", "Homer");
+            assert!(placeholders("{name}") == ["name"]);
+            assert!(render("{name}", &[("other", "x")]) == Err(String::from("name")));
+        }
+
+        #[test]
+        fn lone_braces() {
+            check("{", "{");
+            check("}", "}");
+            check("{{", "{{");
+            check("}}", "}}");
+            check("}{", "}{");
+            check("{}", "{}");
+        }
+
+        #[test]
+        fn ending_with_an_opening_brace() {
+            check("x{", "x{");
+            check("{a}{", "1{");
+            check("{a}{b}{", "12{");
+            check("{a}{{", "1{{");
+        }
+
+        #[test]
+        fn starting_with_a_closing_brace() {
+            check("}x", "}x");
+            check("}{a}", "}1");
+            check("}}{a}", "}}1");
+            check("{a}}", "1}");
+        }
+
+        #[test]
+        fn placeholders_at_the_start() {
+            check("{a}{b}", "12");
+            check("{a}{b} ", "12 ");
+            check("{a}{a}{a}", "111");
+            check("{a}}}{b}{{", "1}}2{{");
+        }
+
+        #[test]
+        fn escapes_at_the_start() {
+            check("{{a}", "{{a}");
+            check("{{a}{b}", "{{a}2");
+            check("\\{a}{b}", "{a}2");
+            check("\\\\{a}{b}", "\\12");
+            check("{a}\\", "1\\");
+            check("\\", "\\");
+        }
     }
 }
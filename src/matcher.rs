@@ -0,0 +1,636 @@
+// Finding placeholders within template text, either with the `regex` crate when the
+// `regex-backend` feature is enabled, or with a hand-rolled scanner when it isn't. Both backends
+// match exactly the same syntax, so the rest of the crate can't tell which one it's using:
+//
+// * A placeholder name is either an identifier or any text other than quotes and braces within
+//   double quotes. Quoted names never split on `|`, so they can contain pipes
+// * A name can be followed by any number of `|name` or `|name(args)` filters, where the arguments
+//   are quoted strings or any text other than quotes, parentheses, and braces
+// * The filters can be followed by a format spec, `:` then an optional fill character other than a
+//   brace and alignment of `<`, `>`, or `^`, then a width, such as `:*^10`. The spec is matched as
+//   the end of the filter chain
+// * The filters can be followed by `?`, or by `=` then a default, which is any text other than
+//   braces, along with any text other than braces within a pair of braces, such as
+//   `{title=Dear {name}}`. The default is as short as possible, so it ends at the first closing
+//   delimiter after it
+// * With custom delimiters, a doubled opening delimiter escapes a literal one
+
+use std::ops::Range;
+
+pub(crate) use backend::Matcher;
+
+// What was matched within the template, as byte ranges into it
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Match {
+    // A doubled custom opening delimiter, which is a literal opening delimiter
+    Escape(Range<usize>),
+    // A placeholder, from its opening delimiter to its closing delimiter
+    Placeholder {
+        span: Range<usize>,
+        name: Range<usize>,
+        filters: Range<usize>,
+        // The default following `=`, which is empty when the placeholder is optional with `?`
+        default: Option<Range<usize>>,
+    },
+}
+
+impl Match {
+    // Where the match is within the template
+    pub(crate) fn span(&self) -> &Range<usize> {
+        match self {
+            Match::Escape(span) | Match::Placeholder { span, .. } => span,
+        }
+    }
+}
+
+// A placeholder whose name is composed around an inner placeholder, such as `{col_{n}}`
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Nested {
+    pub(crate) span: Range<usize>,
+    pub(crate) prefix: Range<usize>,
+    pub(crate) inner: Range<usize>,
+    pub(crate) suffix: Range<usize>,
+}
+
+#[cfg(feature = "regex-backend")]
+mod backend {
+    use super::{Match, Nested};
+    use lazy_static::lazy_static;
+    use regex::Regex;
+
+    lazy_static! {
+        // Compile these once at startup
+        static ref BRACES: Matcher = Matcher {
+            regex: Regex::new(&format!(
+                r"[{{]({})({}(?:{})?)(?:(\?)|=({}))?[}}]",
+                NAME, FILTER_CHAIN, SPEC, DEFAULT
+            ))
+            .unwrap(),
+        };
+        static ref DOUBLE_BRACES: Matcher = Matcher {
+            regex: Regex::new(&format!(
+                r"[{{][{{]({})({}(?:{})?)(?:(\?)|=({}))?[}}][}}]",
+                NAME, FILTER_CHAIN, SPEC, DEFAULT
+            ))
+            .unwrap(),
+        };
+        static ref NESTED: Regex = Regex::new(r"[{](\w+)[{](\w+)[}](\w*)[}]").unwrap();
+    }
+
+    const NAME: &str = r#"\w+|"[^"{}]+""#;
+
+    const FILTER_CHAIN: &str = r#"(?:\|\w+(?:\((?:"[^"]*"|[^"(){}])*\))?)*"#;
+
+    const SPEC: &str = r":(?:[^{}]?[<>^])?[0-9]+";
+
+    const DEFAULT: &str = r"(?:[^{}]|[{][^{}]*[}])*?";
+
+    #[derive(Clone, Debug)]
+    pub(crate) struct Matcher {
+        regex: Regex,
+    }
+
+    impl Matcher {
+        // Match placeholders in the form `{name}`
+        pub(crate) fn braces() -> &'static Matcher {
+            &BRACES
+        }
+
+        // Match placeholders in the form `{{name}}`
+        pub(crate) fn double_braces() -> &'static Matcher {
+            &DOUBLE_BRACES
+        }
+
+        // Match placeholders between custom delimiters, such as `<<name>>`, or a doubled opening
+        // delimiter escaping a literal one
+        pub(crate) fn delimiters(open: &str, close: &str) -> Matcher {
+            let open = regex::escape(open);
+            let close = regex::escape(close);
+
+            match Regex::new(&format!(
+                r"{}{}|{}({})({}(?:{})?)(?:(\?)|=({}))?{}",
+                open, open, open, NAME, FILTER_CHAIN, SPEC, DEFAULT, close
+            )) {
+                Ok(regex) => Matcher { regex },
+                Err(_) => panic!("at the disco"),
+            }
+        }
+
+        // Find each match from left to right, only as they're needed
+        pub(crate) fn matches<'t>(&'t self, template: &'t str) -> impl Iterator<Item = Match> + 't {
+            self.regex.captures_iter(template).map(|capture| {
+                match (capture.get(0), capture.get(1), capture.get(2)) {
+                    (Some(span), Some(name), Some(filters)) => Match::Placeholder {
+                        span: span.range(),
+                        name: name.range(),
+                        filters: filters.range(),
+                        default: match (capture.get(3), capture.get(4)) {
+                            (Some(optional), _) => Some(optional.end()..optional.end()),
+                            (None, default) => default.map(|default| default.range()),
+                        },
+                    },
+                    (Some(double), None, None) => Match::Escape(double.range()),
+                    _ => panic!("at the disco"),
+                }
+            })
+        }
+    }
+
+    pub(crate) fn nested(template: &str) -> Vec<Nested> {
+        NESTED
+            .captures_iter(template)
+            .map(|capture| {
+                match (
+                    capture.get(0),
+                    capture.get(1),
+                    capture.get(2),
+                    capture.get(3),
+                ) {
+                    (Some(span), Some(prefix), Some(inner), Some(suffix)) => Nested {
+                        span: span.range(),
+                        prefix: prefix.range(),
+                        inner: inner.range(),
+                        suffix: suffix.range(),
+                    },
+                    _ => panic!("at the disco"),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(feature = "regex-backend"))]
+mod backend {
+    use super::{Match, Nested};
+    use crate::template::is_word;
+    use std::borrow::Cow;
+    use std::ops::Range;
+
+    // Where a placeholder's name, filter chain, and closing delimiter end, along with its default
+    type Placeholder = (usize, usize, Option<Range<usize>>, usize);
+
+    // Where a filter chain and closing delimiter end, along with the default between them
+    type Chain = (usize, Option<Range<usize>>, usize);
+
+    static BRACES: Matcher = Matcher {
+        open: Cow::Borrowed("{"),
+        close: Cow::Borrowed("}"),
+        doubled_open: false,
+    };
+
+    static DOUBLE_BRACES: Matcher = Matcher {
+        open: Cow::Borrowed("{{"),
+        close: Cow::Borrowed("}}"),
+        doubled_open: false,
+    };
+
+    #[derive(Clone, Debug)]
+    pub(crate) struct Matcher {
+        open: Cow<'static, str>,
+        close: Cow<'static, str>,
+        // Whether a doubled opening delimiter is a literal opening delimiter
+        doubled_open: bool,
+    }
+
+    impl Matcher {
+        // Match placeholders in the form `{name}`
+        pub(crate) fn braces() -> &'static Matcher {
+            &BRACES
+        }
+
+        // Match placeholders in the form `{{name}}`
+        pub(crate) fn double_braces() -> &'static Matcher {
+            &DOUBLE_BRACES
+        }
+
+        // Match placeholders between custom delimiters, such as `<<name>>`, or a doubled opening
+        // delimiter escaping a literal one
+        pub(crate) fn delimiters(open: &str, close: &str) -> Matcher {
+            Matcher {
+                open: Cow::Owned(open.to_string()),
+                close: Cow::Owned(close.to_string()),
+                doubled_open: true,
+            }
+        }
+
+        // Find each match from left to right, only as they're needed, trying every opening
+        // delimiter in turn as the start of a match, the same as a regex
+        pub(crate) fn matches<'t>(&'t self, template: &'t str) -> impl Iterator<Item = Match> + 't {
+            let mut cursor = 0;
+
+            std::iter::from_fn(move || {
+                while let Some(offset) = template[cursor..].find(self.open.as_ref()) {
+                    let start = cursor + offset;
+                    let after = start + self.open.len();
+
+                    if self.doubled_open && template[after..].starts_with(self.open.as_ref()) {
+                        cursor = after + self.open.len();
+                        return Some(Match::Escape(start..cursor));
+                    }
+
+                    if let Some((name_end, filters_end, default, end)) =
+                        self.placeholder(template, after)
+                    {
+                        cursor = end;
+
+                        return Some(Match::Placeholder {
+                            span: start..end,
+                            name: after..name_end,
+                            filters: name_end..filters_end,
+                            default,
+                        });
+                    }
+
+                    cursor = start + template[start..].chars().next().map_or(1, char::len_utf8);
+                }
+
+                None
+            })
+        }
+
+        // Match the rest of a placeholder after its opening delimiter, returning where its name,
+        // filter chain, and closing delimiter end, along with its default
+        fn placeholder(&self, template: &str, start: usize) -> Option<Placeholder> {
+            if let Some(quoted) = template[start..].strip_prefix('"') {
+                let len = quoted.find(['"', '{', '}'])?;
+
+                if len == 0 || !quoted[len..].starts_with('"') {
+                    return None;
+                }
+
+                let name_end = start + len + 2;
+
+                return self
+                    .chain(template, name_end)
+                    .map(|(filters_end, default, end)| (name_end, filters_end, default, end));
+            }
+
+            // An identifier is as long as possible, only giving up characters for a closing
+            // delimiter beginning with identifier characters
+            word_ends(template, start)
+                .into_iter()
+                .rev()
+                .find_map(|name_end| {
+                    self.chain(template, name_end)
+                        .map(|(filters_end, default, end)| (name_end, filters_end, default, end))
+                })
+        }
+
+        // Match as many filters as possible followed by the closing delimiter, returning where the
+        // filter chain and closing delimiter end, along with the default
+        fn chain(&self, template: &str, start: usize) -> Option<Chain> {
+            if template[start..].starts_with('|') {
+                for name_end in word_ends(template, start + 1).into_iter().rev() {
+                    if let Some(found) =
+                        arguments(template, name_end).and_then(|end| self.chain(template, end))
+                    {
+                        return Some(found);
+                    }
+
+                    if let Some(found) = self.chain(template, name_end) {
+                        return Some(found);
+                    }
+                }
+            }
+
+            spec_ends(template, start)
+                .into_iter()
+                .chain([start])
+                .find_map(|filters_end| {
+                    self.default(template, filters_end)
+                        .map(|(default, end)| (filters_end, default, end))
+                })
+        }
+
+        // Match an optional `?` or `=` then a default, followed by the closing delimiter,
+        // returning the default and where the closing delimiter ends
+        fn default(&self, template: &str, start: usize) -> Option<(Option<Range<usize>>, usize)> {
+            let close = |at: usize| {
+                template[at..]
+                    .starts_with(self.close.as_ref())
+                    .then_some(at + self.close.len())
+            };
+
+            if let Some(end) = expect(template, start, '?').and_then(close) {
+                return Some((Some(start + 1..start + 1), end));
+            }
+
+            if let Some(default_start) = expect(template, start, '=') {
+                let mut at = default_start;
+
+                // The default is as short as possible, so it ends at the first closing delimiter
+                loop {
+                    if let Some(end) = close(at) {
+                        return Some((Some(default_start..at), end));
+                    }
+
+                    match template[at..].chars().next() {
+                        Some('{') => match template[at + 1..].find(['{', '}']) {
+                            Some(offset) if template[at + 1 + offset..].starts_with('}') => {
+                                at += offset + 2
+                            }
+                            _ => break,
+                        },
+                        Some('}') | None => break,
+                        Some(c) => at += c.len_utf8(),
+                    }
+                }
+            }
+
+            close(start).map(|end| (None, end))
+        }
+    }
+
+    pub(crate) fn nested(template: &str) -> Vec<Nested> {
+        let mut nested = Vec::new();
+        let mut cursor = 0;
+
+        while let Some(offset) = template[cursor..].find('{') {
+            let start = cursor + offset;
+
+            match nested_at(template, start) {
+                Some(found) => {
+                    cursor = found.span.end;
+                    nested.push(found);
+                }
+                None => cursor = start + 1,
+            }
+        }
+
+        nested
+    }
+
+    fn nested_at(template: &str, start: usize) -> Option<Nested> {
+        let prefix_end = word_ends(template, start + 1).pop()?;
+        let inner_start = expect(template, prefix_end, '{')?;
+        let inner_end = word_ends(template, inner_start).pop()?;
+        let suffix_start = expect(template, inner_end, '}')?;
+        let suffix_end = word_ends(template, suffix_start)
+            .pop()
+            .unwrap_or(suffix_start);
+        let end = expect(template, suffix_end, '}')?;
+
+        Some(Nested {
+            span: start..end,
+            prefix: start + 1..prefix_end,
+            inner: inner_start..inner_end,
+            suffix: suffix_start..suffix_end,
+        })
+    }
+
+    // Where `c` ends, if it's found at `at`
+    fn expect(template: &str, at: usize, c: char) -> Option<usize> {
+        template[at..].starts_with(c).then_some(at + c.len_utf8())
+    }
+
+    // Where each identifier character starting at `start` ends, from shortest to longest
+    fn word_ends(template: &str, start: usize) -> Vec<usize> {
+        template[start..]
+            .char_indices()
+            .take_while(|(_, c)| is_word(*c))
+            .map(|(offset, c)| start + offset + c.len_utf8())
+            .collect()
+    }
+
+    // Where each possible format spec starting at `start` ends, such as `:*^10`, from the fill and
+    // alignment being taken as early as possible, then from longest to shortest width
+    fn spec_ends(template: &str, start: usize) -> Vec<usize> {
+        let rest = match template[start..].strip_prefix(':') {
+            Some(rest) => rest,
+            None => return Vec::new(),
+        };
+
+        let mut chars = rest
+            .char_indices()
+            .map(|(offset, c)| (start + 1 + offset, c));
+        let first = chars.next();
+        let second = chars.next();
+
+        let mut width_starts = Vec::new();
+
+        if let (Some((_, fill)), Some((at, align))) = (first, second) {
+            if fill != '{' && fill != '}' && matches!(align, '<' | '>' | '^') {
+                width_starts.push(at + 1);
+            }
+        }
+
+        if let Some((at, '<' | '>' | '^')) = first {
+            width_starts.push(at + 1);
+        }
+
+        width_starts.push(start + 1);
+
+        width_starts
+            .into_iter()
+            .flat_map(|width_start| {
+                template[width_start..]
+                    .char_indices()
+                    .take_while(|(_, c)| c.is_ascii_digit())
+                    .map(|(offset, _)| width_start + offset + 1)
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .rev()
+            })
+            .collect()
+    }
+
+    // Match a filter's parenthesised arguments, returning where they end
+    fn arguments(template: &str, start: usize) -> Option<usize> {
+        let mut chars = template[start..].strip_prefix('(')?.char_indices();
+
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '"' => {
+                    chars.find(|(_, c)| *c == '"')?;
+                }
+                ')' => return Some(start + offset + 2),
+                '(' | '{' | '}' => return None,
+                _ => {}
+            }
+        }
+
+        None
+    }
+}
+
+pub(crate) use backend::nested;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(matcher: &Matcher, template: &str) -> Vec<String> {
+        matcher
+            .matches(template)
+            .map(|found| match found {
+                Match::Escape(span) => format!("escape {}", &template[span]),
+                Match::Placeholder {
+                    span,
+                    name,
+                    filters,
+                    default: None,
+                } => format!(
+                    "{} {} {}",
+                    &template[span], &template[name], &template[filters]
+                ),
+                Match::Placeholder {
+                    span,
+                    name,
+                    filters,
+                    default: Some(default),
+                } => format!(
+                    "{} {} {} default {}",
+                    &template[span], &template[name], &template[filters], &template[default]
+                ),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn braces() {
+        let template =
+            r#"{a} {{b} {"c d"|x} {e|pad(4, "(}")|rpad(2)} {f|g(} {h|i()} {"j} {} {k{l}"#;
+
+        assert!(
+            placeholders(Matcher::braces(), template)
+                == [
+                    "{a} a ",
+                    "{b} b ",
+                    r#"{"c d"|x} "c d" |x"#,
+                    r#"{e|pad(4, "(}")|rpad(2)} e |pad(4, "(}")|rpad(2)"#,
+                    "{h|i()} h |i()",
+                    "{l} l ",
+                ]
+        );
+    }
+
+    #[test]
+    fn specs() {
+        let template = "{a:10} {b:>4} {c|x:*^12} {d:>>3} {e::<3} {f:} {g:>} {h:{<3} {i:x} {j:|^2}";
+
+        assert!(
+            placeholders(Matcher::braces(), template)
+                == [
+                    "{a:10} a :10",
+                    "{b:>4} b :>4",
+                    "{c|x:*^12} c |x:*^12",
+                    "{d:>>3} d :>>3",
+                    "{e::<3} e ::<3",
+                    "{j:|^2} j :|^2",
+                ]
+        );
+
+        assert!(
+            placeholders(&Matcher::delimiters("<<", ">>"), "<<a:>>5>> <<b:>5>>")
+                == ["<<a:>>5>> a :>>5", "<<b:>5>> b :>5"]
+        );
+    }
+
+    #[test]
+    fn defaults() {
+        let template =
+            "{a?} {b=Dear {c}} {d|x:>4=-} {e=5x} {f={g=h}} {i={{j}}} {k=l}m} {n?=o} {p:5=0}";
+
+        assert!(
+            placeholders(Matcher::braces(), template)
+                == [
+                    "{a?} a  default ",
+                    "{b=Dear {c}} b  default Dear {c}",
+                    "{d|x:>4=-} d |x:>4 default -",
+                    "{e=5x} e  default 5x",
+                    "{f={g=h}} f  default {g=h}",
+                    "{j} j ",
+                    "{k=l} k  default l",
+                    "{p:5=0} p :5 default 0",
+                ]
+        );
+
+        assert!(
+            placeholders(Matcher::double_braces(), "{{a={b}}} {{c?}} {{d=}e}}")
+                == ["{{a={b}}} a  default {b}", "{{c?}} c  default "]
+        );
+
+        assert!(
+            placeholders(
+                &Matcher::delimiters("<<", ">>"),
+                "<<a=b>c>> <<d?>> <<e={f}>>"
+            ) == [
+                "<<a=b>c>> a  default b>c",
+                "<<d?>> d  default ",
+                "<<e={f}>> e  default {f}"
+            ]
+        );
+    }
+
+    #[test]
+    fn non_ascii_names() {
+        let template = "{a²} {e\u{301}} {x\u{203f}y} {\u{661}} {\u{2160}}";
+
+        assert!(
+            placeholders(Matcher::braces(), template)
+                == [
+                    "{e\u{301}} e\u{301} ",
+                    "{x\u{203f}y} x\u{203f}y ",
+                    "{\u{661}} \u{661} ",
+                    "{\u{2160}} \u{2160} ",
+                ]
+        );
+    }
+
+    #[cfg(feature = "regex-backend")]
+    #[test]
+    fn is_word_matches_regex() {
+        let word = regex::Regex::new(r"^\w$").unwrap();
+
+        for c in (0..=0x10ffff).filter_map(char::from_u32) {
+            assert!(crate::template::is_word(c) == word.is_match(c.encode_utf8(&mut [0; 4])));
+        }
+    }
+
+    #[test]
+    fn double_braces() {
+        assert!(
+            placeholders(Matcher::double_braces(), "{name} {{{name|x}}} {{a}")
+                == ["{{name|x}} name |x"]
+        );
+    }
+
+    #[test]
+    fn delimiters() {
+        assert!(
+            placeholders(
+                &Matcher::delimiters("<<", ">>"),
+                "<<a>> <<<<b>> <<<c|d>> <<e"
+            ) == ["<<a>> a ", "escape <<<<", "<<c|d>> c |d"]
+        );
+    }
+
+    #[test]
+    fn delimiters_beginning_with_identifier_characters() {
+        assert!(
+            placeholders(&Matcher::delimiters("ab", "yz"), "abnameyz abn|fyz abnyzyz")
+                == ["abnameyz name ", "abn|fyz n |f", "abnyzyz nyz "]
+        );
+    }
+
+    #[test]
+    fn nested_placeholders() {
+        let template = "{col_{n}} {{a}} {x_{y}_z} {p_{q}";
+
+        assert!(
+            nested(template)
+                .into_iter()
+                .map(|found| (
+                    &template[found.span],
+                    &template[found.prefix],
+                    &template[found.inner],
+                    &template[found.suffix]
+                ))
+                .collect::<Vec<_>>()
+                == [
+                    ("{col_{n}}", "col_", "n", ""),
+                    ("{x_{y}_z}", "x_", "y", "_z")
+                ]
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::renderer::resolve_from;
+use crate::{AsValues, RenderError};
+
+/// Render a template written with Python's `%`-formatting placeholders, such as `%(name)s`
+///
+/// This suits templates ported from Python, without rewriting them to use `{name}`. Each
+/// `%(name)s` is replaced with the value of `name`, and `%%` is a literal `%`. Braces are left as
+/// they are. Only the `s` conversion is supported, so flags, widths, precisions, and other
+/// conversions such as `%(age)d` or a bare `%s` are an error rather than being left in the output.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `%(name)s`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// # Returns
+///
+/// `Ok(output)` is the template text with all its placeholders replaced with their corresponding
+/// placeholder values
+///
+/// `Err(name)` is the name of the first placeholder missing from `values`, or
+/// `RenderError::UnsupportedConversion`'s message for the first unsupported conversion
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render_python;
+///
+/// fn main() {
+///   let values = [("name", "Homer"), ("score", "99")];
+///
+///   assert!(render_python("%(name)s scored %(score)s%% {wow}", &values)
+///     == Ok(String::from("Homer scored 99% {wow}")));
+///   assert!(render_python("%(name)s is %(age)d", &values)
+///     == Err(String::from("unsupported conversion: %(age)d")));
+/// }
+/// ```
+pub fn render_python<T: AsValues>(template: &str, values: T) -> Result<String, String> {
+    let mut lookup = resolve_from(&values);
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('%') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("%%") {
+            output.push('%');
+            rest = after;
+            continue;
+        }
+
+        // The spec runs up to and including the conversion character after the name, if any
+        let name_end = match rest.strip_prefix("%(").and_then(|after| after.find(')')) {
+            Some(end) => end + 2,
+            None => return Err(unsupported(rest, 1)),
+        };
+
+        if !rest[name_end + 1..].starts_with('s') {
+            return Err(unsupported(rest, name_end + 1));
+        }
+
+        let value = lookup(&rest[2..name_end]).map_err(|error| error.to_string())?;
+        output.push_str(&value);
+        rest = &rest[name_end + 2..];
+    }
+
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+// The error for the unsupported spec at the start of `rest`, taking the character after `len`
+// bytes as its conversion
+fn unsupported(rest: &str, len: usize) -> String {
+    let end = rest[len..]
+        .chars()
+        .next()
+        .map_or(rest.len(), |c| len + c.len_utf8());
+
+    RenderError::UnsupportedConversion {
+        spec: rest[..end].to_string(),
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn named_placeholders() {
+        let mut values = HashMap::new();
+        values.insert("greet", "Hello");
+        values.insert("name", "Homer");
+        values.insert("food", "Donuts");
+
+        assert!(
+            render_python("%(greet)s %(name)s, %(greet)s! {food} %(food)s", &values)
+                == Ok(String::from("Hello Homer, Hello! {food} Donuts"))
+        );
+        assert!(render_python("%(nobody)s", &values) == Err(String::from("nobody")));
+        assert!(render_python("", &values) == Ok(String::new()));
+    }
+
+    #[test]
+    fn percent_passthrough() {
+        let values = [("rate", "15")];
+
+        assert!(render_python("%(rate)s%%", &values) == Ok(String::from("15%")));
+        assert!(render_python("100%% %%(rate)s", &values) == Ok(String::from("100% %(rate)s")));
+    }
+
+    #[test]
+    fn unsupported_conversions() {
+        let values = [("age", "39")];
+
+        for (template, spec) in [
+            ("%(age)d", "%(age)d"),
+            ("%(age)5s", "%(age)5"),
+            ("%(age)", "%(age)"),
+            ("%(age", "%("),
+            ("%s", "%s"),
+            ("50%", "%"),
+            ("%é", "%é"),
+        ] {
+            assert!(
+                render_python(template, &values)
+                    == Err(RenderError::UnsupportedConversion {
+                        spec: String::from(spec)
+                    }
+                    .to_string())
+            );
+        }
+    }
+}
@@ -0,0 +1,2675 @@
+use crate::filters;
+use crate::matcher::Matcher;
+use crate::template::{BlockKind, ParseOptions, Segment};
+use crate::{AsValues, LongName, RenderError, Template};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A configurable renderer for when the defaults of `render()` aren't enough
+///
+/// Options are chained on from `Renderer::new()`, which renders exactly like `render()`.
+///
+/// # Example
+///
+/// ```
+/// use placeholder::Renderer;
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///   let renderer = Renderer::new().collapse_whitespace(true);
+///
+///   let mut values = HashMap::new();
+///   values.insert(String::from("greet"), String::from("Hello"));
+///   values.insert(String::from("title"), String::from(""));
+///   values.insert(String::from("name"), String::from("Homer"));
+///
+///   assert!(renderer.render("{greet} {title} {name}", &values)
+///     == Ok(String::from("Hello Homer")));
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Renderer {
+    collapse_whitespace: bool,
+    drop_empty_lines: bool,
+    value_escapes: bool,
+    newlines: Option<Newline>,
+    on_missing: OnMissing,
+    missing_blocks_absent: bool,
+    missing_marker: Option<(String, String)>,
+    yes_no_labels: Option<(String, String)>,
+    unknown_filter: UnknownFilter,
+    panic_on_missing: bool,
+    reject_duplicates: bool,
+    constants: HashMap<String, String>,
+    escape_mode: EscapeMode,
+    middlewares: Vec<Middleware>,
+    max_depth: Option<usize>,
+    max_value_len: Option<usize>,
+    timeout: Option<Duration>,
+    parse_options: ParseOptions,
+}
+
+impl Renderer {
+    /// Create a renderer with the default options
+    pub fn new() -> Renderer {
+        Renderer::default()
+    }
+
+    /// Collapse the whitespace around placeholders that resolve to an empty value
+    ///
+    /// When a placeholder resolves to an empty value and is either at the very start of the output
+    /// or directly after a space or tab, any spaces and tabs directly after the placeholder are
+    /// removed. For example, `"Hello {middle} world"` renders as `"Hello world"` rather than
+    /// `"Hello  world"` when `middle` is empty. Newlines are never removed.
+    ///
+    /// Defaults to `false`
+    pub fn collapse_whitespace(mut self, collapse: bool) -> Renderer {
+        self.collapse_whitespace = collapse;
+        self
+    }
+
+    /// Remove lines left empty only because their placeholders resolved to empty values
+    ///
+    /// A line made up of nothing but placeholders, spaces, and tabs is removed along with its
+    /// newline when every placeholder on it resolves to an empty value, such as an optional line
+    /// of an address. Lines that are empty or blank in the template itself are kept, as are lines
+    /// with any literal text or a value that isn't empty.
+    ///
+    /// Defaults to `false`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Renderer;
+    ///
+    /// fn main() {
+    ///   let renderer = Renderer::new().drop_empty_lines(true);
+    ///   let values = [("name", "Homer"), ("unit", ""), ("street", "742 Evergreen Tce")];
+    ///
+    ///   assert!(renderer.render("{name}\n\n  {unit}\n{street}\n", &values)
+    ///     == Ok(String::from("Homer\n\n742 Evergreen Tce\n")));
+    /// }
+    /// ```
+    pub fn drop_empty_lines(mut self, drop: bool) -> Renderer {
+        self.drop_empty_lines = drop;
+        self
+    }
+
+    /// Interpret backslash escape sequences within placeholder values before they're inserted
+    ///
+    /// This suits values from single line sources, such as config files, that encode newlines as
+    /// `\n`. The supported escapes are `\n` for a newline, `\r` for a carriage return, `\t` for a
+    /// tab, and `\\` for a backslash. Any other backslash is left as is. Only values are affected,
+    /// never the template's literal text.
+    ///
+    /// Defaults to `false`
+    pub fn value_escapes(mut self, value_escapes: bool) -> Renderer {
+        self.value_escapes = value_escapes;
+        self
+    }
+
+    /// Normalize the line endings of the template's literal text
+    ///
+    /// Every line ending within the template's literal text, whether `\r\n` or `\n`, is written
+    /// as `newline`. This suits rendering templates authored on one platform for another. Values
+    /// are never altered.
+    ///
+    /// Defaults to leaving line endings as they are
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::{Newline, Renderer};
+    ///
+    /// fn main() {
+    ///   let renderer = Renderer::new().normalize_newlines(Newline::Lf);
+    ///
+    ///   assert!(renderer.render("<h1>{name}</h1>\r\n<p>Bye</p>\r\n", &[("name", "Homer")])
+    ///     == Ok(String::from("<h1>Homer</h1>\n<p>Bye</p>\n")));
+    /// }
+    /// ```
+    pub fn normalize_newlines(mut self, newline: Newline) -> Renderer {
+        self.newlines = Some(newline);
+        self
+    }
+
+    /// Choose what happens to placeholders whose values are missing
+    ///
+    /// See `OnMissing` for the choices.
+    ///
+    /// Defaults to `OnMissing::Error`
+    pub fn on_missing(mut self, on_missing: OnMissing) -> Renderer {
+        self.on_missing = on_missing;
+        self
+    }
+
+    /// Treat a block whose value is missing as absent, leaving it out even with
+    /// `OnMissing::Error`
+    ///
+    /// A block's name is often a flag that's only given when it's set, such as `{#if admin}`, while
+    /// a missing value within the output is a mistake. With this on, a block whose value is
+    /// missing is left out just like one whose value is empty, while a missing placeholder is
+    /// still handled by `on_missing()`. Without it, a missing block value is handled the same as
+    /// any other missing value. Defaults to `false`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::{RenderError, Renderer};
+    ///
+    /// fn main() {
+    ///   let renderer = Renderer::new().missing_blocks_absent(true);
+    ///
+    ///   assert!(renderer.render("{#if admin}Admin {/if}{name}", &[("name", "Homer")])
+    ///     == Ok(String::from("Homer")));
+    ///   assert!(renderer.render("{#if admin}Admin {/if}{name}", &[("admin", "yes")])
+    ///     == Err(RenderError::MissingPlaceholder {
+    ///       name: String::from("name"),
+    ///       available: None,
+    ///     }));
+    /// }
+    /// ```
+    pub fn missing_blocks_absent(mut self, missing_blocks_absent: bool) -> Renderer {
+        self.missing_blocks_absent = missing_blocks_absent;
+        self
+    }
+
+    /// Set the marker wrapped around the names of missing placeholders by `OnMissing::Mark`
+    ///
+    /// Defaults to `[[` and `]]`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::{OnMissing, Renderer};
+    ///
+    /// fn main() {
+    ///   let renderer = Renderer::new().on_missing(OnMissing::Mark).missing_marker("<<", "?>>");
+    ///
+    ///   assert!(renderer.render("<h1>{greet} {name}</h1>", &[("greet", "Hello")])
+    ///     == Ok(String::from("<h1>Hello <<name?>></h1>")));
+    /// }
+    /// ```
+    pub fn missing_marker(mut self, prefix: &str, suffix: &str) -> Renderer {
+        self.missing_marker = Some((prefix.to_string(), suffix.to_string()));
+        self
+    }
+
+    /// Set the labels the `yesno` filter renders booleans as
+    ///
+    /// Defaults to `Yes` and `No`
+    pub fn yes_no_labels(mut self, yes: &str, no: &str) -> Renderer {
+        self.yes_no_labels = Some((yes.to_string(), no.to_string()));
+        self
+    }
+
+    /// Choose what happens to filters that don't exist, such as `{name|notregistered}`
+    ///
+    /// `UnknownFilter::Error` fails with `RenderError::UnknownFilter`, while
+    /// `UnknownFilter::Ignore` skips the filter, as if it weren't in the filter chain. Ignoring
+    /// unknown filters suits templates shared across environments that don't all have the same
+    /// filters, such as filters only available with an optional feature. Filters that do exist
+    /// but are given invalid arguments are always an error.
+    ///
+    /// Defaults to `UnknownFilter::Error`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::{Renderer, UnknownFilter};
+    ///
+    /// fn main() {
+    ///   let renderer = Renderer::new().unknown_filter(UnknownFilter::Ignore);
+    ///
+    ///   assert!(renderer.render("{name|notregistered|pad(7)}", &[("name", "Homer")])
+    ///     == Ok(String::from("  Homer")));
+    /// }
+    /// ```
+    pub fn unknown_filter(mut self, unknown_filter: UnknownFilter) -> Renderer {
+        self.unknown_filter = unknown_filter;
+        self
+    }
+
+    /// Escape every placeholder value for the kind of output being rendered
+    ///
+    /// With `EscapeMode::Html`, values are escaped after any filters and middlewares, so that
+    /// values can't inject markup. Values that are known to be safe can opt out with the `raw`
+    /// filter, such as `{body|raw}`. Literal text from the template is never escaped. The mode is
+    /// usually chosen from the content type of the output, such as `Html` for `text/html` and
+    /// `None` for `text/plain`. Defaults to `EscapeMode::None`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::{EscapeMode, Renderer};
+    ///
+    /// fn main() {
+    ///   let renderer = Renderer::new().auto_escape(EscapeMode::Html);
+    ///   let values = [("name", "<b>Homer</b>")];
+    ///
+    ///   assert!(renderer.render("<p>{name}</p>", &values)
+    ///     == Ok(String::from("<p>&lt;b&gt;Homer&lt;/b&gt;</p>")));
+    ///   assert!(renderer.render("<p>{name|raw}</p>", &values)
+    ///     == Ok(String::from("<p><b>Homer</b></p>")));
+    /// }
+    /// ```
+    pub fn auto_escape(mut self, escape_mode: EscapeMode) -> Renderer {
+        self.escape_mode = escape_mode;
+        self
+    }
+
+    /// Add a constant placeholder value, used whenever the placeholder values don't have one
+    ///
+    /// This suits app-wide values such as a version number or build date, so they don't need to
+    /// be in every render's values. A value given to a render takes precedence over a constant of
+    /// the same name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Renderer;
+    ///
+    /// fn main() {
+    ///   let renderer = Renderer::new().constant("version", "1.1.4");
+    ///
+    ///   assert!(renderer.render("{name} v{version}", &[("name", "Homer")])
+    ///     == Ok(String::from("Homer v1.1.4")));
+    ///   assert!(renderer.render("{name} v{version}", &[("name", "Homer"), ("version", "2.0")])
+    ///     == Ok(String::from("Homer v2.0")));
+    /// }
+    /// ```
+    pub fn constant(mut self, name: &str, value: &str) -> Renderer {
+        self.constants.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Fail when the placeholder values give more than one value for the same placeholder
+    ///
+    /// Slices and arrays of `(&str, &str)` pairs can repeat a name, in which case the last pair
+    /// wins. Turning this on makes a repeated name fail with `RenderError::DuplicateValue`
+    /// instead, whether or not the template uses it, so that data bugs aren't silently hidden.
+    /// Values such as maps can't repeat a name, so they're unaffected.
+    ///
+    /// Defaults to `false`
+    pub fn reject_duplicates(mut self, reject: bool) -> Renderer {
+        self.reject_duplicates = reject;
+        self
+    }
+
+    /// Panic instead of returning an error when a placeholder value is missing
+    ///
+    /// This makes failing templates loud during development and in tests. The panic message names
+    /// the missing placeholder. Missing values handled by `on_missing()` don't panic. The same
+    /// goes for the name of an `{#if name}` or `{#each name}` block, unless
+    /// `missing_blocks_absent()` leaves the block out instead.
+    ///
+    /// Defaults to `false`
+    pub fn panic_on_missing(mut self, panic: bool) -> Renderer {
+        self.panic_on_missing = panic;
+        self
+    }
+
+    /// Add a middleware that transforms every placeholder value before it's inserted
+    ///
+    /// The middleware is called with the placeholder's name and value, and returns the value to
+    /// insert. Middlewares are applied in the order they're added, each receiving the value
+    /// returned by the one before it. This suits concerns that apply to every value, such as
+    /// trimming or escaping.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Renderer;
+    /// use std::collections::HashMap;
+    ///
+    /// fn main() {
+    ///   let renderer = Renderer::new()
+    ///     .middleware(|_, value| value.trim().to_string())
+    ///     .middleware(|_, value| value.replace('<', "&lt;").replace('>', "&gt;"));
+    ///
+    ///   let mut values = HashMap::new();
+    ///   values.insert(String::from("name"), String::from("  <Homer>  "));
+    ///
+    ///   assert!(renderer.render("<h1>{name}</h1>", &values)
+    ///     == Ok(String::from("<h1>&lt;Homer&gt;</h1>")));
+    /// }
+    /// ```
+    pub fn middleware<F>(mut self, middleware: F) -> Renderer
+    where
+        F: Fn(&str, &str) -> String + Send + Sync + 'static,
+    {
+        self.middlewares.push(Middleware(Arc::new(middleware)));
+        self
+    }
+
+    /// Render placeholder values as templates of their own, up to `max_depth` values deep
+    ///
+    /// By default values are inserted verbatim. When rendering recursively, a value containing
+    /// placeholders is itself rendered with the same values before it's inserted, so a value of
+    /// `"{first} {last}"` is replaced by the values of `first` and `last`. A value still containing
+    /// placeholders at `max_depth` fails with `RenderError::RecursionLimit`, which also catches
+    /// values that refer to each other in a cycle.
+    ///
+    /// Middlewares are only applied to the fully expanded values, while filters are applied at
+    /// every depth.
+    ///
+    /// Defaults to not rendering recursively
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Renderer;
+    ///
+    /// fn main() {
+    ///   let renderer = Renderer::new().recursive(4);
+    ///   let values = [
+    ///     ("greet", "Hello"),
+    ///     ("name", "{first} {last}"),
+    ///     ("first", "Homer"),
+    ///     ("last", "Simpson"),
+    ///   ];
+    ///
+    ///   assert!(renderer.render("<h1>{greet} {name}</h1>", &values)
+    ///     == Ok(String::from("<h1>Hello Homer Simpson</h1>")));
+    /// }
+    /// ```
+    pub fn recursive(mut self, max_depth: usize) -> Renderer {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Limit how long a recursive render may take
+    ///
+    /// This is a safety valve for rendering untrusted input recursively, failing with
+    /// `RenderError::Timeout` once `timeout` has passed. The timing is best effort, as the clock is
+    /// only checked before each value is expanded, so a single slow lookup or filter can overrun
+    /// it. It has no effect unless rendering `recursive()`ly.
+    ///
+    /// Defaults to unlimited
+    pub fn timeout(mut self, timeout: Duration) -> Renderer {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Only replace placeholders that stand as words of their own
+    ///
+    /// A placeholder directly before or after an identifier character, that is a letter, digit, or
+    /// underscore, is left as literal text. For example, `{foo}` is replaced within `"a {foo} b"`
+    /// and `"({foo})"`, but not within `"prefix{foo}"` or `"{foo}bar"`. The start and end of the
+    /// template count as boundaries.
+    ///
+    /// Defaults to `false`
+    pub fn word_boundaries(mut self, word_boundaries: bool) -> Renderer {
+        self.parse_options.word_boundaries = word_boundaries;
+        self
+    }
+
+    /// Strip a UTF-8 byte order mark from the start of the template
+    ///
+    /// Templates loaded from files saved with a byte order mark begin with `\u{FEFF}`, which is
+    /// otherwise kept as literal text at the start of the output. Placeholders after it are
+    /// replaced either way, but with the mark stripped, the output starts where the template's text
+    /// does, such as for `collapse_whitespace()` treating a placeholder as the start of the output.
+    ///
+    /// Defaults to `false`
+    pub fn strip_bom(mut self, strip: bool) -> Renderer {
+        self.parse_options.strip_bom = strip;
+        self
+    }
+
+    /// Use custom delimiters around placeholders instead of braces
+    ///
+    /// For example, with `delimiters("<<", ">>")` placeholders are written as `<<name>>`, and
+    /// braces are always literal text. A doubled opening delimiter is a literal opening delimiter,
+    /// so `<<<<name>>` renders as the literal text `<<name>>`. Backslashes escape placeholders the
+    /// same as with braces.
+    ///
+    /// Defaults to `{` and `}`
+    ///
+    /// # Panics
+    ///
+    /// When either delimiter is empty
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Renderer;
+    ///
+    /// fn main() {
+    ///   let renderer = Renderer::new().delimiters("<<", ">>");
+    ///
+    ///   assert!(renderer.render("{<<name>>} is written <<<<name>>", &[("name", "Homer")])
+    ///     == Ok(String::from("{Homer} is written <<name>>")));
+    /// }
+    /// ```
+    pub fn delimiters(mut self, open: &str, close: &str) -> Renderer {
+        if open.is_empty() || close.is_empty() {
+            panic!("placeholder delimiters can't be empty");
+        }
+
+        self.parse_options.delimiters = Some(Matcher::delimiters(open, close));
+        self
+    }
+
+    /// Limit the length of placeholder names, in bytes
+    ///
+    /// This guards against pathological templates from untrusted sources. What happens to a
+    /// placeholder with a longer name is chosen by `long_name()`.
+    ///
+    /// Defaults to unlimited
+    pub fn max_name_len(mut self, len: usize) -> Renderer {
+        self.parse_options.max_name_len = Some(len);
+        self
+    }
+
+    /// Limit the number of placeholders in a template, with each block counting as one
+    ///
+    /// This guards against untrusted templates made of a huge number of tiny placeholders, failing
+    /// with `RenderError::TooManyPlaceholders` while the template is parsed, before it's rendered.
+    /// Escaped placeholders don't count.
+    ///
+    /// Defaults to unlimited
+    pub fn max_placeholders(mut self, max: usize) -> Renderer {
+        self.parse_options.max_placeholders = Some(max);
+        self
+    }
+
+    /// Choose what happens to placeholders with names longer than `max_name_len()`
+    ///
+    /// `LongName::Error` fails with `RenderError::NameTooLong`, while `LongName::Literal` leaves
+    /// the placeholder as literal text.
+    ///
+    /// Defaults to `LongName::Error`
+    pub fn long_name(mut self, long_name: LongName) -> Renderer {
+        self.parse_options.long_name = long_name;
+        self
+    }
+
+    /// Limit the length of placeholder values, in characters
+    ///
+    /// This catches oversized values before they reach a sink with a limit on each field, such as
+    /// an SMS. A placeholder whose value is longer, after its filters and middlewares have been
+    /// applied, fails with `RenderError::ValueTooLong`. Values are checked before
+    /// `auto_escape()` escapes them.
+    ///
+    /// Defaults to unlimited
+    pub fn max_value_len(mut self, len: usize) -> Renderer {
+        self.max_value_len = Some(len);
+        self
+    }
+
+    /// Render the template with placeholder values
+    ///
+    /// # Parameters
+    ///
+    /// `template` is the template text containing placeholders in the form `{name}`
+    ///
+    /// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+    /// `AsValues`)
+    ///
+    /// # Returns
+    ///
+    /// `Ok(output)` is the template text with all its placeholders replaced with their
+    /// corresponding placeholder values
+    ///
+    /// `Err(RenderError::MissingPlaceholder { name, .. })` is the name of the first placeholder
+    /// missing from `values`
+    ///
+    /// `Err(RenderError::NameTooLong { name })` is the name of the first placeholder longer than
+    /// `max_name_len()`
+    ///
+    /// `Err(RenderError::ValueTooLong { name })` is the name of the first placeholder whose value
+    /// is longer than `max_value_len()`
+    pub fn render<T: AsValues>(&self, template: &str, values: T) -> Result<String, RenderError> {
+        let template = Template::parse_with_options(template, &self.parse_options)?;
+        self.render_template(&template, values)
+    }
+
+    /// Parse the template once with this renderer's options, ready to be rendered many times
+    ///
+    /// The options are fixed at this point, including parsing options such as `delimiters()` and
+    /// `max_name_len()`, so rendering the compiled template skips parsing entirely.
+    ///
+    /// # Parameters
+    ///
+    /// `template` is the template text containing placeholders in the form `{name}`
+    ///
+    /// # Returns
+    ///
+    /// `Ok(compiled)` is the compiled template, ready for rendering
+    ///
+    /// `Err(RenderError::NameTooLong { name })` is the name of the first placeholder longer than
+    /// `max_name_len()`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Renderer;
+    ///
+    /// fn main() {
+    ///   let compiled = Renderer::new().delimiters("<<", ">>").compile("{<<greet>>} <<name>>").unwrap();
+    ///
+    ///   assert!(compiled.render(&[("greet", "Hello"), ("name", "Homer")])
+    ///     == Ok(String::from("{Hello} Homer")));
+    ///   assert!(compiled.render(&[("greet", "Hi"), ("name", "Marge")])
+    ///     == Ok(String::from("{Hi} Marge")));
+    /// }
+    /// ```
+    pub fn compile(&self, template: &str) -> Result<CompiledTemplate, RenderError> {
+        Ok(CompiledTemplate {
+            renderer: self.clone(),
+            template: Template::parse_with_options(template, &self.parse_options)?,
+        })
+    }
+
+    /// Render an already parsed template with placeholder values
+    ///
+    /// See `render()` for details. As the template is already parsed, limits on parsing such as
+    /// `max_name_len()` don't apply.
+    pub fn render_template<T: AsValues>(
+        &self,
+        template: &Template,
+        values: T,
+    ) -> Result<String, RenderError> {
+        let mut output = String::new();
+        self.append_template(template, values, &mut output)?;
+        Ok(output)
+    }
+
+    /// Render the template with placeholder values, appending the output to `out`
+    ///
+    /// `out` isn't cleared first, so a document can be assembled from many renders into a single
+    /// buffer. When rendering fails, `out` is truncated back to how it was before, so a failed
+    /// render never leaves partial output behind.
+    ///
+    /// # Parameters
+    ///
+    /// `template` is the template text containing placeholders in the form `{name}`
+    ///
+    /// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+    /// `AsValues`)
+    ///
+    /// `out` is the string to append the output to
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` when the output has been appended to `out`
+    ///
+    /// `Err(error)` is why the template couldn't be rendered, as for `render()`, with `out` left
+    /// unchanged
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Renderer;
+    ///
+    /// fn main() {
+    ///   let renderer = Renderer::new();
+    ///   let mut out = String::from("<body>");
+    ///
+    ///   renderer.render_append("<h1>{title}</h1>", &[("title", "Menu")], &mut out).unwrap();
+    ///   renderer.render_append("<p>{food}</p>", &[("food", "Donuts")], &mut out).unwrap();
+    ///
+    ///   assert!(renderer.render_append("<p>{drink}</p>", &[("food", "Duff")], &mut out).is_err());
+    ///   assert!(out == "<body><h1>Menu</h1><p>Donuts</p>");
+    /// }
+    /// ```
+    pub fn render_append<T: AsValues>(
+        &self,
+        template: &str,
+        values: T,
+        out: &mut String,
+    ) -> Result<(), RenderError> {
+        let template = Template::parse_with_options(template, &self.parse_options)?;
+        self.append_template(&template, values, out)
+    }
+
+    // Render an already parsed template onto the end of `out`, truncating it back on failure
+    fn append_template<T: AsValues>(
+        &self,
+        template: &Template,
+        values: T,
+        out: &mut String,
+    ) -> Result<(), RenderError> {
+        if self.reject_duplicates {
+            if let Some(name) = values.duplicate_name() {
+                return Err(RenderError::DuplicateValue {
+                    name: name.into_owned(),
+                });
+            }
+        }
+
+        let mut resolve = resolve_from(&values);
+        let len = out.len();
+
+        let resolve = |name: &str| match resolve(name) {
+            Err(RenderError::MissingPlaceholder { .. }) if self.constants.contains_key(name) => {
+                Ok(Cow::Borrowed(self.constants[name].as_str()))
+            }
+            resolved => resolved,
+        };
+
+        match self.render_template_to(template, resolve, out) {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(error)) => {
+                out.truncate(len);
+                Err(error)
+            }
+            Err(never) => match never {},
+        }
+    }
+
+    // What to do with filters that don't exist
+    pub(crate) fn unknown_filters(&self) -> UnknownFilter {
+        self.unknown_filter
+    }
+
+    // The most characters a placeholder's value can have, if limited
+    pub(crate) fn value_len_limit(&self) -> Option<usize> {
+        self.max_value_len
+    }
+
+    // The labels the `yesno` filter renders booleans as
+    pub(crate) fn bool_labels(&self) -> (&str, &str) {
+        match &self.yes_no_labels {
+            None => ("Yes", "No"),
+            Some((yes, no)) => (yes, no),
+        }
+    }
+
+    // Render using `resolve` to look up each placeholder's value, so that other kinds of values
+    // can share the rendering logic
+    pub(crate) fn render_template_with<'a, F>(
+        &self,
+        template: &Template,
+        resolve: F,
+    ) -> Result<String, RenderError>
+    where
+        F: FnMut(&str) -> Result<Cow<'a, str>, RenderError>,
+    {
+        let mut output = String::new();
+
+        match self.render_template_to(template, resolve, &mut output) {
+            Ok(result) => result.map(|()| output),
+            Err(never) => match never {},
+        }
+    }
+
+    // Render into `sink` as the template is walked, so that the output doesn't have to be built up
+    // in memory first. The outer error is from the sink, while the inner error is from rendering
+    pub(crate) fn render_template_to<'a, F, S>(
+        &self,
+        template: &Template,
+        mut resolve: F,
+        sink: &mut S,
+    ) -> Result<Result<(), RenderError>, S::Error>
+    where
+        F: FnMut(&str) -> Result<Cow<'a, str>, RenderError>,
+        S: Sink,
+    {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+
+        if !self.drop_empty_lines {
+            return self.render_segments(
+                template.segments(),
+                &mut resolve,
+                sink,
+                0,
+                deadline,
+                &mut HashMap::new(),
+            );
+        }
+
+        let mut lines = EmptyLines::new(sink);
+        let result = self.render_segments(
+            template.segments(),
+            &mut resolve,
+            &mut lines,
+            0,
+            deadline,
+            &mut HashMap::new(),
+        )?;
+
+        lines.finish()?;
+        Ok(result)
+    }
+
+    // Render the template's segments into `sink`, with `depth` being how many values deep a
+    // recursive render has expanded. Filtered values are kept in `filtered` by placeholder source,
+    // such as `{price|pad(8)}`, so each distinct use of filters only runs them once per render.
+    // As the source includes both the name and the filters, `{price}` and `{price|pad(8)}` share
+    // the value looked up for `price` but never each other's filtered values
+    fn render_segments<'a, S: Sink>(
+        &self,
+        segments: &[Segment],
+        resolve: &mut Resolve<'_, 'a>,
+        sink: &mut S,
+        depth: usize,
+        deadline: Option<Instant>,
+        filtered: &mut HashMap<String, String>,
+    ) -> Result<Result<(), RenderError>, S::Error> {
+        let mut skip_blanks = false;
+        let mut after_blank = true;
+
+        for segment in segments {
+            // The text to write, along with the placeholder name when it's a value
+            let (text, value_of) = match segment {
+                Segment::Literal(text) => {
+                    let text = if skip_blanks {
+                        text.trim_start_matches(is_blank)
+                    } else {
+                        text
+                    };
+
+                    skip_blanks = false;
+
+                    let text = match self.newlines {
+                        // Literal text within values expanded by a recursive render is left alone
+                        Some(newline) if depth == 0 => newline.normalize(text),
+                        _ => Cow::Borrowed(text),
+                    };
+
+                    (text, None)
+                }
+                Segment::Placeholder {
+                    name,
+                    filters,
+                    source,
+                    start,
+                    default,
+                } => {
+                    // A default is template text, so once rendered it's only filtered, rather
+                    // than expanded, unescaped, or escaped as a value would be
+                    let mut defaulted = false;
+                    let resolved = resolve_name(name, resolve);
+
+                    match (&resolved, default) {
+                        (Ok(_), _) => sink.substituted(name)?,
+                        (Err(RenderError::MissingPlaceholder { .. }), None) => {
+                            sink.missing(name, *start)?
+                        }
+                        _ => {}
+                    }
+
+                    let value = match (resolved, default) {
+                        (Ok(value), _) => value,
+                        (Err(RenderError::MissingPlaceholder { .. }), Some(default)) => {
+                            defaulted = true;
+
+                            match self.render_default(default, resolve, sink, depth, deadline)? {
+                                Ok(value) => Cow::Owned(value),
+                                Err(error) => return Ok(Err(error)),
+                            }
+                        }
+                        (Err(RenderError::MissingPlaceholder { .. }), None)
+                            if self.on_missing == OnMissing::Keep =>
+                        {
+                            sink.write_str(source)?;
+                            skip_blanks = false;
+                            after_blank = source.ends_with(is_blank);
+                            continue;
+                        }
+                        (Err(RenderError::MissingPlaceholder { .. }), None)
+                            if self.on_missing != OnMissing::Error =>
+                        {
+                            match (self.on_missing, &self.missing_marker) {
+                                (OnMissing::Empty, _) => Cow::Borrowed(""),
+                                (OnMissing::Mark, None) => Cow::Owned(format!("[[{}]]", name)),
+                                (OnMissing::Mark, Some((prefix, suffix))) => {
+                                    Cow::Owned(format!("{}{}{}", prefix, name, suffix))
+                                }
+                                (OnMissing::Humanize, _) => Cow::Owned(humanize(name)),
+                                _ => Cow::Owned(name.clone()),
+                            }
+                        }
+                        (Err(RenderError::MissingPlaceholder { name, .. }), None)
+                            if self.panic_on_missing =>
+                        {
+                            panic!("missing value for placeholder: {}", name)
+                        }
+                        (Err(error), _) => return Ok(Err(error)),
+                    };
+
+                    let mut value = if defaulted {
+                        value
+                    } else {
+                        match self.expand(name, value, resolve, depth, deadline, filtered) {
+                            Ok(value) => value,
+                            Err(error) => return Ok(Err(error)),
+                        }
+                    };
+
+                    if self.value_escapes && !defaulted && value.contains('\\') {
+                        value = Cow::Owned(unescape(&value));
+                    }
+
+                    if let Some(cached) = filtered.get(source) {
+                        value = Cow::Owned(cached.clone());
+                    } else if !filters.is_empty() {
+                        for filter in filters {
+                            match filters::apply(self, name, filter, &value) {
+                                Ok(output) => value = Cow::Owned(output),
+                                Err(error) => return Ok(Err(error)),
+                            }
+                        }
+
+                        filtered.insert(source.clone(), value.to_string());
+                    }
+
+                    // Middlewares only see the fully expanded values of the outermost template
+                    if depth == 0 {
+                        value = self
+                            .middlewares
+                            .iter()
+                            .fold(value, |value, Middleware(middleware)| {
+                                Cow::Owned(middleware(name, &value))
+                            });
+                    }
+
+                    // The limit is on the value itself, so it's checked before escaping
+                    if self
+                        .max_value_len
+                        .is_some_and(|max_len| value.chars().count() > max_len)
+                    {
+                        return Ok(Err(RenderError::ValueTooLong { name: name.clone() }));
+                    }
+
+                    if self.escape_mode == EscapeMode::Html
+                        && !defaulted
+                        && !filters.iter().any(|filter| filter.name == "raw")
+                    {
+                        value = escape_html(value);
+                    }
+
+                    skip_blanks = self.collapse_whitespace && value.is_empty() && after_blank;
+                    (value, Some(name))
+                }
+                Segment::Block {
+                    kind,
+                    name,
+                    body,
+                    start,
+                } => {
+                    let value = match resolve(name) {
+                        Ok(value) => value,
+                        // A block whose value is missing is left out, unless that's an error
+                        Err(RenderError::MissingPlaceholder { .. })
+                            if self.on_missing != OnMissing::Error
+                                || self.missing_blocks_absent =>
+                        {
+                            sink.missing(name, *start)?;
+                            Cow::Borrowed("")
+                        }
+                        Err(RenderError::MissingPlaceholder { name, .. })
+                            if self.panic_on_missing =>
+                        {
+                            panic!("missing value for placeholder: {}", name)
+                        }
+                        Err(error) => return Ok(Err(error)),
+                    };
+
+                    for item in kind.iterations(&value) {
+                        // Within the body, the block's name is the value for this iteration
+                        let mut resolve_item = |other: &str| {
+                            if other == name {
+                                Ok(Cow::Owned(item.to_string()))
+                            } else {
+                                resolve(other)
+                            }
+                        };
+
+                        // Filtered values can't be reused across iterations, as each has its own
+                        // value for the block's name
+                        let mut iteration = HashMap::new();
+                        let filtered = match kind {
+                            BlockKind::If => &mut *filtered,
+                            BlockKind::Each => &mut iteration,
+                        };
+
+                        if let Err(error) = self.render_segments(
+                            body,
+                            &mut resolve_item,
+                            sink,
+                            depth,
+                            deadline,
+                            filtered,
+                        )? {
+                            return Ok(Err(error));
+                        }
+                    }
+
+                    continue;
+                }
+            };
+
+            // Empty values are only written out when lines they leave empty are to be removed
+            if text.is_empty() && self.drop_empty_lines {
+                if let Some(name) = value_of {
+                    sink.write_value(name, "")?;
+                }
+            }
+
+            if !text.is_empty() {
+                match value_of {
+                    Some(name) => sink.write_value(name, &text)?,
+                    None => sink.write_str(&text)?,
+                }
+
+                after_blank = text.ends_with(is_blank);
+            }
+        }
+
+        Ok(Ok(()))
+    }
+
+    // Render the segments of a placeholder's default, used in place of its missing value, with
+    // the same rules as any other segments
+    fn render_default<'a, S: Sink>(
+        &self,
+        default: &[Segment],
+        resolve: &mut Resolve<'_, 'a>,
+        sink: &mut S,
+        depth: usize,
+        deadline: Option<Instant>,
+    ) -> Result<Result<String, RenderError>, S::Error> {
+        let mut nested = Nested {
+            output: String::new(),
+            sink,
+        };
+
+        let result = self.render_segments(
+            default,
+            resolve,
+            &mut nested,
+            depth,
+            deadline,
+            &mut HashMap::new(),
+        )?;
+
+        Ok(result.map(|()| nested.output))
+    }
+
+    // When rendering recursively, render the value of the placeholder called `name` as a template
+    // of its own
+    fn expand<'a>(
+        &self,
+        name: &str,
+        value: Cow<'a, str>,
+        resolve: &mut Resolve<'_, 'a>,
+        depth: usize,
+        deadline: Option<Instant>,
+        filtered: &mut HashMap<String, String>,
+    ) -> Result<Cow<'a, str>, RenderError> {
+        let max_depth = match self.max_depth {
+            Some(max_depth) => max_depth,
+            None => return Ok(value),
+        };
+
+        let template = Template::parse_with_options(&value, &self.parse_options)?;
+
+        if !template
+            .segments()
+            .iter()
+            .any(|segment| matches!(segment, Segment::Placeholder { .. } | Segment::Block { .. }))
+        {
+            return Ok(value);
+        }
+
+        if depth >= max_depth {
+            return Err(RenderError::RecursionLimit {
+                name: name.to_string(),
+            });
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(RenderError::Timeout);
+        }
+
+        let mut output = String::new();
+
+        match self.render_segments(
+            template.segments(),
+            resolve,
+            &mut output,
+            depth + 1,
+            deadline,
+            filtered,
+        ) {
+            Ok(result) => result.map(|()| Cow::Owned(output)),
+            Err(never) => match never {},
+        }
+    }
+}
+
+/// A template parsed with a `Renderer`'s options, returned by `Renderer::compile()`
+#[derive(Clone, Debug)]
+pub struct CompiledTemplate {
+    renderer: Renderer,
+    template: Template,
+}
+
+impl CompiledTemplate {
+    /// Render the compiled template with placeholder values
+    ///
+    /// See `Renderer::render()` for details.
+    pub fn render<T: AsValues>(&self, values: T) -> Result<String, RenderError> {
+        self.renderer.render_template(&self.template, values)
+    }
+
+    /// The parsed template
+    pub fn template(&self) -> &Template {
+        &self.template
+    }
+}
+
+/// What a `Renderer` does with a placeholder whose value is missing
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{OnMissing, Renderer};
+///
+/// fn main() {
+///   let renderer = Renderer::new().on_missing(OnMissing::Name);
+///
+///   assert!(renderer.render("<h1>{greet} {name}</h1>", &[("greet", "Hello")])
+///     == Ok(String::from("<h1>Hello name</h1>")));
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OnMissing {
+    /// Fail with `RenderError::MissingPlaceholder`
+    #[default]
+    Error,
+
+    /// Leave the placeholder exactly as it's written in the template, delimiters included, without
+    /// applying any filters. A later render with the same delimiters can still resolve it
+    Keep,
+
+    /// Replace the placeholder with nothing
+    Empty,
+
+    /// Replace the placeholder with its name, without braces, such as for previewing the shape of
+    /// a template
+    Name,
+
+    /// Replace the placeholder with its name wrapped in the `Renderer`'s `missing_marker()`, such
+    /// as `[[name]]`, so that missing values stand out for review
+    Mark,
+
+    /// Replace the placeholder with its name as a title, such as `First Name` for `first_name`,
+    /// giving sensible labels before translations exist. Underscores and hyphens become spaces,
+    /// and the first letter of each word is capitalised
+    Humanize,
+}
+
+/// How a `Renderer` treats filters that don't exist, as chosen by `Renderer::unknown_filter()`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnknownFilter {
+    /// Fail with `RenderError::UnknownFilter`
+    #[default]
+    Error,
+
+    /// Skip the filter, leaving the value as it is
+    Ignore,
+}
+
+/// How placeholder values are escaped, as chosen by `Renderer::auto_escape()`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EscapeMode {
+    /// Output values as they are
+    #[default]
+    None,
+
+    /// Escape `&`, `<`, `>`, `"`, and `'` as HTML character references
+    Html,
+}
+
+/// The line ending chosen by `Renderer::normalize_newlines()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Newline {
+    /// Unix line endings, `\n`
+    Lf,
+
+    /// Windows line endings, `\r\n`
+    CrLf,
+}
+
+impl Newline {
+    fn normalize(self, text: &str) -> Cow<'_, str> {
+        match self {
+            Newline::Lf if text.contains("\r\n") => Cow::Owned(text.replace("\r\n", "\n")),
+            Newline::CrLf if text.contains('\n') => {
+                Cow::Owned(text.replace("\r\n", "\n").replace('\n', "\r\n"))
+            }
+            _ => Cow::Borrowed(text),
+        }
+    }
+}
+
+// Looks up each placeholder's value while rendering
+type Resolve<'r, 'a> = dyn FnMut(&str) -> Result<Cow<'a, str>, RenderError> + 'r;
+
+// Resolve a placeholder's name, which is composed around an inner placeholder when it's braced,
+// such as `col_{n}` as parsed for `render_nested()`. The inner placeholder is resolved first, then
+// the name composed with its value
+fn resolve_name<'a>(
+    name: &str,
+    resolve: &mut Resolve<'_, 'a>,
+) -> Result<Cow<'a, str>, RenderError> {
+    let composed = name
+        .split_once('{')
+        .and_then(|(prefix, rest)| Some((prefix, rest.split_once('}')?)));
+
+    match composed {
+        None => resolve(name),
+        Some((prefix, (inner, suffix))) => {
+            let inner = resolve(inner)?;
+            resolve(&format!("{}{}{}", prefix, inner, suffix))
+        }
+    }
+}
+
+// A value transform added by `Renderer::middleware()`, called with the placeholder name and value
+type Transform = dyn Fn(&str, &str) -> String + Send + Sync;
+
+#[derive(Clone)]
+struct Middleware(Arc<Transform>);
+
+impl fmt::Debug for Middleware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Middleware")
+    }
+}
+
+// The error for the placeholder called `name` having no value, for lookups to share
+pub(crate) fn missing(name: &str) -> RenderError {
+    RenderError::MissingPlaceholder {
+        name: name.to_string(),
+        available: None,
+    }
+}
+
+// Look up each placeholder's value from `values`, with a missing value being an error. Each name
+// is only looked up once, with its value reused for any repeats of the placeholder
+pub(crate) fn resolve_from<'v, T: AsValues>(
+    values: &'v T,
+) -> impl FnMut(&str) -> Result<Cow<'v, str>, RenderError> + 'v {
+    let mut resolved: HashMap<String, Cow<'v, str>> = HashMap::new();
+
+    move |name| {
+        if let Some(value) = resolved.get(name) {
+            return Ok(value.clone());
+        }
+
+        match values.get_value(name) {
+            None => Err(missing(name)),
+            Some(value) => {
+                resolved.insert(name.to_string(), value.clone());
+                Ok(value)
+            }
+        }
+    }
+}
+
+// Somewhere to write rendered output to
+pub(crate) trait Sink {
+    type Error;
+
+    fn write_str(&mut self, text: &str) -> Result<(), Self::Error>;
+
+    // Write the value of the placeholder called `name`, which is just more output by default
+    fn write_value(&mut self, _name: &str, text: &str) -> Result<(), Self::Error> {
+        self.write_str(text)
+    }
+
+    // Note that the placeholder called `name` was given its value, which is ignored by default.
+    // Neither this nor `missing()` hear of the placeholders within values a recursive render
+    // expands, as their offsets aren't within the template
+    fn substituted(&mut self, _name: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    // Note that the placeholder or block called `name`, at byte `start` of the template, is
+    // missing its value with nothing to fall back to, which is ignored by default
+    fn missing(&mut self, _name: &str, _start: usize) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl Sink for String {
+    type Error = Infallible;
+
+    fn write_str(&mut self, text: &str) -> Result<(), Self::Error> {
+        self.push_str(text);
+        Ok(())
+    }
+}
+
+// A sink for `Renderer::drop_empty_lines()`, holding back each line until it's known whether
+// it was left empty by empty values. Empty values are written to it too, so they can be seen
+struct EmptyLines<'s, S> {
+    sink: &'s mut S,
+    // Blank literal text at the start of the current line, not yet written out
+    pending: String,
+    // Whether the current line has an empty value, and so would be removed if it ends up blank
+    has_empty: bool,
+    // Whether the current line has anything other than blanks, and so is kept
+    keep: bool,
+}
+
+impl<'s, S: Sink> EmptyLines<'s, S> {
+    fn new(sink: &'s mut S) -> EmptyLines<'s, S> {
+        EmptyLines {
+            sink,
+            pending: String::new(),
+            has_empty: false,
+            keep: false,
+        }
+    }
+
+    // Keep the current line, writing out what's been held back of it
+    fn keep(&mut self) -> Result<(), S::Error> {
+        if !self.keep {
+            self.keep = true;
+
+            if !self.pending.is_empty() {
+                self.sink.write_str(&std::mem::take(&mut self.pending))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Write out or remove the rest of the last line, which has no newline
+    fn finish(mut self) -> Result<(), S::Error> {
+        if !self.has_empty {
+            self.keep()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: Sink> Sink for EmptyLines<'_, S> {
+    type Error = S::Error;
+
+    fn write_str(&mut self, text: &str) -> Result<(), Self::Error> {
+        for line in text.split_inclusive('\n') {
+            let (content, newline) = match line.strip_suffix('\n') {
+                Some(content) => (content, true),
+                None => (line, false),
+            };
+
+            // A carriage return of a Windows line ending doesn't stop a line being blank
+            if !self.keep && content.chars().all(|c| is_blank(c) || c == '\r') {
+                self.pending.push_str(content);
+            } else {
+                self.keep()?;
+                self.sink.write_str(content)?;
+            }
+
+            if newline {
+                if self.keep || !self.has_empty {
+                    self.keep()?;
+                    self.sink.write_str("\n")?;
+                }
+
+                self.pending.clear();
+                self.has_empty = false;
+                self.keep = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_value(&mut self, name: &str, text: &str) -> Result<(), Self::Error> {
+        if text.is_empty() {
+            self.has_empty = true;
+            return Ok(());
+        }
+
+        self.keep()?;
+        self.sink.write_value(name, text)
+    }
+
+    fn substituted(&mut self, name: &str) -> Result<(), Self::Error> {
+        self.sink.substituted(name)
+    }
+
+    fn missing(&mut self, name: &str, start: usize) -> Result<(), Self::Error> {
+        self.sink.missing(name, start)
+    }
+}
+
+// A sink building up output of its own, such as a placeholder's default, while passing what's
+// noted about placeholders on to the sink the whole template is rendered into. That sink is
+// behind a reference to a trait object, so defaults within defaults nest the same type
+struct Nested<'s, E> {
+    output: String,
+    sink: &'s mut dyn Sink<Error = E>,
+}
+
+impl<E> Sink for Nested<'_, E> {
+    type Error = E;
+
+    fn write_str(&mut self, text: &str) -> Result<(), Self::Error> {
+        self.output.push_str(text);
+        Ok(())
+    }
+
+    fn substituted(&mut self, name: &str) -> Result<(), Self::Error> {
+        self.sink.substituted(name)
+    }
+
+    fn missing(&mut self, name: &str, start: usize) -> Result<(), Self::Error> {
+        self.sink.missing(name, start)
+    }
+}
+
+// A sink gathering the placeholders and blocks missing their values as the output is rendered,
+// along with how many placeholders were given theirs. Rendering stops once more than `max_names`
+// distinct names are missing
+pub(crate) struct Collect {
+    pub(crate) output: String,
+    // The name and offset of every placeholder and block reached that's missing its value
+    pub(crate) missing: Vec<(String, usize)>,
+    // The distinct names within `missing`, in the order they're first reached
+    pub(crate) names: Vec<String>,
+    pub(crate) substitutions: usize,
+    max_names: usize,
+}
+
+// The error from a `Collect` sink once more than its `max_names` names are missing
+pub(crate) struct TooManyMissing;
+
+impl Collect {
+    pub(crate) fn new(max_names: usize) -> Collect {
+        Collect {
+            output: String::new(),
+            missing: Vec::new(),
+            names: Vec::new(),
+            substitutions: 0,
+            max_names,
+        }
+    }
+}
+
+impl Sink for Collect {
+    type Error = TooManyMissing;
+
+    fn write_str(&mut self, text: &str) -> Result<(), Self::Error> {
+        self.output.push_str(text);
+        Ok(())
+    }
+
+    fn substituted(&mut self, _name: &str) -> Result<(), Self::Error> {
+        self.substitutions += 1;
+        Ok(())
+    }
+
+    fn missing(&mut self, name: &str, start: usize) -> Result<(), Self::Error> {
+        if !self.names.iter().any(|missing| missing == name) {
+            if self.names.len() == self.max_names {
+                return Err(TooManyMissing);
+            }
+
+            self.names.push(name.to_string());
+        }
+
+        self.missing.push((name.to_string(), start));
+        Ok(())
+    }
+}
+
+// A sink that only counts the bytes of rendered output, without keeping any of it
+pub(crate) struct LenSink(pub(crate) usize);
+
+impl Sink for LenSink {
+    type Error = Infallible;
+
+    fn write_str(&mut self, text: &str) -> Result<(), Self::Error> {
+        self.0 += text.len();
+        Ok(())
+    }
+}
+
+// A sink keeping each piece of output as a chunk of its own, rather than joining them
+impl Sink for Vec<String> {
+    type Error = Infallible;
+
+    fn write_str(&mut self, text: &str) -> Result<(), Self::Error> {
+        self.push(text.to_string());
+        Ok(())
+    }
+}
+
+// An `io::Write` sink, writing rendered output as UTF-8 bytes
+pub(crate) struct IoSink<W>(pub(crate) W);
+
+impl<W: io::Write> Sink for IoSink<W> {
+    type Error = io::Error;
+
+    fn write_str(&mut self, text: &str) -> Result<(), Self::Error> {
+        self.0.write_all(text.as_bytes())
+    }
+}
+
+/// A piece of rendered output, returned by `render_pieces()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OutputPiece {
+    /// Literal text from the template, after escapes have been applied
+    Literal(String),
+
+    /// The value of a placeholder, after its filters have been applied
+    ///
+    /// `name` is the name of the placeholder
+    ///
+    /// `text` is the value as it appears in the output
+    Value { name: String, text: String },
+}
+
+// A sink keeping literal text and placeholder values apart, with adjacent literal text joined
+impl Sink for Vec<OutputPiece> {
+    type Error = Infallible;
+
+    fn write_str(&mut self, text: &str) -> Result<(), Self::Error> {
+        match self.last_mut() {
+            Some(OutputPiece::Literal(literal)) => literal.push_str(text),
+            _ => self.push(OutputPiece::Literal(text.to_string())),
+        }
+
+        Ok(())
+    }
+
+    fn write_value(&mut self, name: &str, text: &str) -> Result<(), Self::Error> {
+        self.push(OutputPiece::Value {
+            name: name.to_string(),
+            text: text.to_string(),
+        });
+
+        Ok(())
+    }
+}
+
+// Escape the characters with special meaning in HTML, borrowing the value when there are none
+fn escape_html(value: Cow<'_, str>) -> Cow<'_, str> {
+    if !value.contains(['&', '<', '>', '"', '\'']) {
+        return value;
+    }
+
+    let mut escaped = String::with_capacity(value.len() + 16);
+
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    Cow::Owned(escaped)
+}
+
+// Turn a placeholder name into a title for `OnMissing::Humanize`, such as `first_name` into
+// `First Name`
+fn humanize(name: &str) -> String {
+    name.split(['_', '-'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Replace the backslash escape sequences supported by `Renderer::value_escapes()`
+fn unescape(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+
+        match chars.clone().next() {
+            Some('n') => output.push('\n'),
+            Some('r') => output.push('\r'),
+            Some('t') => output.push('\t'),
+            Some('\\') => output.push('\\'),
+            _ => {
+                output.push('\\');
+                continue;
+            }
+        }
+
+        chars.next();
+    }
+
+    output
+}
+
+fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lookup;
+    use std::cell::Cell;
+
+    #[test]
+    fn default_keeps_whitespace() {
+        let mut values = HashMap::new();
+        values.insert(String::from("middle"), String::from(""));
+
+        assert!(
+            Renderer::new().render("Hello {middle} world", &values)
+                == Ok(String::from("Hello  world"))
+        );
+    }
+
+    #[test]
+    fn on_missing_empty() {
+        let renderer = Renderer::new().on_missing(OnMissing::Empty);
+
+        assert!(
+            renderer.render(
+                "{start} {middle} {end}",
+                &[("start", "Hello"), ("end", "world")]
+            ) == Ok(String::from("Hello  world"))
+        );
+    }
+
+    #[test]
+    fn on_missing_keep() {
+        let renderer = Renderer::new().on_missing(OnMissing::Keep);
+
+        assert!(
+            renderer.render(
+                "{start} {middle|pad(8)} {end} {middle}",
+                &[("start", "Hello"), ("end", "world")]
+            ) == Ok(String::from("Hello {middle|pad(8)} world {middle}"))
+        );
+        assert!(
+            renderer
+                .clone()
+                .delimiters("<<", ">>")
+                .render("<<start>> <<middle>>", &[("start", "Hello")])
+                == Ok(String::from("Hello <<middle>>"))
+        );
+    }
+
+    #[test]
+    fn defaults() {
+        let values = [("name", "Homer"), ("html", "<b>")];
+
+        for (before, after) in [
+            ("{name=Nobody}", "Homer"),
+            ("{title=Mr} {name}", "Mr Homer"),
+            ("[{middle?}]", "[]"),
+            ("[{title|pad(4)=Mr}]", "[  Mr]"),
+            ("[{title:>4=Mr}]", "[  Mr]"),
+            ("[{title=}]", "[]"),
+            ("{title=<i>}{html}", "<i>&lt;b&gt;"),
+        ] {
+            assert!(
+                Renderer::new()
+                    .auto_escape(EscapeMode::Html)
+                    .render(before, &values)
+                    == Ok(String::from(after))
+            );
+        }
+
+        for on_missing in [OnMissing::Keep, OnMissing::Mark] {
+            assert!(
+                Renderer::new()
+                    .on_missing(on_missing)
+                    .render("{title=Mr} {middle?}{name}", &values)
+                    == Ok(String::from("Mr Homer"))
+            );
+        }
+
+        assert!(
+            Renderer::new().render("{title=Mr} {surname}", &values)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("surname"),
+                    available: None,
+                })
+        );
+    }
+
+    #[test]
+    fn defaults_not_specs() {
+        let empty: [(&str, &str); 0] = [];
+
+        for text in ["a {color: red} b", "var x = {a: 1};", "{\"a\": 1}"] {
+            assert!(Renderer::new().render(text, &empty) == Ok(String::from(text)));
+        }
+
+        assert!(Renderer::new().render("[{n:0}]", &[("n", "7")]) == Ok(String::from("[7]")));
+        assert!(Renderer::new().render("[{n:3}]", &[("n", "7")]) == Ok(String::from("[7  ]")));
+        assert!(
+            Renderer::new().render("[{n:0}]", &empty)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("n"),
+                    available: None,
+                })
+        );
+        assert!(Renderer::new().render("[{n=0}]", &[("n", "7")]) == Ok(String::from("[7]")));
+        assert!(Renderer::new().render("[{n=0}]", &empty) == Ok(String::from("[0]")));
+        assert!(Renderer::new().render("[{n:3=0}]", &empty) == Ok(String::from("[0  ]")));
+    }
+
+    #[test]
+    fn default_references() {
+        let values = [("b", "Homer")];
+
+        for (before, after) in [
+            ("{a={b}}", "Homer"),
+            ("{a=Dear {b|pad(6)}!}", "Dear  Homer!"),
+            ("{a={c=x}}", "x"),
+            ("{a={c={b}}}", "{a=Homer}"),
+            ("{{a={b}}}", "{{a={b}}}"),
+        ] {
+            assert!(Renderer::new().render(before, &values) == Ok(String::from(after)));
+        }
+
+        assert!(Renderer::new().render("{b={a}}", &values) == Ok(String::from("Homer")));
+        assert!(
+            Renderer::new().render("{a={c}}", &values)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("c"),
+                    available: None,
+                })
+        );
+        assert!(
+            Renderer::new()
+                .on_missing(OnMissing::Keep)
+                .render("{a={c}}", &values)
+                == Ok(String::from("{c}"))
+        );
+        assert!(
+            Renderer::new()
+                .delimiters("<<", ">>")
+                .render("<<a={b}>>", &values)
+                == Ok(String::from("Homer"))
+        );
+    }
+
+    #[test]
+    fn on_missing_keep_second_pass() {
+        let renderer = Renderer::new()
+            .on_missing(OnMissing::Keep)
+            .delimiters("<<", ">>");
+
+        let first = renderer
+            .render(
+                "{start} <<start>> <<x>> <<middle|pad(8)>>!",
+                &[("start", "Hello")],
+            )
+            .unwrap();
+
+        assert!(first == "{start} Hello <<x>> <<middle|pad(8)>>!");
+        assert!(
+            renderer.render(&first, &[("x", "there"), ("middle", "world")])
+                == Ok(String::from("{start} Hello there    world!"))
+        );
+    }
+
+    #[test]
+    fn on_missing_name() {
+        let renderer = Renderer::new().on_missing(OnMissing::Name);
+
+        assert!(
+            renderer.render(
+                "{start} {middle} {end}",
+                &[("start", "Hello"), ("end", "world")]
+            ) == Ok(String::from("Hello middle world"))
+        );
+        assert!(
+            renderer.render("Dear {first_name},", &[("last_name", "Simpson")])
+                == Ok(String::from("Dear first_name,"))
+        );
+    }
+
+    #[test]
+    fn unknown_filter_modes() {
+        let values = [("x", "Homer")];
+
+        assert!(
+            Renderer::new().render("{x|notregistered}", &values)
+                == Err(RenderError::UnknownFilter {
+                    name: String::from("notregistered")
+                })
+        );
+        assert!(
+            Renderer::new()
+                .unknown_filter(UnknownFilter::Error)
+                .render("{x|pad(6)|notregistered}", &values)
+                == Err(RenderError::UnknownFilter {
+                    name: String::from("notregistered")
+                })
+        );
+
+        let renderer = Renderer::new().unknown_filter(UnknownFilter::Ignore);
+
+        assert!(renderer.render("{x|notregistered}", &values) == Ok(String::from("Homer")));
+        assert!(
+            renderer.render("[{x|notregistered(1, 2)|rpad(6)}]", &values)
+                == Ok(String::from("[Homer ]"))
+        );
+        assert!(
+            renderer.render("{x|pad}", &values)
+                == Err(RenderError::InvalidFilterArguments {
+                    name: String::from("pad")
+                })
+        );
+    }
+
+    #[test]
+    fn render_append() {
+        let renderer = Renderer::new().constant("site", "Moe's");
+        let mut out = String::new();
+
+        assert!(renderer.render_append("<h1>{site}</h1>\n", &[("x", "1")], &mut out) == Ok(()));
+        assert!(
+            renderer.render_append(
+                "<p>{name} likes {food|pad(7)}</p>",
+                &[("name", "Homer"), ("food", "Duff")],
+                &mut out
+            ) == Ok(())
+        );
+        assert!(out == "<h1>Moe's</h1>\n<p>Homer likes    Duff</p>");
+
+        assert!(
+            renderer.render_append("<p>{name} {nobody}</p>", &[("name", "Homer")], &mut out)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("nobody"),
+                    available: None
+                })
+        );
+        assert!(
+            renderer.render_append("{name|frobnicate}", &[("name", "Homer")], &mut out)
+                == Err(RenderError::UnknownFilter {
+                    name: String::from("frobnicate")
+                })
+        );
+        assert!(out == "<h1>Moe's</h1>\n<p>Homer likes    Duff</p>");
+    }
+
+    #[test]
+    fn drop_empty_lines() {
+        let renderer = Renderer::new().drop_empty_lines(true);
+        let before =
+            "Dear {name},\n\n{line1}\n  {line2}\t\n{line3}{line4}\n \n{city} {zip}\n{sign}";
+        let values = [
+            ("name", "Homer"),
+            ("line1", "742 Evergreen Tce"),
+            ("line2", ""),
+            ("line3", ""),
+            ("line4", ""),
+            ("city", "Springfield"),
+            ("zip", ""),
+            ("sign", ""),
+        ];
+
+        assert!(
+            renderer.render(before, &values)
+                == Ok(String::from(
+                    "Dear Homer,\n\n742 Evergreen Tce\n \nSpringfield \n"
+                ))
+        );
+        assert!(
+            renderer.render("a\r\n{x}\r\n{y}|\r\nb", &[("x", ""), ("y", "")])
+                == Ok(String::from("a\r\n|\r\nb"))
+        );
+        assert!(Renderer::new().render("a\n{x}\nb", &[("x", "")]) == Ok(String::from("a\n\nb")));
+
+        let mut pieces: Vec<OutputPiece> = Vec::new();
+        let template = Template::parse("{x}\n{y}\n");
+
+        assert!(matches!(
+            renderer.render_template_to(
+                &template,
+                |name| Ok(Cow::Borrowed(if name == "x" { "" } else { "Y" })),
+                &mut pieces
+            ),
+            Ok(Ok(()))
+        ));
+        assert!(
+            pieces
+                == vec![
+                    OutputPiece::Value {
+                        name: String::from("y"),
+                        text: String::from("Y")
+                    },
+                    OutputPiece::Literal(String::from("\n")),
+                ]
+        );
+    }
+
+    #[test]
+    fn on_missing_humanize() {
+        let renderer = Renderer::new().on_missing(OnMissing::Humanize);
+
+        assert!(
+            renderer.render("{greet} {first_name}!", &[("last_name", "Simpson")])
+                == Ok(String::from("Greet First Name!"))
+        );
+        assert!(
+            renderer.render("{greet|pad(7)} {__home__town_}", &[("name", "Homer")])
+                == Ok(String::from("  Greet Home Town"))
+        );
+        assert!(
+            renderer.render("{greet} {name}", &[("name", "Homer")])
+                == Ok(String::from("Greet Homer"))
+        );
+        assert!(
+            renderer.render(r#"{"home-town"}"#, &[("name", "Homer")])
+                == Ok(String::from("Home Town"))
+        );
+    }
+
+    #[test]
+    fn on_missing_mark() {
+        let renderer = Renderer::new().on_missing(OnMissing::Mark);
+
+        assert!(
+            renderer.render(
+                "{start} {middle} {end} [{start}]",
+                &[("start", "Hello"), ("end", "world")]
+            ) == Ok(String::from("Hello [[middle]] world [Hello]"))
+        );
+    }
+
+    #[test]
+    fn on_missing_mark_configurable() {
+        let renderer = Renderer::new()
+            .on_missing(OnMissing::Mark)
+            .missing_marker("<span class=\"missing\">", "</span>");
+
+        assert!(
+            renderer.render(
+                "{start} {middle} {end}",
+                &[("start", "Hello"), ("end", "world")]
+            ) == Ok(String::from(
+                "Hello <span class=\"missing\">middle</span> world"
+            ))
+        );
+    }
+
+    #[test]
+    fn on_missing_name_does_not_panic() {
+        let renderer = Renderer::new()
+            .on_missing(OnMissing::Name)
+            .panic_on_missing(true);
+
+        assert!(renderer.render("{start}", &[("end", "world")]) == Ok(String::from("start")));
+    }
+
+    #[test]
+    fn recursive() {
+        let renderer = Renderer::new().recursive(2);
+
+        let values = [
+            ("start", "{greet}"),
+            ("greet", "{hello}"),
+            ("hello", "Hello"),
+            ("end", "world"),
+        ];
+
+        assert!(renderer.render("{start} {end}", &values) == Ok(String::from("Hello world")));
+        assert!(
+            Renderer::new().render("{start} {end}", &values) == Ok(String::from("{greet} world"))
+        );
+    }
+
+    #[test]
+    fn recursive_limit() {
+        let renderer = Renderer::new().recursive(1);
+        let values = [
+            ("start", "{greet}"),
+            ("greet", "{hello}"),
+            ("hello", "Hello"),
+        ];
+
+        assert!(
+            renderer.render("{start}", &values)
+                == Err(RenderError::RecursionLimit {
+                    name: String::from("greet")
+                })
+        );
+    }
+
+    #[test]
+    fn recursive_cycle() {
+        let renderer = Renderer::new().recursive(8);
+        let values = [("start", "<{end}>"), ("end", "<{start}>")];
+
+        assert!(
+            renderer.render("{start}", &values)
+                == Err(RenderError::RecursionLimit {
+                    name: String::from("start")
+                })
+        );
+    }
+
+    #[test]
+    fn recursive_middlewares_see_expanded_values() {
+        let renderer = Renderer::new()
+            .recursive(2)
+            .middleware(|_, value| format!("[{}]", value));
+
+        let values = [
+            ("start", "{greet} {name}"),
+            ("greet", "Hello"),
+            ("name", "Homer"),
+        ];
+
+        assert!(renderer.render("{start}!", &values) == Ok(String::from("[Hello Homer]!")));
+    }
+
+    #[test]
+    fn timeout() {
+        let values = [("start", "{greet}"), ("greet", "Hello"), ("end", "world")];
+
+        assert!(
+            Renderer::new()
+                .recursive(4)
+                .timeout(Duration::ZERO)
+                .render("{start} {end}", &values)
+                == Err(RenderError::Timeout)
+        );
+        assert!(
+            Renderer::new()
+                .recursive(4)
+                .timeout(Duration::from_secs(60))
+                .render("{start} {end}", &values)
+                == Ok(String::from("Hello world"))
+        );
+    }
+
+    #[test]
+    fn timeout_only_applies_when_recursive() {
+        let renderer = Renderer::new().timeout(Duration::ZERO);
+
+        assert!(
+            renderer.render("{start} {end}", &[("start", "{greet}"), ("end", "world")])
+                == Ok(String::from("{greet} world"))
+        );
+    }
+
+    #[test]
+    fn value_escapes() {
+        let renderer = Renderer::new().value_escapes(true);
+        let values = [("lines", r"one\ntwo\tthree\\n \x \"), ("end", "world")];
+
+        assert!(
+            renderer.render(r"{lines} \n {end}", &values)
+                == Ok(String::from("one\ntwo\tthree\\n \\x \\ \\n world"))
+        );
+    }
+
+    #[test]
+    fn value_escapes_off_by_default() {
+        assert!(
+            Renderer::new().render("{lines}", &[("lines", r"one\ntwo")])
+                == Ok(String::from(r"one\ntwo"))
+        );
+    }
+
+    #[test]
+    fn normalize_newlines_lf() {
+        let renderer = Renderer::new().normalize_newlines(Newline::Lf);
+        let values = [("start", "Hello\r\n"), ("end", "world")];
+
+        assert!(
+            renderer.render("{start}\r\n{end}\r\nbye\n", &values)
+                == Ok(String::from("Hello\r\n\nworld\nbye\n"))
+        );
+    }
+
+    #[test]
+    fn normalize_newlines_crlf() {
+        let renderer = Renderer::new().normalize_newlines(Newline::CrLf);
+        let values = [("start", "Hello\n"), ("end", "world")];
+
+        assert!(
+            renderer.render("{start}\n{end}\r\nbye\n", &values)
+                == Ok(String::from("Hello\n\r\nworld\r\nbye\r\n"))
+        );
+    }
+
+    #[test]
+    fn newlines_kept_by_default() {
+        assert!(
+            Renderer::new().render(
+                "{start}\r\n{end}\n",
+                &[("start", "Hello"), ("end", "world")]
+            ) == Ok(String::from("Hello\r\nworld\n"))
+        );
+    }
+
+    #[test]
+    fn repeats_looked_up_once() {
+        struct Counted(Cell<usize>);
+
+        impl Lookup for Counted {
+            fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+                self.0.set(self.0.get() + 1);
+                (name == "a").then_some(Cow::Borrowed("x"))
+            }
+        }
+
+        let values = Counted(Cell::new(0));
+
+        assert!(Renderer::new().render(&"{a}".repeat(1000), &values) == Ok("x".repeat(1000)));
+        assert!(values.0.get() == 1);
+    }
+
+    #[test]
+    fn if_block() {
+        let renderer = Renderer::new();
+        let before = "<p>Hi {name}{#if member}, welcome back{/if}!</p>";
+
+        assert!(
+            renderer.render(before, &[("name", "Homer"), ("member", "yes")])
+                == Ok(String::from("<p>Hi Homer, welcome back!</p>"))
+        );
+        assert!(
+            renderer.render(before, &[("name", "Homer"), ("member", "")])
+                == Ok(String::from("<p>Hi Homer!</p>"))
+        );
+        assert!(
+            renderer.render(before, &[("name", "Homer")])
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("member"),
+                    available: None,
+                })
+        );
+    }
+
+    #[test]
+    fn if_block_missing_left_out() {
+        let renderer = Renderer::new().on_missing(OnMissing::Mark);
+
+        assert!(
+            renderer.render("{#if member}member {name}{/if}{name}", &[("name", "Homer")])
+                == Ok(String::from("Homer"))
+        );
+    }
+
+    #[test]
+    fn missing_blocks_absent() {
+        let renderer = Renderer::new().missing_blocks_absent(true);
+        let before = "{#if member}Welcome back {/if}{name}{#each items}, {items}{/each}";
+
+        assert!(renderer.render(before, &[("name", "Homer")]) == Ok(String::from("Homer")));
+        assert!(
+            renderer.render(before, &[("member", "yes"), ("items", "Duff")])
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("name"),
+                    available: None,
+                })
+        );
+        assert!(
+            renderer.render("{#if member}Hi {name}{/if}", &[("member", "yes")])
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("name"),
+                    available: None,
+                })
+        );
+        assert!(
+            Renderer::new().render(before, &[("name", "Homer")])
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("member"),
+                    available: None,
+                })
+        );
+    }
+
+    #[test]
+    fn each_block() {
+        let renderer = Renderer::new();
+        let before = "<ul>{#each items}<li>{items|pad(6)} for {name}</li>{/each}</ul>";
+
+        assert!(
+            renderer.render(before, &[("name", "Homer"), ("items", "Donuts\nDuff\n")])
+                == Ok(String::from(
+                    "<ul><li>Donuts for Homer</li><li>  Duff for Homer</li></ul>"
+                ))
+        );
+        assert!(
+            renderer.render(before, &[("name", "Homer"), ("items", "")])
+                == Ok(String::from("<ul></ul>"))
+        );
+    }
+
+    #[test]
+    fn blocks_in_recursive_values() {
+        let renderer = Renderer::new().recursive(2);
+
+        assert!(
+            renderer.render(
+                "{list}",
+                &[("list", "{#each xs}({xs}){/each}"), ("xs", "a\nb")]
+            ) == Ok(String::from("(a)(b)"))
+        );
+    }
+
+    #[test]
+    fn max_value_len() {
+        let renderer = Renderer::new().max_value_len(5);
+
+        assert!(
+            renderer.render("{greet} {name}", &[("greet", "Hello"), ("name", "Homér")])
+                == Ok(String::from("Hello Homér"))
+        );
+        assert!(
+            renderer.render("{greet} {name}", &[("greet", "Hello"), ("name", "Homers")])
+                == Err(RenderError::ValueTooLong {
+                    name: String::from("name")
+                })
+        );
+        assert!(
+            renderer.render("{name|pad(6)}", &[("name", "Homer")])
+                == Err(RenderError::ValueTooLong {
+                    name: String::from("name")
+                })
+        );
+        assert!(
+            renderer.render("{name}", &[("greet", "Hello")])
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("name"),
+                    available: None,
+                })
+        );
+    }
+
+    #[test]
+    fn max_value_len_escaped() {
+        let renderer = Renderer::new()
+            .max_value_len(5)
+            .auto_escape(EscapeMode::Html);
+
+        assert!(renderer.render("{x}", &[("x", "a&b&c")]) == Ok(String::from("a&amp;b&amp;c")));
+        assert!(
+            renderer.render("{x}", &[("x", "a&b&cd")])
+                == Err(RenderError::ValueTooLong {
+                    name: String::from("x")
+                })
+        );
+    }
+
+    #[test]
+    fn repeated_filters_run_once() {
+        let before = "{price|pad(6)} {price|pad(6)} {price|pad(8)} {price|pad(6)} {price}";
+        let after = "    42     42       42     42 42";
+
+        let runs = filters::RUNS.with(|runs| runs.get());
+
+        assert!(Renderer::new().render(before, &[("price", "42")]) == Ok(String::from(after)));
+        assert!(filters::RUNS.with(|runs| runs.get()) == runs + 2);
+
+        assert!(Renderer::new().render(before, &[("price", "7")]).is_ok());
+        assert!(filters::RUNS.with(|runs| runs.get()) == runs + 4);
+    }
+
+    #[test]
+    fn repeated_names_with_and_without_filters() {
+        struct Counted(Cell<usize>);
+
+        impl Lookup for Counted {
+            fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+                self.0.set(self.0.get() + 1);
+                (name == "name").then_some(Cow::Borrowed("Homer"))
+            }
+        }
+
+        let values = Counted(Cell::new(0));
+        let runs = filters::RUNS.with(|runs| runs.get());
+
+        assert!(
+            Renderer::new().render(
+                "[{name}] [{name|pad(7)}] [{name|rpad(7)}] [{name}] [{name|pad(7)}]",
+                &values
+            ) == Ok(String::from(
+                "[Homer] [  Homer] [Homer  ] [Homer] [  Homer]"
+            ))
+        );
+
+        // The value is looked up once, while each distinct filter chain runs once
+        assert!(values.0.get() == 1);
+        assert!(filters::RUNS.with(|runs| runs.get()) == runs + 2);
+    }
+
+    #[test]
+    fn repeated_filters_middlewares_still_run() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let calls = AtomicUsize::new(0);
+
+        let renderer = Renderer::new().middleware(move |_, value| {
+            format!("{}:{}", calls.fetch_add(1, Ordering::Relaxed) + 1, value)
+        });
+
+        assert!(
+            renderer.render("{a|pad(2)} {a|pad(2)}", &[("a", "x")])
+                == Ok(String::from("1: x 2: x"))
+        );
+    }
+
+    #[test]
+    fn compile() {
+        let compiled = Renderer::new()
+            .delimiters("<<", ">>")
+            .on_missing(OnMissing::Mark)
+            .compile("<<greet>> {name} <<name|pad(6)>><<end>>")
+            .unwrap();
+
+        for (name, after) in [
+            ("Homer", "Hello {name}  Homer[[end]]"),
+            ("Marge", "Hello {name}  Marge[[end]]"),
+            ("Bart", "Hello {name}   Bart[[end]]"),
+        ] {
+            assert!(
+                compiled.render(&[("greet", "Hello"), ("name", name)]) == Ok(String::from(after))
+            );
+        }
+
+        assert!(compiled
+            .template()
+            .required_keys()
+            .iter()
+            .eq(["end", "greet", "name"]));
+    }
+
+    #[test]
+    fn compile_name_too_long() {
+        assert!(
+            Renderer::new()
+                .max_name_len(3)
+                .compile("{name}")
+                .unwrap_err()
+                == RenderError::NameTooLong {
+                    name: String::from("name")
+                }
+        );
+    }
+
+    #[test]
+    fn auto_escape_html() {
+        let renderer = Renderer::new().auto_escape(EscapeMode::Html);
+        let values = [("x", r#"<a href="/tom">Tom & Jerry's</a>"#), ("n", "5")];
+
+        assert!(
+            renderer.render("<td>{x}</td>", &values)
+                == Ok(String::from(
+                    "<td>&lt;a href=&quot;/tom&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;</td>"
+                ))
+        );
+        assert!(
+            renderer.render("<td>{x|raw}</td>", &values)
+                == Ok(String::from(r#"<td><a href="/tom">Tom & Jerry's</a></td>"#))
+        );
+        assert!(renderer.render("<{n|pad(3, \"<\")}>", &values) == Ok(String::from("<&lt;&lt;5>")));
+        assert!(Renderer::new().render("{x}", &values) == Ok(String::from(values[0].1)));
+    }
+
+    #[test]
+    fn max_placeholders() {
+        let renderer = Renderer::new().max_placeholders(3);
+        let values = [("a", "1")];
+
+        assert!(renderer.render("{a}{a} \\{a} {a}", &values) == Ok(String::from("11 {a} 1")));
+        assert!(renderer.render("{a}{#if a}{a}{/if}", &values) == Ok(String::from("11")));
+        assert!(
+            renderer.compile("{a}{a}{#if a}{a}{/if}").err()
+                == Some(RenderError::TooManyPlaceholders { max: 3 })
+        );
+        assert!(
+            renderer.render(&"{a}".repeat(100_000), &values)
+                == Err(RenderError::TooManyPlaceholders { max: 3 })
+        );
+        assert!(Renderer::new().render(&"{a}".repeat(1_000), &values) == Ok("1".repeat(1_000)));
+    }
+
+    #[test]
+    fn constants() {
+        let renderer = Renderer::new()
+            .constant("version", "1.1.4")
+            .constant("built", "2026-10-15");
+
+        let empty: HashMap<String, String> = HashMap::new();
+
+        assert!(
+            renderer.render("v{version} ({built})", &empty)
+                == Ok(String::from("v1.1.4 (2026-10-15)"))
+        );
+        assert!(
+            renderer.render(
+                "{name} v{version}",
+                &[("version", "2.0"), ("name", "Homer")]
+            ) == Ok(String::from("Homer v2.0"))
+        );
+        assert!(
+            renderer.render("{name} v{version}", &empty)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("name"),
+                    available: None
+                })
+        );
+
+        let compiled = renderer.compile("v{version|pad(7)}").unwrap();
+
+        assert!(compiled.render(&empty) == Ok(String::from("v  1.1.4")));
+    }
+
+    #[test]
+    fn strip_bom() {
+        let values = [("name", "Homer"), ("title", "")];
+
+        assert!(
+            Renderer::new().render("\u{feff}{name} {name}", &values)
+                == Ok(String::from("\u{feff}Homer Homer"))
+        );
+        assert!(
+            Renderer::new()
+                .strip_bom(true)
+                .render("\u{feff}{name} {name}", &values)
+                == Ok(String::from("Homer Homer"))
+        );
+
+        let renderer = Renderer::new()
+            .collapse_whitespace(true)
+            .word_boundaries(true);
+
+        assert!(
+            renderer.render("\u{feff}{title} {name}", &values)
+                == Ok(String::from("\u{feff} Homer"))
+        );
+        assert!(
+            renderer
+                .strip_bom(true)
+                .render("\u{feff}{title} {name}", &values)
+                == Ok(String::from("Homer"))
+        );
+
+        // Only a single mark at the very start is stripped
+        assert!(
+            Renderer::new()
+                .strip_bom(true)
+                .render("\u{feff}\u{feff}{name}\u{feff}", &values)
+                == Ok(String::from("\u{feff}Homer\u{feff}"))
+        );
+    }
+
+    #[test]
+    fn delimiters() {
+        let renderer = Renderer::new().delimiters("<<", ">>");
+        let values = [("start", "Hello"), ("end", "world")];
+
+        assert!(
+            renderer.render("<<start>> {end} <<end|pad(6)>>", &values)
+                == Ok(String::from("Hello {end}  world"))
+        );
+    }
+
+    #[test]
+    fn delimiters_escaped_by_doubling() {
+        let renderer = Renderer::new().delimiters("<<", ">>");
+        let values = [("start", "Hello"), ("end", "world")];
+
+        assert!(
+            renderer.render("<<<<start>> <<start>> a <<<< b <<<<<<end>>", &values)
+                == Ok(String::from("<<start>> Hello a << b <<world"))
+        );
+    }
+
+    #[test]
+    fn delimiters_escaped_by_backslash() {
+        let renderer = Renderer::new().delimiters("[[", "]]");
+        let values = [("start", "Hello"), ("end", "world")];
+
+        assert!(
+            renderer.render(r"\[[start]] \\[[end]]", &values)
+                == Ok(String::from(r"[[start]] \world"))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "placeholder delimiters can't be empty")]
+    fn delimiters_empty() {
+        let _ = Renderer::new().delimiters("", "}");
+    }
+
+    #[test]
+    #[should_panic(expected = "missing value for placeholder: middle")]
+    fn panic_on_missing() {
+        let renderer = Renderer::new().panic_on_missing(true);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+
+        let _ = renderer.render("{start} {middle} world", &values);
+    }
+
+    #[test]
+    #[should_panic(expected = "missing value for placeholder: member")]
+    fn panic_on_missing_block() {
+        let renderer = Renderer::new().panic_on_missing(true);
+
+        let _ = renderer.render("{#if member}Welcome back{/if}", &[("name", "Homer")]);
+    }
+
+    #[test]
+    fn panic_on_missing_block_absent() {
+        let renderer = Renderer::new()
+            .panic_on_missing(true)
+            .missing_blocks_absent(true);
+
+        assert!(
+            renderer.render("{#each items}- {items}\n{/each}Done", &[("name", "Homer")])
+                == Ok(String::from("Done"))
+        );
+    }
+
+    #[test]
+    fn panic_on_missing_not_missing() {
+        let renderer = Renderer::new().panic_on_missing(true);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+
+        assert!(renderer.render("{start} world", &values) == Ok(String::from("Hello world")));
+    }
+
+    #[test]
+    fn missing_is_error_by_default() {
+        let values: HashMap<String, String> = HashMap::new();
+
+        assert!(
+            Renderer::new().render("{start} world", &values)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("start"),
+                    available: None,
+                })
+        );
+    }
+
+    #[test]
+    fn middleware_chain() {
+        let renderer = Renderer::new()
+            .middleware(|_, value| value.trim().to_string())
+            .middleware(|_, value| value.to_uppercase());
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from(" Hello\t"));
+        values.insert(String::from("end"), String::from("\nworld "));
+
+        assert!(renderer.render("{start} {end}!", &values) == Ok(String::from("HELLO WORLD!")));
+    }
+
+    #[test]
+    fn middleware_order() {
+        let renderer = Renderer::new()
+            .middleware(|_, value| value.to_uppercase())
+            .middleware(|_, value| value.trim().to_string());
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from(" Hello "));
+
+        assert!(renderer.render("[{start}]", &values) == Ok(String::from("[HELLO]")));
+    }
+
+    #[test]
+    fn middleware_gets_name() {
+        let renderer = Renderer::new().middleware(|name, value| format!("{}={}", name, value));
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(
+            renderer.render("{start} {end}", &values) == Ok(String::from("start=Hello end=world"))
+        );
+    }
+
+    #[test]
+    fn word_boundaries_free() {
+        let renderer = Renderer::new().word_boundaries(true);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("foo"), String::from("FOO"));
+
+        assert!(
+            renderer.render("{foo} ({foo}) -{foo}- {foo}", &values)
+                == Ok(String::from("FOO (FOO) -FOO- FOO"))
+        );
+    }
+
+    #[test]
+    fn word_boundaries_glued() {
+        let renderer = Renderer::new().word_boundaries(true);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("foo"), String::from("FOO"));
+
+        assert!(
+            renderer.render("prefix{foo} {foo}bar _{foo} {foo}9 {foo}", &values)
+                == Ok(String::from("prefix{foo} {foo}bar _{foo} {foo}9 FOO"))
+        );
+    }
+
+    #[test]
+    fn word_boundaries_off_by_default() {
+        let mut values = HashMap::new();
+        values.insert(String::from("foo"), String::from("FOO"));
+
+        assert!(
+            Renderer::new().render("prefix{foo} {foo}bar", &values)
+                == Ok(String::from("prefixFOO FOObar"))
+        );
+    }
+
+    #[test]
+    fn name_within_limit() {
+        let renderer = Renderer::new().max_name_len(6);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("middle"), String::from("beautiful"));
+
+        assert!(
+            renderer.render("Hello {middle} world", &values)
+                == Ok(String::from("Hello beautiful world"))
+        );
+    }
+
+    #[test]
+    fn name_too_long_error() {
+        let renderer = Renderer::new().max_name_len(5);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("middle"), String::from("beautiful"));
+
+        assert!(
+            renderer.render("Hello {middle} world", &values)
+                == Err(RenderError::NameTooLong {
+                    name: String::from("middle")
+                })
+        );
+    }
+
+    #[test]
+    fn name_too_long_literal() {
+        let renderer = Renderer::new().max_name_len(5).long_name(LongName::Literal);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("middle"), String::from("beautiful"));
+
+        assert!(
+            renderer.render("{start} {middle} world", &values)
+                == Ok(String::from("Hello {middle} world"))
+        );
+    }
+
+    #[test]
+    fn collapse_middle() {
+        let renderer = Renderer::new().collapse_whitespace(true);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("middle"), String::from(""));
+
+        assert!(
+            renderer.render("Hello {middle} world", &values) == Ok(String::from("Hello world"))
+        );
+        assert!(
+            renderer.render("Hello \t{middle}\t world", &values)
+                == Ok(String::from("Hello \tworld"))
+        );
+    }
+
+    #[test]
+    fn collapse_start_and_repeated() {
+        let renderer = Renderer::new().collapse_whitespace(true);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from(""));
+        values.insert(String::from("middle"), String::from(""));
+
+        assert!(renderer.render("{start} {middle} world", &values) == Ok(String::from("world")));
+    }
+
+    #[test]
+    fn collapse_keeps_newlines_and_glued_text() {
+        let renderer = Renderer::new().collapse_whitespace(true);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("middle"), String::from(""));
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(
+            renderer.render("Hello {middle}\n{end}", &values) == Ok(String::from("Hello \nworld"))
+        );
+        assert!(renderer.render("Hello{middle} {end}", &values) == Ok(String::from("Hello world")));
+    }
+}
@@ -0,0 +1,1990 @@
+use crate::filters;
+use crate::matcher::{self, Match, Matcher};
+use crate::unicode;
+use crate::{AsValues, RenderError, Renderer};
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Segment {
+    Literal(String),
+    // A placeholder, starting `start` bytes into the template text, with the segments of its
+    // default, if it has one, which are rendered in its place when its value is missing
+    Placeholder {
+        name: String,
+        filters: Vec<Filter>,
+        source: String,
+        start: usize,
+        default: Option<Vec<Segment>>,
+    },
+    // A block such as `{#if name}...{/if}`, whose body is included depending on the value of
+    // `name`, with its opening tag starting `start` bytes into the template text
+    Block {
+        kind: BlockKind,
+        name: String,
+        body: Vec<Segment>,
+        start: usize,
+    },
+}
+
+// The kinds of block, each opened by `{#kind name}` and closed by `{/kind}`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BlockKind {
+    If,
+    Each,
+}
+
+impl BlockKind {
+    fn parse(kind: &str) -> Option<BlockKind> {
+        match kind {
+            "if" => Some(BlockKind::If),
+            "each" => Some(BlockKind::Each),
+            _ => None,
+        }
+    }
+
+    // The value of the block's name each time its body is included: once with the value itself
+    // for an `if` whose value isn't empty, or once for each line of the value for an `each`
+    pub(crate) fn iterations(self, value: &str) -> Vec<&str> {
+        match self {
+            BlockKind::If if value.is_empty() => Vec::new(),
+            BlockKind::If => vec![value],
+            BlockKind::Each => value.lines().collect(),
+        }
+    }
+}
+
+// A block tag found within literal text
+pub(crate) enum BlockTag<'t> {
+    Open { kind: BlockKind, name: &'t str },
+    Close { kind: BlockKind },
+}
+
+// Match a block tag, such as `{#if name}` or `{/if}`, at the start of the text, returning it
+// along with its length
+fn block_tag(text: &str) -> Option<(BlockTag<'_>, usize)> {
+    let end = text.find('}')?;
+    let tag = text[..end].strip_prefix('{')?;
+
+    let found = match tag.strip_prefix('/') {
+        Some(kind) => BlockTag::Close {
+            kind: BlockKind::parse(kind)?,
+        },
+        None => {
+            let (kind, name) = tag.strip_prefix('#')?.split_once(' ')?;
+
+            if name.is_empty() || !name.chars().all(is_word) {
+                return None;
+            }
+
+            BlockTag::Open {
+                kind: BlockKind::parse(kind)?,
+                name,
+            }
+        }
+    };
+
+    Some((found, end + 1))
+}
+
+// Find the block tags within literal text, each with where it starts, its length, and how many
+// backslashes directly precede it. A tag directly after an opening brace is escaped, so it's skipped
+pub(crate) fn block_tags(text: &str) -> impl Iterator<Item = (usize, BlockTag<'_>, usize, usize)> {
+    text.match_indices('{').filter_map(move |(start, _)| {
+        if text[..start].ends_with('{') {
+            return None;
+        }
+
+        let (tag, len) = block_tag(&text[start..])?;
+        let backslashes = start - text[..start].trim_end_matches('\\').len();
+
+        Some((start, tag, len, backslashes))
+    })
+}
+
+// A segment, or a block tag that's yet to be matched with another, found while parsing
+enum Parsed<'t> {
+    Segment(Segment),
+    Tag {
+        tag: BlockTag<'t>,
+        start: usize,
+        source: &'t str,
+    },
+}
+
+// Split the literal text starting `offset` bytes into the template at its block tags, pushing the
+// text onto `literal` and each tag onto `parsed`. As with placeholders, each pair of backslashes
+// directly before a tag is a literal backslash, while a lone backslash leaves the tag as literal
+// text
+fn split_tags<'t>(
+    text: &'t str,
+    offset: usize,
+    literal: &mut String,
+    parsed: &mut Vec<Parsed<'t>>,
+) {
+    let mut cursor = 0;
+
+    for (start, tag, len, backslashes) in block_tags(text) {
+        if backslashes % 2 == 1 {
+            literal.push_str(&text[cursor..start - backslashes]);
+            literal.push_str(&"\\".repeat(backslashes / 2));
+            literal.push_str(&text[start..start + len]);
+        } else {
+            literal.push_str(&text[cursor..start - backslashes / 2]);
+
+            if !literal.is_empty() {
+                parsed.push(Parsed::Segment(Segment::Literal(std::mem::take(literal))));
+            }
+
+            parsed.push(Parsed::Tag {
+                tag,
+                start: offset + start,
+                source: &text[start..start + len],
+            });
+        }
+
+        cursor = start + len;
+    }
+
+    literal.push_str(&text[cursor..]);
+}
+
+// Nest the segments between matching block tags into blocks. Tags that aren't matched by another
+// are left as literal text
+fn nest_blocks(parsed: Vec<Parsed<'_>>) -> Vec<Segment> {
+    // The blocks still open, each with its opening tag and the segments of its body so far
+    let mut open: Vec<(BlockKind, &str, usize, &str, Vec<Segment>)> = Vec::new();
+    let mut nested = Vec::new();
+
+    fn push(
+        open: &mut [(BlockKind, &str, usize, &str, Vec<Segment>)],
+        nested: &mut Vec<Segment>,
+        segment: Segment,
+    ) {
+        let body = match open.last_mut() {
+            Some((.., body)) => body,
+            None => nested,
+        };
+
+        match (body.last_mut(), segment) {
+            (_, Segment::Literal(more)) if more.is_empty() => {}
+            (Some(Segment::Literal(text)), Segment::Literal(more)) => text.push_str(&more),
+            (_, segment) => body.push(segment),
+        }
+    }
+
+    for item in parsed {
+        match item {
+            Parsed::Segment(segment) => push(&mut open, &mut nested, segment),
+            Parsed::Tag {
+                tag: BlockTag::Open { kind, name },
+                start,
+                source,
+            } => open.push((kind, name, start, source, Vec::new())),
+            Parsed::Tag {
+                tag: BlockTag::Close { kind },
+                source,
+                ..
+            } => match open.pop() {
+                Some((open_kind, name, start, _, body)) if open_kind == kind => push(
+                    &mut open,
+                    &mut nested,
+                    Segment::Block {
+                        kind,
+                        name: name.to_string(),
+                        body,
+                        start,
+                    },
+                ),
+                unmatched => {
+                    open.extend(unmatched);
+                    push(&mut open, &mut nested, Segment::Literal(source.to_string()));
+                }
+            },
+        }
+    }
+
+    // Blocks never closed are literal text after all
+    while let Some((.., tag, body)) = open.pop() {
+        push(&mut open, &mut nested, Segment::Literal(tag.to_string()));
+
+        for segment in body {
+            push(&mut open, &mut nested, segment);
+        }
+    }
+
+    nested
+}
+
+// Move each segment `by` bytes further into the template text, for segments parsed from text
+// starting that far into it
+fn shift(segments: &mut [Segment], by: usize) {
+    for segment in segments {
+        match segment {
+            Segment::Literal(_) => {}
+            Segment::Placeholder { start, default, .. } => {
+                *start += by;
+
+                if let Some(default) = default {
+                    shift(default, by);
+                }
+            }
+            Segment::Block { start, body, .. } => {
+                *start += by;
+                shift(body, by);
+            }
+        }
+    }
+}
+
+// A filter applied to a placeholder's value, such as `pad(8)` in `{code|pad(8)}`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Filter {
+    pub(crate) name: String,
+    pub(crate) args: Vec<String>,
+}
+
+// Written back out as template syntax, with arguments only quoted when they need to be
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+
+        if self.args.is_empty() {
+            return Ok(());
+        }
+
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| {
+                if !arg.is_empty() && arg.chars().all(|c| is_word(c) || c == '.' || c == '-') {
+                    arg.clone()
+                } else {
+                    format!("\"{}\"", arg)
+                }
+            })
+            .collect();
+
+        write!(f, "({})", args.join(", "))
+    }
+}
+
+// Write a filter chain back out as template syntax, such as `truncate(8)|pad(10, "0")`
+pub(crate) fn filter_chain(filters: &[Filter]) -> String {
+    filters
+        .iter()
+        .map(|filter| filter.to_string())
+        .collect::<Vec<String>>()
+        .join("|")
+}
+
+// Write out each segment for `Template::debug_structure()`, indented by `depth` levels
+fn debug_segments(segments: &[Segment], depth: usize, dump: &mut String) {
+    let indent = "  ".repeat(depth);
+
+    for segment in segments {
+        match segment {
+            Segment::Literal(text) if text.chars().count() > 20 => {
+                let text: String = text.chars().take(20).collect();
+                dump.push_str(&format!("{}literal {:?}...\n", indent, text));
+            }
+            Segment::Literal(text) => dump.push_str(&format!("{}literal {:?}\n", indent, text)),
+            Segment::Placeholder {
+                name,
+                filters,
+                default,
+                ..
+            } => {
+                if filters.is_empty() {
+                    dump.push_str(&format!("{}placeholder {}\n", indent, name));
+                } else {
+                    dump.push_str(&format!(
+                        "{}placeholder {} | {}\n",
+                        indent,
+                        name,
+                        filter_chain(filters)
+                    ));
+                }
+
+                if let Some(default) = default {
+                    dump.push_str(&format!("{}default\n", indent));
+                    debug_segments(default, depth + 1, dump);
+                    dump.push_str(&format!("{}end default\n", indent));
+                }
+            }
+            Segment::Block {
+                kind, name, body, ..
+            } => {
+                let kind = match kind {
+                    BlockKind::If => "if",
+                    BlockKind::Each => "each",
+                };
+
+                dump.push_str(&format!("{}block {} {}\n", indent, kind, name));
+                debug_segments(body, depth + 1, dump);
+                dump.push_str(&format!("{}end {}\n", indent, kind));
+            }
+        }
+    }
+}
+
+/// How a `Renderer` treats placeholder names longer than its `max_name_len()`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LongName {
+    /// Fail with `RenderError::NameTooLong`
+    #[default]
+    Error,
+
+    /// Leave the placeholder as literal text
+    Literal,
+}
+
+// Options limiting what is accepted while parsing a template
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ParseOptions {
+    pub(crate) max_name_len: Option<usize>,
+    pub(crate) max_placeholders: Option<usize>,
+    pub(crate) long_name: LongName,
+    pub(crate) word_boundaries: bool,
+    pub(crate) strip_bom: bool,
+    pub(crate) delimiters: Option<Matcher>,
+    // Whether a placeholder's name can be composed around an inner placeholder, such as
+    // `{col_{n}}`, for `render_nested()`. The composed name is kept braced, such as `col_{n}`
+    pub(crate) nested: bool,
+}
+
+// A lossless view of the template text, so that tooling can rewrite a template without disturbing
+// anything other than the placeholders it's interested in
+pub(crate) enum Token<'t> {
+    // Literal text, exactly as it appears in the template
+    Text(&'t str),
+    // Backslashes escaping a placeholder, or a backslash, in the template
+    Escape {
+        source: &'t str,
+        text: String,
+    },
+    // A placeholder to be replaced with its value, with its default, if it has one, along with
+    // where the default starts within the template
+    Placeholder {
+        start: usize,
+        source: &'t str,
+        name: &'t str,
+        filters: &'t str,
+        default: Option<(usize, &'t str)>,
+    },
+}
+
+// Scan the template text containing placeholders in the form `{name}`
+pub(crate) fn scan(template: &str) -> Vec<Token<'_>> {
+    scan_with(template, Matcher::braces(), true, false).collect()
+}
+
+#[cfg(test)]
+thread_local! {
+    // How many times a template has been scanned, so tests can check for fast paths
+    pub(crate) static SCANS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    // How many matches have been scanned, so tests can check that a scan stops early
+    pub(crate) static MATCHES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+fn scan_with<'t>(
+    template: &'t str,
+    matcher: &'t Matcher,
+    brace_escapes: bool,
+    nested: bool,
+) -> impl Iterator<Item = Token<'t>> {
+    #[cfg(test)]
+    SCANS.with(|scans| scans.set(scans.get() + 1));
+
+    let mut matches: Box<dyn Iterator<Item = Match> + 't> = if nested && brace_escapes {
+        Box::new(with_nested(template, matcher.matches(template).collect()).into_iter())
+    } else {
+        Box::new(matcher.matches(template).fuse())
+    };
+
+    // The tokens made from the last match, which can make up to three of them
+    let mut pending = VecDeque::new();
+    let mut cursor = 0;
+
+    // Tokens are scanned only as they're needed, so the rest of the template isn't scanned when a
+    // caller stops early, such as on reaching a limit on the number of placeholders
+    std::iter::from_fn(move || loop {
+        if let Some(token) = pending.pop_front() {
+            return Some(token);
+        }
+
+        let found = match matches.next() {
+            Some(found) => {
+                #[cfg(test)]
+                MATCHES.with(|matches| matches.set(matches.get() + 1));
+
+                found
+            }
+            None if cursor < template.len() => {
+                let text = &template[cursor..];
+                cursor = template.len();
+                return Some(Token::Text(text));
+            }
+            None => return None,
+        };
+
+        let (start, end, name, filters, default) = match found {
+            Match::Placeholder {
+                span,
+                name,
+                filters,
+                default,
+            } => (
+                span.start,
+                span.end,
+                &template[name],
+                &template[filters],
+                default.map(|default| (default.start, &template[default])),
+            ),
+            // A doubled custom opening delimiter is a literal opening delimiter
+            Match::Escape(double) => {
+                if cursor < double.start {
+                    pending.push_back(Token::Text(&template[cursor..double.start]));
+                }
+
+                let source = &template[double.clone()];
+
+                pending.push_back(Token::Escape {
+                    source,
+                    text: source[..source.len() / 2].to_string(),
+                });
+
+                cursor = double.end;
+                continue;
+            }
+        };
+
+        // A placeholder directly after an opening brace is escaped, so it's left as is
+        if brace_escapes && template[..start].ends_with('{') {
+            continue;
+        }
+
+        // A quoted name of only whitespace names nothing, so it's left as literal text
+        if unquote_name(name).trim().is_empty() {
+            continue;
+        }
+
+        // Each pair of backslashes directly before a placeholder is a literal backslash, while a
+        // lone backslash leaves the placeholder as literal text
+        let before = &template[cursor..start];
+        let text = before.trim_end_matches('\\');
+        let backslashes = before.len() - text.len();
+        let escape_start = cursor + text.len();
+
+        if !text.is_empty() {
+            pending.push_back(Token::Text(text));
+        }
+
+        if backslashes % 2 == 1 {
+            pending.push_back(Token::Escape {
+                source: &template[escape_start..end],
+                text: "\\".repeat(backslashes / 2) + &template[start..end],
+            });
+        } else {
+            if backslashes > 0 {
+                pending.push_back(Token::Escape {
+                    source: &template[escape_start..start],
+                    text: "\\".repeat(backslashes / 2),
+                });
+            }
+
+            pending.push_back(Token::Placeholder {
+                start,
+                source: &template[start..end],
+                name: unquote_name(name),
+                filters,
+                default,
+            });
+        }
+
+        cursor = end;
+    })
+}
+
+// Add the placeholders whose names are composed around an inner placeholder, such as `{col_{n}}`,
+// to those matched. Where matches overlap, the one starting first wins, so the inner placeholder
+// isn't matched on its own
+fn with_nested(template: &str, matches: Vec<Match>) -> Vec<Match> {
+    let mut all: Vec<Match> = matcher::nested(template)
+        .into_iter()
+        .map(|nested| Match::Placeholder {
+            name: nested.prefix.start..nested.suffix.end,
+            filters: nested.span.end - 1..nested.span.end - 1,
+            default: None,
+            span: nested.span,
+        })
+        .chain(matches)
+        .collect();
+
+    all.sort_by_key(|found| found.span().start);
+
+    let mut end = 0;
+
+    all.into_iter()
+        .filter(|found| {
+            if found.span().start < end {
+                return false;
+            }
+
+            end = found.span().end;
+            true
+        })
+        .collect()
+}
+
+fn unquote_name(name: &str) -> &str {
+    match name
+        .strip_prefix('"')
+        .and_then(|name| name.strip_suffix('"'))
+    {
+        Some(name) => name,
+        None => name,
+    }
+}
+
+// Whether the placeholder name has to be quoted to be written in a template
+pub(crate) fn needs_quotes(name: &str) -> bool {
+    name.is_empty() || !name.chars().all(is_word)
+}
+
+// Split a filter chain such as `|truncate(8)|pad(10, "0")` into its filters, with each argument
+// trimmed of surrounding whitespace and quotes. A format spec ending the chain becomes the last
+// filter, so `:>10` is `|pad(10, " ")`, `:<10` or `:10` is `|rpad(10, " ")`, and `:^10` is
+// `|center(10, " ")`
+fn parse_filters(chain: &str) -> Vec<Filter> {
+    let (chain, spec) = split_spec(chain);
+    let mut filters = Vec::new();
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut quoted = false;
+    let mut depth = 0;
+
+    for c in chain.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            '|' if !quoted && depth == 0 => {
+                parts.push(std::mem::take(&mut part));
+                continue;
+            }
+            _ => {}
+        }
+
+        part.push(c);
+    }
+
+    parts.push(part);
+
+    for part in parts.into_iter().skip(1) {
+        let (name, args) = match part.split_once('(') {
+            None => (part.as_str(), ""),
+            Some((name, args)) => (name, args.strip_suffix(')').unwrap_or(args)),
+        };
+
+        filters.push(Filter {
+            name: name.to_string(),
+            args: split_args(args),
+        });
+    }
+
+    filters.extend(spec);
+    filters
+}
+
+// Split a format spec such as `:*^10` off the end of a filter chain. As filters never end with a
+// colon, a colon before both a fill character and an alignment means the fill is given
+fn split_spec(chain: &str) -> (&str, Option<Filter>) {
+    let head = chain.trim_end_matches(|c: char| c.is_ascii_digit());
+
+    if head.len() == chain.len() {
+        return (chain, None);
+    }
+
+    let mut chars = head.chars().rev();
+
+    let (align, fill, start) = match (chars.next(), chars.next(), chars.next()) {
+        (Some(align @ ('<' | '>' | '^')), Some(fill), Some(':')) => {
+            (align, fill, head.len() - fill.len_utf8() - 2)
+        }
+        (Some(align @ ('<' | '>' | '^')), Some(':'), _) => (align, ' ', head.len() - 2),
+        (Some(':'), _, _) => ('<', ' ', head.len() - 1),
+        _ => return (chain, None),
+    };
+
+    let name = match align {
+        '>' => "pad",
+        '^' => "center",
+        _ => "rpad",
+    };
+
+    let filter = Filter {
+        name: name.to_string(),
+        args: vec![chain[head.len()..].to_string(), fill.to_string()],
+    };
+
+    (&chain[..start], Some(filter))
+}
+
+// Split filter arguments on the commas outside quotes
+fn split_args(args: &str) -> Vec<String> {
+    if args.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut split = Vec::new();
+    let mut arg = String::new();
+    let mut quoted = false;
+
+    for c in args.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                split.push(unquote(&arg));
+                arg.clear();
+                continue;
+            }
+            _ => {}
+        }
+
+        arg.push(c);
+    }
+
+    split.push(unquote(&arg));
+    split
+}
+
+fn unquote(arg: &str) -> String {
+    let arg = arg.trim();
+
+    match arg.strip_prefix('"').and_then(|arg| arg.strip_suffix('"')) {
+        Some(arg) => arg.to_string(),
+        None => arg.to_string(),
+    }
+}
+
+// Whether the character can be part of an identifier, exactly the same as `\w`
+pub(crate) fn is_word(c: char) -> bool {
+    if c.is_ascii() {
+        return c.is_ascii_alphanumeric() || c == '_';
+    }
+
+    unicode::WORD
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                Ordering::Less
+            } else if start > c {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// A template parsed once so that it can be rendered many times
+///
+/// # Example
+///
+/// ```
+/// use placeholder::Template;
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///   let template = Template::parse("<h1>{greet} {name}</h1>");
+///
+///   let mut values = HashMap::new();
+///   values.insert(String::from("greet"), String::from("Hello"));
+///   values.insert(String::from("name"), String::from("Homer"));
+///
+///   assert!(template.render(&values) == Ok(String::from("<h1>Hello Homer</h1>")));
+///
+///   values.insert(String::from("name"), String::from("Marge"));
+///
+///   assert!(template.render(&values) == Ok(String::from("<h1>Hello Marge</h1>")));
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+    // The distinct placeholder names, gathered while parsing
+    required: BTreeSet<String>,
+}
+
+impl Template {
+    /// Parse the template text into its literal text and placeholders
+    ///
+    /// A placeholder directly after an opening brace, such as `{{name}`, is left as is. A backslash
+    /// directly before a placeholder escapes it, so `\{name}` is the literal text `{name}`, while a
+    /// pair of backslashes is a literal backslash, so `\\{name}` is a backslash followed by the
+    /// value of `name`. Backslashes anywhere else are literal text.
+    ///
+    /// A placeholder can be followed by a chain of filters that transform its value, such as
+    /// `{code|truncate(8)|pad(10, "0")}`. See the crate documentation for the available filters.
+    ///
+    /// # Parameters
+    ///
+    /// `template` is the template text containing placeholders in the form `{name}`
+    ///
+    /// # Returns
+    ///
+    /// The parsed template, ready for rendering
+    pub fn parse(template: &str) -> Template {
+        match Template::parse_with_options(template, &ParseOptions::default()) {
+            Ok(template) => template,
+            Err(_) => panic!("at the disco"),
+        }
+    }
+
+    // Parse the template text containing placeholders in the form `{name}`, as limited by the
+    // `Renderer`'s options
+    pub(crate) fn parse_with_options(
+        template: &str,
+        options: &ParseOptions,
+    ) -> Result<Template, RenderError> {
+        let template = if options.strip_bom {
+            template.strip_prefix('\u{feff}').unwrap_or(template)
+        } else {
+            template
+        };
+
+        match &options.delimiters {
+            None => Template::parse_with(template, Matcher::braces(), true, options),
+            Some(matcher) => Template::parse_with(template, matcher, false, options),
+        }
+    }
+
+    /// Parse the template text using `{{name}}` placeholders
+    ///
+    /// Single braces are always literal text, which allows templates such as JSON to be written
+    /// without escaping. Backslashes escape placeholders the same as `parse()`.
+    ///
+    /// # Parameters
+    ///
+    /// `template` is the template text containing placeholders in the form `{{name}}`
+    ///
+    /// # Returns
+    ///
+    /// The parsed template, ready for rendering
+    pub fn parse_double_brace(template: &str) -> Template {
+        match Template::parse_with(
+            template,
+            Matcher::double_braces(),
+            false,
+            &ParseOptions::default(),
+        ) {
+            Ok(template) => template,
+            Err(_) => panic!("at the disco"),
+        }
+    }
+
+    fn parse_with(
+        template: &str,
+        matcher: &Matcher,
+        brace_escapes: bool,
+        options: &ParseOptions,
+    ) -> Result<Template, RenderError> {
+        let mut parsed = Vec::new();
+        let mut literal = String::new();
+        let mut placeholders = 0;
+        // Where the current token starts within the template, as tokens cover all of it in order
+        let mut offset = 0;
+
+        for token in scan_with(template, matcher, brace_escapes, options.nested) {
+            let token_start = offset;
+
+            offset += match &token {
+                Token::Text(text) => text.len(),
+                Token::Escape { source, .. } | Token::Placeholder { source, .. } => source.len(),
+            };
+
+            match token {
+                // Blocks use braces, so they're only found in templates using braces for
+                // placeholders
+                Token::Text(text) if brace_escapes => {
+                    split_tags(text, token_start, &mut literal, &mut parsed)
+                }
+                Token::Text(text) => literal.push_str(text),
+                Token::Escape { text, .. } => literal.push_str(&text),
+                Token::Placeholder {
+                    start,
+                    source,
+                    name,
+                    filters,
+                    default,
+                } => {
+                    // A placeholder glued to identifier characters isn't a word of its own
+                    if options.word_boundaries
+                        && (template[..start].chars().next_back().is_some_and(is_word)
+                            || template[start + source.len()..]
+                                .chars()
+                                .next()
+                                .is_some_and(is_word))
+                    {
+                        literal.push_str(source);
+                        continue;
+                    }
+
+                    if options.max_name_len.is_some_and(|max| name.len() > max) {
+                        match options.long_name {
+                            LongName::Error => {
+                                return Err(RenderError::NameTooLong {
+                                    name: name.to_string(),
+                                })
+                            }
+                            LongName::Literal => {
+                                literal.push_str(source);
+                                continue;
+                            }
+                        }
+                    }
+
+                    placeholders += 1;
+
+                    // A default is a template of its own, which always uses braces for
+                    // placeholders. Its placeholders and blocks count towards the limit too
+                    let default = match default {
+                        None => None,
+                        Some((default_start, default)) => {
+                            let mut default =
+                                Template::parse_with(default, Matcher::braces(), true, options)?;
+
+                            shift(&mut default.segments, default_start);
+                            placeholders += default.names().len();
+                            Some(default.segments)
+                        }
+                    };
+
+                    if let Some(max) = options.max_placeholders.filter(|&max| placeholders > max) {
+                        return Err(RenderError::TooManyPlaceholders { max });
+                    }
+
+                    if !literal.is_empty() {
+                        parsed.push(Parsed::Segment(Segment::Literal(std::mem::take(
+                            &mut literal,
+                        ))));
+                    }
+
+                    parsed.push(Parsed::Segment(Segment::Placeholder {
+                        name: name.to_string(),
+                        filters: parse_filters(filters),
+                        source: source.to_string(),
+                        start,
+                        default,
+                    }));
+                }
+            }
+        }
+
+        if !literal.is_empty() {
+            parsed.push(Parsed::Segment(Segment::Literal(literal)));
+        }
+
+        let mut template = Template {
+            segments: nest_blocks(parsed),
+            required: BTreeSet::new(),
+        };
+
+        // Each block counts once towards the limit, along with the placeholders counted so far
+        if let Some(max) = options.max_placeholders {
+            let blocks = template
+                .all_segments()
+                .into_iter()
+                .filter(|segment| matches!(segment, Segment::Block { .. }))
+                .count();
+
+            if placeholders + blocks > max {
+                return Err(RenderError::TooManyPlaceholders { max });
+            }
+        }
+
+        template.required = template.names().into_iter().map(String::from).collect();
+
+        Ok(template)
+    }
+
+    pub(crate) fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    // Every segment of the template in the order they appear, with each block followed by the
+    // segments of its body
+    pub(crate) fn all_segments(&self) -> Vec<&Segment> {
+        fn flatten<'s>(segments: &'s [Segment], all: &mut Vec<&'s Segment>) {
+            for segment in segments {
+                all.push(segment);
+
+                if let Segment::Block { body, .. } = segment {
+                    flatten(body, all);
+                }
+            }
+        }
+
+        let mut all = Vec::new();
+        flatten(&self.segments, &mut all);
+        all
+    }
+
+    // The names of the placeholders and blocks in the order they appear, including repeats and
+    // the placeholders within defaults
+    pub(crate) fn names(&self) -> Vec<&str> {
+        fn gather<'s>(segments: &'s [Segment], names: &mut Vec<&'s str>) {
+            for segment in segments {
+                match segment {
+                    Segment::Literal(_) => {}
+                    Segment::Placeholder { name, default, .. } => {
+                        names.push(name);
+
+                        if let Some(default) = default {
+                            gather(default, names);
+                        }
+                    }
+                    Segment::Block { name, body, .. } => {
+                        names.push(name);
+                        gather(body, names);
+                    }
+                }
+            }
+        }
+
+        let mut names = Vec::new();
+        gather(&self.segments, &mut names);
+        names
+    }
+
+    // The sorted names of the placeholders and blocks that could be missing when rendered,
+    // skipping placeholders with a default or that are optional, though not those within defaults
+    pub(crate) fn needed_names(&self) -> BTreeSet<&str> {
+        fn gather<'s>(segments: &'s [Segment], names: &mut BTreeSet<&'s str>) {
+            for segment in segments {
+                match segment {
+                    Segment::Literal(_) => {}
+                    Segment::Placeholder { name, default, .. } => match default {
+                        Some(default) => gather(default, names),
+                        None => {
+                            names.insert(name);
+                        }
+                    },
+                    Segment::Block { name, body, .. } => {
+                        names.insert(name);
+                        gather(body, names);
+                    }
+                }
+            }
+        }
+
+        let mut names = BTreeSet::new();
+        gather(&self.segments, &mut names);
+        names
+    }
+
+    /// The names of the placeholders within the template, each listed once
+    ///
+    /// These are gathered while parsing, so they can be checked against the placeholder values
+    /// before rendering without scanning the template again.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Template;
+    ///
+    /// fn main() {
+    ///   let template = Template::parse("<h1>{greet} {name}</h1><p>Bye {name}</p>");
+    ///
+    ///   assert!(template.required_keys().iter().eq(["greet", "name"]));
+    /// }
+    /// ```
+    pub fn required_keys(&self) -> &BTreeSet<String> {
+        &self.required
+    }
+
+    /// Measure the parsed template, such as for sizing output buffers
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Template;
+    ///
+    /// fn main() {
+    ///   let template = Template::parse("<h1>{greet} {name}</h1><p>Bye {name}</p>");
+    ///
+    ///   let stats = template.stats();
+    ///
+    ///   assert!(stats.literal_bytes == 21);
+    ///   assert!(stats.placeholders == 3);
+    ///   assert!(stats.distinct_names == 2);
+    ///   assert!(stats.max_name_len == 5);
+    /// }
+    /// ```
+    pub fn stats(&self) -> TemplateStats {
+        let mut stats = TemplateStats::default();
+        let mut names = HashSet::new();
+
+        for segment in self.all_segments() {
+            match segment {
+                Segment::Literal(text) => stats.literal_bytes += text.len(),
+                Segment::Placeholder { name, .. } => {
+                    stats.placeholders += 1;
+                    stats.max_name_len = stats.max_name_len.max(name.len());
+                    names.insert(name.as_str());
+                }
+                Segment::Block { .. } => {}
+            }
+        }
+
+        stats.distinct_names = names.len();
+        stats
+    }
+
+    /// Estimate how much of the template is dynamic, such as for flagging templates that aren't
+    /// mostly static for review
+    ///
+    /// This is the fraction of the template text taken up by placeholders, from `0.0` for a
+    /// template of only literal text, or no text at all, to `1.0` for one of only placeholders.
+    /// Each placeholder counts at its length within the template, `{name}` and any filters
+    /// included, as the length of its value isn't known until it's rendered.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Template;
+    ///
+    /// fn main() {
+    ///   assert!(Template::parse("<h1>{name}</h1>").dynamic_ratio() == 0.4);
+    ///   assert!(Template::parse("<h1>Hello</h1>").dynamic_ratio() == 0.0);
+    /// }
+    /// ```
+    pub fn dynamic_ratio(&self) -> f64 {
+        let (mut literal, mut dynamic) = (0, 0);
+
+        for segment in self.all_segments() {
+            match segment {
+                Segment::Literal(text) => literal += text.len(),
+                Segment::Placeholder { source, .. } => dynamic += source.len(),
+                Segment::Block { .. } => {}
+            }
+        }
+
+        if dynamic == 0 {
+            0.0
+        } else {
+            dynamic as f64 / (literal + dynamic) as f64
+        }
+    }
+
+    /// The template's literal text, with every placeholder and block tag removed
+    ///
+    /// The skeleton only depends on the template, so templates sharing a skeleton differ only in
+    /// their placeholders, such as for bucketing rendered output in a cache. Escapes are applied,
+    /// and the body of each block is included once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Template;
+    ///
+    /// fn main() {
+    ///   assert!(Template::parse("<h1>{greet} {name}</h1> \\{name}").skeleton()
+    ///     == String::from("<h1> </h1> {name}"));
+    /// }
+    /// ```
+    pub fn skeleton(&self) -> String {
+        self.literal_text("")
+    }
+
+    // The template's literal text, with every placeholder and opening block tag replaced with
+    // `replacement`, and closing block tags removed
+    pub(crate) fn literal_text(&self, replacement: &str) -> String {
+        self.all_segments()
+            .into_iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.as_str(),
+                _ => replacement,
+            })
+            .collect()
+    }
+
+    /// A human readable dump of the parsed template, one segment per line, for debugging
+    ///
+    /// Each line is a literal with its text quoted, after escapes are applied and cut short past
+    /// 20 characters, a placeholder with its name and filter chain, or a block tag, with the body
+    /// of each block indented. This shows how a template was parsed, such as where escapes and
+    /// filters ended up. The format is only meant to be read, and may change between versions.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Template;
+    ///
+    /// fn main() {
+    ///   let template = Template::parse("<h1>{name|pad(8)}</h1>{#if admin}!{/if}");
+    ///
+    ///   assert!(template.debug_structure()
+    ///     == "literal \"<h1>\"\nplaceholder name | pad(8)\nliteral \"</h1>\"\n\
+    ///         block if admin\n  literal \"!\"\nend if\n");
+    /// }
+    /// ```
+    pub fn debug_structure(&self) -> String {
+        let mut dump = String::new();
+        debug_segments(&self.segments, 0, &mut dump);
+        dump
+    }
+
+    /// Render the template with placeholder values
+    ///
+    /// Values are inserted verbatim, so any placeholders within a value are not themselves
+    /// replaced.
+    ///
+    /// # Parameters
+    ///
+    /// `values` is the placeholder values to replace within the template, such as a HashMap (see
+    /// `AsValues`)
+    ///
+    /// # Returns
+    ///
+    /// `Ok(output)` is the template text with all its placeholders replaced with their
+    /// corresponding placeholder values
+    ///
+    /// `Err(RenderError::MissingPlaceholder { name, .. })` is the name of the first placeholder
+    /// missing from `values`
+    pub fn render<T: AsValues>(&self, values: T) -> Result<String, RenderError> {
+        Renderer::new().render_template(self, values)
+    }
+
+    /// Fill in the placeholders that have values, keeping the rest for a later pass
+    ///
+    /// Each placeholder with a value is replaced with its filtered value as literal text, while
+    /// placeholders without a value are kept as they are. Blocks whose value is known are resolved
+    /// too. The result is a template of its own, so a multi-stage pipeline can fill in values as
+    /// they become known without parsing the template again. As values become literal text, any
+    /// placeholders within them are never replaced by a later pass.
+    ///
+    /// # Parameters
+    ///
+    /// `values` is the placeholder values to replace within the template, such as a HashMap (see
+    /// `AsValues`)
+    ///
+    /// # Returns
+    ///
+    /// `Ok(template)` is the template with the placeholders that have values filled in
+    ///
+    /// `Err(error)` is the first error from a filter, such as `RenderError::UnknownFilter`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use placeholder::Template;
+    ///
+    /// fn main() {
+    ///   let template = Template::parse("<h1>{greet} {name}</h1>");
+    ///   let partial = template.render_partial(&[("greet", "Hello")]).unwrap();
+    ///
+    ///   assert!(partial.required_keys().iter().eq(["name"]));
+    ///   assert!(partial.render(&[("greet", "Bye"), ("name", "Homer")])
+    ///     == Ok(String::from("<h1>Hello Homer</h1>")));
+    /// }
+    /// ```
+    pub fn render_partial<T: AsValues>(&self, values: T) -> Result<Template, RenderError> {
+        let mut fill = Fill {
+            renderer: &Renderer::new(),
+            filled: Vec::new(),
+            error: None,
+        };
+
+        visit(
+            &self.segments,
+            &|name| values.get_value(name).map(String::from),
+            &mut fill,
+        );
+
+        if let Some(error) = fill.error {
+            return Err(error);
+        }
+
+        let mut template = Template {
+            segments: fill.filled,
+            required: BTreeSet::new(),
+        };
+
+        template.required = template.names().into_iter().map(String::from).collect();
+
+        Ok(template)
+    }
+}
+
+// What to do with each segment reached while walking a template with `visit()`
+pub(crate) trait Visitor {
+    fn literal(&mut self, _text: &str) {}
+
+    // A placeholder starting `start` bytes into the template text, with its value if there is one
+    fn placeholder(
+        &mut self,
+        _name: &str,
+        _filters: &[Filter],
+        _source: &str,
+        _start: usize,
+        _value: Option<String>,
+    ) {
+    }
+
+    // A placeholder without a value that has a default, whose segments aren't walked. `lookup` is
+    // the lookup the placeholder was reached with, for walking its default
+    fn defaulted(
+        &mut self,
+        _name: &str,
+        _filters: &[Filter],
+        _source: &str,
+        _start: usize,
+        _default: &[Segment],
+        _lookup: &dyn Fn(&str) -> Option<String>,
+    ) {
+    }
+
+    // A block with a value, before its body is walked once for each of its `iterations`
+    fn block(&mut self, _kind: BlockKind, _name: &str, _iterations: usize) {}
+
+    // A block without a value, with its opening tag starting `start` bytes into the template text,
+    // whose body isn't walked. `lookup` is the lookup the block was reached with, for walking its
+    // body anyway
+    fn missing_block(
+        &mut self,
+        _kind: BlockKind,
+        _name: &str,
+        _body: &[Segment],
+        _start: usize,
+        _lookup: &dyn Fn(&str) -> Option<String>,
+    ) {
+    }
+}
+
+// Walk the segments in the order rendering reaches them, looking up the value of each placeholder
+// and block with `lookup`. A block's body is walked once for each of its iterations, within which
+// the block's name is the value for that iteration
+pub(crate) fn visit(
+    segments: &[Segment],
+    lookup: &dyn Fn(&str) -> Option<String>,
+    visitor: &mut dyn Visitor,
+) {
+    for segment in segments {
+        match segment {
+            Segment::Literal(text) => visitor.literal(text),
+            Segment::Placeholder {
+                name,
+                filters,
+                source,
+                start,
+                default,
+            } => match (lookup(name), default) {
+                (None, Some(default)) => {
+                    visitor.defaulted(name, filters, source, *start, default, lookup)
+                }
+                (value, _) => visitor.placeholder(name, filters, source, *start, value),
+            },
+            Segment::Block {
+                kind,
+                name,
+                body,
+                start,
+            } => match lookup(name) {
+                None => visitor.missing_block(*kind, name, body, *start, lookup),
+                Some(value) => {
+                    let iterations = kind.iterations(&value);
+                    visitor.block(*kind, name, iterations.len());
+
+                    for item in iterations {
+                        let lookup_item = |other: &str| {
+                            if other == name {
+                                Some(item.to_string())
+                            } else {
+                                lookup(other)
+                            }
+                        };
+
+                        visit(body, &lookup_item, visitor);
+                    }
+                }
+            },
+        }
+    }
+}
+
+// Fills in the segments that have values, keeping the rest, up until the first error from a filter
+struct Fill<'r> {
+    renderer: &'r Renderer,
+    filled: Vec<Segment>,
+    error: Option<RenderError>,
+}
+
+impl Visitor for Fill<'_> {
+    fn literal(&mut self, text: &str) {
+        push_literal(&mut self.filled, text);
+    }
+
+    fn placeholder(
+        &mut self,
+        name: &str,
+        filters: &[Filter],
+        source: &str,
+        start: usize,
+        value: Option<String>,
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let mut value = match value {
+            Some(value) => value,
+            None => {
+                self.filled.push(Segment::Placeholder {
+                    name: name.to_string(),
+                    filters: filters.to_vec(),
+                    source: source.to_string(),
+                    start,
+                    default: None,
+                });
+
+                return;
+            }
+        };
+
+        for filter in filters {
+            match filters::apply(self.renderer, name, filter, &value) {
+                Ok(filtered) => value = filtered,
+                Err(error) => {
+                    self.error = Some(error);
+                    return;
+                }
+            }
+        }
+
+        push_literal(&mut self.filled, &value);
+    }
+
+    // The default is kept for a later pass, as its placeholder might have a value by then
+    fn defaulted(
+        &mut self,
+        name: &str,
+        filters: &[Filter],
+        source: &str,
+        start: usize,
+        default: &[Segment],
+        _lookup: &dyn Fn(&str) -> Option<String>,
+    ) {
+        if self.error.is_none() {
+            self.filled.push(Segment::Placeholder {
+                name: name.to_string(),
+                filters: filters.to_vec(),
+                source: source.to_string(),
+                start,
+                default: Some(default.to_vec()),
+            });
+        }
+    }
+
+    fn missing_block(
+        &mut self,
+        kind: BlockKind,
+        name: &str,
+        body: &[Segment],
+        start: usize,
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) {
+        let mut fill = Fill {
+            renderer: self.renderer,
+            filled: Vec::new(),
+            error: None,
+        };
+
+        visit(body, lookup, &mut fill);
+
+        self.error = self.error.take().or(fill.error);
+        self.filled.push(Segment::Block {
+            kind,
+            name: name.to_string(),
+            body: fill.filled,
+            start,
+        });
+    }
+}
+
+fn push_literal(segments: &mut Vec<Segment>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+
+    match segments.last_mut() {
+        Some(Segment::Literal(literal)) => literal.push_str(text),
+        _ => segments.push(Segment::Literal(text.to_string())),
+    }
+}
+
+/// Measurements of a parsed template, returned by `Template::stats()`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TemplateStats {
+    /// The number of bytes of literal text, after escapes have been applied
+    pub literal_bytes: usize,
+
+    /// The number of placeholders, including repeats
+    pub placeholders: usize,
+
+    /// The number of distinct placeholder names
+    pub distinct_names: usize,
+
+    /// The length of the longest placeholder name, in bytes, or zero without any placeholders
+    pub max_name_len: usize,
+}
+
+/// A collection of named templates, each parsed once on insertion
+///
+/// # Example
+///
+/// ```
+/// use placeholder::TemplateSet;
+/// use std::collections::HashMap;
+///
+/// fn main() {
+///   let mut templates = TemplateSet::new();
+///   templates.insert("title", "<h1>{greet} {name}</h1>");
+///   templates.insert("body", "<p>Do you like {food}?</p>");
+///
+///   let mut values = HashMap::new();
+///   values.insert(String::from("greet"), String::from("Hello"));
+///   values.insert(String::from("name"), String::from("Homer"));
+///   values.insert(String::from("food"), String::from("Donuts"));
+///
+///   assert!(templates.render("title", &values) == Ok(String::from("<h1>Hello Homer</h1>")));
+///   assert!(templates.render("body", &values) == Ok(String::from("<p>Do you like Donuts?</p>")));
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TemplateSet {
+    templates: HashMap<String, Template>,
+}
+
+impl TemplateSet {
+    /// Create an empty set of templates
+    pub fn new() -> TemplateSet {
+        TemplateSet::default()
+    }
+
+    /// Parse and store a template under `name`, replacing any template already stored under it
+    pub fn insert(&mut self, name: &str, template: &str) {
+        self.templates
+            .insert(name.to_string(), Template::parse(template));
+    }
+
+    /// Get the parsed template stored under `name`
+    pub fn get(&self, name: &str) -> Option<&Template> {
+        self.templates.get(name)
+    }
+
+    /// Render the template stored under `name` with placeholder values
+    ///
+    /// # Returns
+    ///
+    /// `Ok(output)` is the rendered template
+    ///
+    /// `Err(RenderError::UnknownTemplate { name })` is the name of the template missing from the
+    /// set
+    ///
+    /// `Err(RenderError::MissingPlaceholder { name, .. })` is the name of the placeholder missing
+    /// from `values`
+    pub fn render<T: AsValues>(&self, name: &str, values: T) -> Result<String, RenderError> {
+        match self.templates.get(name) {
+            None => Err(RenderError::UnknownTemplate {
+                name: name.to_string(),
+            }),
+            Some(template) => template.render(values),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_structure() {
+        let template = Template::parse(
+            "Dear \\{title} {name|truncate(5)|pad(6)},\nThe quick brown fox {#each items}- {items}{/each}",
+        );
+        let dump = template.debug_structure();
+
+        assert!(dump.contains("literal \"Dear {title} \"\n"));
+        assert!(dump.contains("placeholder name | truncate(5)|pad(6)\n"));
+        assert!(dump.contains("literal \",\\nThe quick brown fo\"...\n"));
+        assert!(
+            dump.contains("block each items\n  literal \"- \"\n  placeholder items\nend each\n")
+        );
+        assert!(Template::parse("").debug_structure().is_empty());
+    }
+
+    #[test]
+    fn defaults() {
+        let template = Template::parse("Dear {title=Mr {name}}{suffix?}");
+
+        assert!(
+            template.segments()
+                == [
+                    Segment::Literal(String::from("Dear ")),
+                    Segment::Placeholder {
+                        name: String::from("title"),
+                        filters: Vec::new(),
+                        source: String::from("{title=Mr {name}}"),
+                        start: 5,
+                        default: Some(vec![
+                            Segment::Literal(String::from("Mr ")),
+                            Segment::Placeholder {
+                                name: String::from("name"),
+                                filters: Vec::new(),
+                                source: String::from("{name}"),
+                                start: 15,
+                                default: None,
+                            },
+                        ]),
+                    },
+                    Segment::Placeholder {
+                        name: String::from("suffix"),
+                        filters: Vec::new(),
+                        source: String::from("{suffix?}"),
+                        start: 22,
+                        default: Some(Vec::new()),
+                    },
+                ]
+        );
+        assert!(template
+            .required_keys()
+            .iter()
+            .eq(["name", "suffix", "title"]));
+        assert!(
+            template.debug_structure()
+                == "literal \"Dear \"\nplaceholder title\ndefault\n  literal \"Mr \"\n  \
+                    placeholder name\nend default\nplaceholder suffix\ndefault\nend default\n"
+        );
+
+        let partial = template.render_partial(&[("name", "Homer")]).unwrap();
+
+        assert!(partial.render(&[("title", "Dr")]) == Ok(String::from("Dear Dr")));
+        assert!(partial.render(&[("name", "Marge")]) == Ok(String::from("Dear Mr Marge")));
+    }
+
+    #[test]
+    fn render_partial_two_stages() {
+        let template = Template::parse(
+            "Dear {title} {name},{#if vip} VIP {name}!{/if}{#each items} [{items|pad(3)}]{/each} {tail}",
+        );
+
+        let stage_one = [("title", "{name}"), ("items", "a\nbb"), ("tail", "")];
+        let partial = template.render_partial(&stage_one).unwrap();
+
+        assert!(partial.required_keys().iter().eq(["name", "vip"]));
+
+        let stage_two = [("name", "Homer"), ("vip", "yes")];
+
+        assert!(
+            partial.render(&stage_two)
+                == Ok(String::from("Dear {name} Homer, VIP Homer! [  a] [ bb] "))
+        );
+        assert!(
+            partial.render(&stage_two)
+                == template.render(&[
+                    ("title", "{name}"),
+                    ("items", "a\nbb"),
+                    ("tail", ""),
+                    ("name", "Homer"),
+                    ("vip", "yes")
+                ])
+        );
+
+        let unchanged = template.render_partial(&[("other", "x")]).unwrap();
+
+        assert!(unchanged.required_keys() == template.required_keys());
+        assert!(unchanged.render(&stage_one) == template.render(&stage_one));
+    }
+
+    #[test]
+    fn render_partial_filter_error() {
+        assert!(
+            Template::parse("{name|shout} {other}").render_partial(&[("name", "Homer")])
+                == Err(RenderError::UnknownFilter {
+                    name: String::from("shout")
+                })
+        );
+    }
+
+    #[test]
+    fn render_many_times() {
+        let template = Template::parse("{start} {middle} {end}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("middle"), String::from("beautiful"));
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(template.render(&values) == Ok(String::from("Hello beautiful world")));
+
+        values.insert(String::from("middle"), String::from("cruel"));
+
+        assert!(template.render(&values) == Ok(String::from("Hello cruel world")));
+    }
+
+    #[test]
+    fn values_are_not_rendered() {
+        let template = Template::parse("{start} {end}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("start"), String::from("{end}"));
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(template.render(&values) == Ok(String::from("{end} world")));
+    }
+
+    #[test]
+    fn filter_chain() {
+        let template = Template::parse(r#"{code|truncate(8)|pad(10, "|")} {name|lower}"#);
+
+        assert!(
+            template.segments()
+                == [
+                    Segment::Placeholder {
+                        name: String::from("code"),
+                        filters: vec![
+                            Filter {
+                                name: String::from("truncate"),
+                                args: vec![String::from("8")],
+                            },
+                            Filter {
+                                name: String::from("pad"),
+                                args: vec![String::from("10"), String::from("|")],
+                            },
+                        ],
+                        source: String::from(r#"{code|truncate(8)|pad(10, "|")}"#),
+                        start: 0,
+                        default: None,
+                    },
+                    Segment::Literal(String::from(" ")),
+                    Segment::Placeholder {
+                        name: String::from("name"),
+                        filters: vec![Filter {
+                            name: String::from("lower"),
+                            args: Vec::new(),
+                        }],
+                        source: String::from("{name|lower}"),
+                        start: 32,
+                        default: None,
+                    },
+                ]
+        );
+    }
+
+    #[test]
+    fn quoted_name_never_splits() {
+        let template = Template::parse(r#"{"a|b"} {a|b} {"first name"|lower}"#);
+
+        assert!(
+            template.segments()
+                == [
+                    Segment::Placeholder {
+                        name: String::from("a|b"),
+                        filters: Vec::new(),
+                        source: String::from(r#"{"a|b"}"#),
+                        start: 0,
+                        default: None,
+                    },
+                    Segment::Literal(String::from(" ")),
+                    Segment::Placeholder {
+                        name: String::from("a"),
+                        filters: vec![Filter {
+                            name: String::from("b"),
+                            args: Vec::new(),
+                        }],
+                        source: String::from("{a|b}"),
+                        start: 8,
+                        default: None,
+                    },
+                    Segment::Literal(String::from(" ")),
+                    Segment::Placeholder {
+                        name: String::from("first name"),
+                        filters: vec![Filter {
+                            name: String::from("lower"),
+                            args: Vec::new(),
+                        }],
+                        source: String::from(r#"{"first name"|lower}"#),
+                        start: 14,
+                        default: None,
+                    },
+                ]
+        );
+    }
+
+    #[test]
+    fn incomplete_quoted_name_is_literal() {
+        let template = Template::parse(r#"{""} {"a} {"a"b} {"{a}"}"#);
+
+        assert!(
+            template.segments()
+                == [
+                    Segment::Literal(String::from(r#"{""} {"a} {"a"b} {""#)),
+                    Segment::Placeholder {
+                        name: String::from("a"),
+                        filters: Vec::new(),
+                        source: String::from("{a}"),
+                        start: 19,
+                        default: None,
+                    },
+                    Segment::Literal(String::from(r#""}"#)),
+                ]
+        );
+    }
+
+    #[test]
+    fn incomplete_filter_chain_is_literal() {
+        let template = Template::parse("{name|} {name|pad(4} {name|pad(\"4)}");
+
+        assert!(
+            template.segments()
+                == [Segment::Literal(String::from(
+                    "{name|} {name|pad(4} {name|pad(\"4)}"
+                ))]
+        );
+    }
+
+    #[test]
+    fn stats() {
+        let template = Template::parse("{start} \\{middle} {middle|upper} {end}\n{end} {start}!");
+
+        assert!(
+            template.stats()
+                == TemplateStats {
+                    literal_bytes: 14,
+                    placeholders: 5,
+                    distinct_names: 3,
+                    max_name_len: 6,
+                }
+        );
+    }
+
+    #[test]
+    fn stats_without_placeholders() {
+        assert!(
+            Template::parse("Hello world").stats()
+                == TemplateStats {
+                    literal_bytes: 11,
+                    placeholders: 0,
+                    distinct_names: 0,
+                    max_name_len: 0,
+                }
+        );
+    }
+
+    #[test]
+    fn blank_names() {
+        let values = [("name", "Homer"), (" ", "space"), ("\t", "tab")];
+
+        for template in [
+            "{}",
+            "{ }",
+            "{\t}",
+            "{\" \"}",
+            "{\"\t\"}",
+            "{\"  \"|pad(8)}",
+        ] {
+            assert!(Template::parse(template).render(&values) == Ok(String::from(template)));
+            assert!(crate::placeholders(template).is_empty());
+        }
+
+        assert!(
+            Template::parse("{} {name} {\" \"}").render(&values)
+                == Ok(String::from("{} Homer {\" \"}"))
+        );
+    }
+
+    #[test]
+    fn blocks() {
+        let template = Template::parse("a{#if x}b{#each y}{y}{/each}{/if}c");
+
+        assert!(
+            template.segments()
+                == [
+                    Segment::Literal(String::from("a")),
+                    Segment::Block {
+                        kind: BlockKind::If,
+                        name: String::from("x"),
+                        body: vec![
+                            Segment::Literal(String::from("b")),
+                            Segment::Block {
+                                kind: BlockKind::Each,
+                                name: String::from("y"),
+                                body: vec![Segment::Placeholder {
+                                    name: String::from("y"),
+                                    filters: Vec::new(),
+                                    source: String::from("{y}"),
+                                    start: 18,
+                                    default: None,
+                                }],
+                                start: 9,
+                            },
+                        ],
+                        start: 1,
+                    },
+                    Segment::Literal(String::from("c")),
+                ]
+        );
+        assert!(template.required_keys().iter().eq(["x", "y"]));
+    }
+
+    #[test]
+    fn unmatched_blocks_are_literal() {
+        for text in [
+            "{#if x}never closed",
+            "{/if} never opened",
+            "{#if x}{/each}",
+            "{{#if x}escaped{/if}",
+            "{#unless x}{/unless} {#if} {#if x y}{/if} {#if  x}{/if}",
+        ] {
+            assert!(Template::parse(text).segments() == [Segment::Literal(String::from(text))]);
+        }
+    }
+
+    #[test]
+    fn escaped_blocks() {
+        let values = [("a", "1")];
+
+        for (text, rendered) in [
+            (r"\{#if a}X{/if}", r"{#if a}X{/if}"),
+            (r"\\{#if a}X{/if}", r"\X"),
+            (r"\\\{#if a}X{/if}", r"\{#if a}X{/if}"),
+            (r"{#if a}X\{/if}{/if}", r"X{/if}"),
+            (r"{#each a}\{a}{a}{/each}", r"{a}1"),
+        ] {
+            assert!(Template::parse(text).render(&values) == Ok(String::from(rendered)));
+        }
+    }
+
+    #[test]
+    fn mismatched_closing_tags_are_literal() {
+        let template = Template::parse("{#each y}{/if}{/each}");
+
+        assert!(
+            template.segments()
+                == [Segment::Block {
+                    kind: BlockKind::Each,
+                    name: String::from("y"),
+                    body: vec![Segment::Literal(String::from("{/if}"))],
+                    start: 0,
+                }]
+        );
+    }
+
+    #[test]
+    fn blocks_only_with_braces() {
+        let template = Template::parse_double_brace("{#if x}{{x}}{/if}");
+
+        assert!(template.render(&[("x", "")]) == Ok(String::from("{#if x}{/if}")));
+    }
+
+    #[test]
+    fn required_keys() {
+        for text in [
+            "{start} {middle} {end} {middle}",
+            r"{a} {{b} \{c} \\{d} {} {e|pad(4)}",
+            "Hello world",
+        ] {
+            let template = Template::parse(text);
+
+            assert!(template
+                .required_keys()
+                .iter()
+                .eq(crate::placeholders(text).iter()));
+        }
+    }
+
+    #[test]
+    fn dynamic_ratio() {
+        assert!(Template::parse("{name}, {name}!").dynamic_ratio() == 0.8);
+        assert!(Template::parse("{name|pad(8)}").dynamic_ratio() == 1.0);
+        assert!(Template::parse("Hello {{name}}").dynamic_ratio() == 0.0);
+        assert!(Template::parse("").dynamic_ratio() == 0.0);
+    }
+
+    #[test]
+    fn double_brace_json() {
+        let template = Template::parse_double_brace(r#"{"name": "{{name}}", "tags": {"a": 1}}"#);
+
+        let mut values = HashMap::new();
+        values.insert(String::from("name"), String::from("Homer"));
+
+        assert!(
+            template.render(&values) == Ok(String::from(r#"{"name": "Homer", "tags": {"a": 1}}"#))
+        );
+    }
+
+    #[test]
+    fn double_brace_ignores_single_brace() {
+        let template = Template::parse_double_brace("{name} {{name}} {name}}");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("name"), String::from("Homer"));
+
+        assert!(template.render(&values) == Ok(String::from("{name} Homer {name}}")));
+    }
+
+    #[test]
+    fn set_renders_different_templates() {
+        let mut templates = TemplateSet::new();
+        templates.insert("greeting", "{greet} {name}");
+        templates.insert("question", "Do you like {food}?");
+
+        let mut values = HashMap::new();
+        values.insert(String::from("greet"), String::from("Hello"));
+        values.insert(String::from("name"), String::from("Homer"));
+        values.insert(String::from("food"), String::from("Donuts"));
+
+        assert!(templates.render("greeting", &values) == Ok(String::from("Hello Homer")));
+        assert!(templates.render("question", &values) == Ok(String::from("Do you like Donuts?")));
+    }
+
+    #[test]
+    fn set_unknown_template() {
+        let mut templates = TemplateSet::new();
+        templates.insert("greeting", "{greet} {name}");
+
+        let values: HashMap<String, String> = HashMap::new();
+
+        assert!(
+            templates.render("farewell", &values)
+                == Err(RenderError::UnknownTemplate {
+                    name: String::from("farewell")
+                })
+        );
+
+        assert!(
+            templates.render("greeting", &values)
+                == Err(RenderError::MissingPlaceholder {
+                    name: String::from("greet"),
+                    available: None,
+                })
+        );
+    }
+
+    #[test]
+    fn max_placeholders_stops_scan() {
+        let options = ParseOptions {
+            max_placeholders: Some(3),
+            ..ParseOptions::default()
+        };
+        let matches = MATCHES.with(|matches| matches.get());
+
+        assert!(
+            Template::parse_with_options(&"{a}".repeat(100_000), &options).err()
+                == Some(RenderError::TooManyPlaceholders { max: 3 })
+        );
+        assert!(MATCHES.with(|matches| matches.get()) == matches + 4);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{AsValues, Renderer};
+
+/// Assert that the template renders with placeholder values to exactly the expected output
+///
+/// This is a test helper for crates building on this one, such as checking middlewares or value
+/// sources against the rendering engine, so that their tests don't each need to render and
+/// compare. On a mismatch, or when the template fails to render, it panics with the template and
+/// a line by line diff of the expected and actual output, where lines only in the expected
+/// output start with `-` and lines only in the actual output start with `+`.
+///
+/// # Parameters
+///
+/// `template` is the template text containing placeholders in the form `{name}`
+///
+/// `values` is the placeholder values to replace within `template`, such as a HashMap (see
+/// `AsValues`)
+///
+/// `expected` is the output the template should render to
+///
+/// # Panics
+///
+/// When the template fails to render, or renders to anything other than `expected`
+///
+/// # Example
+///
+/// ```
+/// use placeholder::assert_renders;
+///
+/// fn main() {
+///   assert_renders("<h1>{greet} {name}</h1>", &[("greet", "Hello"), ("name", "Homer")],
+///     "<h1>Hello Homer</h1>");
+/// }
+/// ```
+#[track_caller]
+pub fn assert_renders<T: AsValues>(template: &str, values: T, expected: &str) {
+    match Renderer::new().render(template, values) {
+        Ok(output) if output == expected => {}
+        Ok(output) => panic!(
+            "template rendered differently\ntemplate: {:?}\n--- expected\n+++ actual\n{}",
+            template,
+            diff(expected, &output)
+        ),
+        Err(error) => panic!(
+            "template failed to render: {}\ntemplate: {:?}\nexpected: {:?}",
+            error, template, expected
+        ),
+    }
+}
+
+// A line by line diff, pairing up the lines of each text in order. Lines are quoted so that
+// differences in whitespace stand out
+fn diff(expected: &str, actual: &str) -> String {
+    let mut expected = expected.split('\n');
+    let mut actual = actual.split('\n');
+    let mut diff = String::new();
+
+    loop {
+        match (expected.next(), actual.next()) {
+            (None, None) => return diff,
+            (Some(line), Some(other)) if line == other => {
+                diff.push_str(&format!("  {:?}\n", line));
+            }
+            (line, other) => {
+                if let Some(line) = line {
+                    diff.push_str(&format!("- {:?}\n", line));
+                }
+
+                if let Some(other) = other {
+                    diff.push_str(&format!("+ {:?}\n", other));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic;
+
+    // The message of the panic from `assert_renders()`
+    fn panic_message<T: AsValues + panic::UnwindSafe>(
+        template: &str,
+        values: T,
+        expected: &str,
+    ) -> String {
+        let payload = panic::catch_unwind(|| assert_renders(template, values, expected))
+            .expect_err("assert_renders() should have panicked");
+
+        match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(_) => panic!("at the disco"),
+        }
+    }
+
+    #[test]
+    fn matching_output() {
+        assert_renders(
+            "{greet}\n{name}",
+            &[("greet", "Hello"), ("name", "Homer")],
+            "Hello\nHomer",
+        );
+        assert_renders("", &[("name", "Homer")], "");
+    }
+
+    #[test]
+    fn mismatch_diff() {
+        let message = panic_message(
+            "{greet}\n{name}\nBye",
+            &[("greet", "Hello"), ("name", "Homer ")],
+            "Hello\nHomer\nBye\n",
+        );
+
+        assert!(
+            message
+                == "template rendered differently\n\
+                    template: \"{greet}\\n{name}\\nBye\"\n\
+                    --- expected\n\
+                    +++ actual\n  \
+                    \"Hello\"\n\
+                    - \"Homer\"\n\
+                    + \"Homer \"\n  \
+                    \"Bye\"\n\
+                    - \"\"\n"
+        );
+    }
+
+    #[test]
+    fn render_error() {
+        let message = panic_message("Hi {name}", &[("greet", "Hello")], "Hi Homer");
+
+        assert!(
+            message
+                == "template failed to render: name\n\
+                    template: \"Hi {name}\"\n\
+                    expected: \"Hi Homer\""
+        );
+    }
+}
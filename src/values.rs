@@ -0,0 +1,423 @@
+use std::borrow::{Borrow, Cow};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+
+/// Placeholder values that can be passed to `render()` and friends
+///
+/// This is implemented for a reference to anything implementing `Lookup`, such as a `HashMap` or
+/// `BTreeMap` keyed by `String` or `&str`, with values of `String`, `&str`, `Arc<str>`, or anything
+/// else that is `AsRef<str>`, as well as slices and arrays of `(&str, &str)` pairs for when
+/// building a map isn't worth it. When a pair's name appears more than once, the last one wins,
+/// unless the `Renderer`'s `reject_duplicates()` is on. Closures computing values, and slices and
+/// arrays of `&dyn Lookup` sources tried in order, also implement `Lookup`.
+///
+/// # Example
+///
+/// ```
+/// use placeholder::render;
+///
+/// fn main() {
+///   let template = String::from("<h1>{greet} {name}</h1>");
+///
+///   assert!(render(&template, &[("greet", "Hello"), ("name", "Homer")])
+///     == Ok(String::from("<h1>Hello Homer</h1>")));
+/// }
+/// ```
+pub trait AsValues {
+    /// Get the value of the placeholder called `name`
+    fn get_value(&self, name: &str) -> Option<Cow<'_, str>>;
+
+    /// Get the name of the first placeholder given more than one value, for
+    /// `Renderer::reject_duplicates()`
+    ///
+    /// Defaults to `None`, as values such as maps can't hold duplicates
+    fn duplicate_name(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+}
+
+impl<L: Lookup + ?Sized> AsValues for &L {
+    fn get_value(&self, name: &str) -> Option<Cow<'_, str>> {
+        (**self).get(name)
+    }
+
+    fn duplicate_name(&self) -> Option<Cow<'_, str>> {
+        (**self).duplicate()
+    }
+}
+
+/// A source of placeholder values, looked up by placeholder name
+///
+/// Implement this to render directly from a source of values that isn't a map of `String`s, such
+/// as a concurrent map holding live configuration, without first copying it into a `HashMap`.
+/// Values can be borrowed from the source, or owned when they can't outlive a lock or are
+/// computed on the fly.
+///
+/// As values are inserted verbatim, a computed value can also act as an expansion point, such as
+/// a `{rows}` placeholder replaced by any number of rendered lines, or by nothing at all. Any
+/// placeholders within the computed value are left as is, so the rows can't inject placeholders of
+/// their own.
+///
+/// # Example
+///
+/// ```
+/// use placeholder::{render, Lookup};
+/// use std::borrow::Cow;
+/// use std::collections::HashMap;
+/// use std::sync::RwLock;
+///
+/// struct Config(RwLock<HashMap<String, String>>);
+///
+/// impl Lookup for Config {
+///   fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+///     self.0.read().unwrap().get(name).cloned().map(Cow::Owned)
+///   }
+/// }
+///
+/// fn main() {
+///   let config = Config(RwLock::new(HashMap::new()));
+///   config.0.write().unwrap().insert(String::from("name"), String::from("Homer"));
+///
+///   assert!(render("<h1>Hello {name}</h1>", &config) == Ok(String::from("<h1>Hello Homer</h1>")));
+/// }
+/// ```
+pub trait Lookup {
+    /// Get the value of the placeholder called `name`
+    fn get(&self, name: &str) -> Option<Cow<'_, str>>;
+
+    /// Get the name of the first placeholder given more than one value, for
+    /// `Renderer::reject_duplicates()`
+    ///
+    /// Defaults to `None`, as sources such as maps can't hold duplicates
+    fn duplicate(&self) -> Option<Cow<'_, str>> {
+        None
+    }
+}
+
+impl<K, V, S> Lookup for HashMap<K, V, S>
+where
+    K: Borrow<str> + Eq + Hash,
+    V: AsRef<str>,
+    S: BuildHasher,
+{
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        HashMap::get(self, name).map(|value| Cow::Borrowed(value.as_ref()))
+    }
+}
+
+impl<K, V> Lookup for BTreeMap<K, V>
+where
+    K: Borrow<str> + Ord,
+    V: AsRef<str>,
+{
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        BTreeMap::get(self, name).map(|value| Cow::Borrowed(value.as_ref()))
+    }
+}
+
+impl Lookup for [(&str, &str)] {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        get_pair(self, name)
+    }
+
+    fn duplicate(&self) -> Option<Cow<'_, str>> {
+        duplicate_pair(self)
+    }
+}
+
+impl<const N: usize> Lookup for [(&str, &str); N] {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        get_pair(self, name)
+    }
+
+    fn duplicate(&self) -> Option<Cow<'_, str>> {
+        duplicate_pair(self)
+    }
+}
+
+/// Look up each name from the sources in turn, taking the value from the first that has it
+impl Lookup for [&dyn Lookup] {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.iter().find_map(|source| source.get(name))
+    }
+}
+
+impl<const N: usize> Lookup for [&dyn Lookup; N] {
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        Lookup::get(&self[..], name)
+    }
+}
+
+/// Compute each value with a closure, returning `None` when it's missing
+impl<F> Lookup for F
+where
+    F: Fn(&str) -> Option<String>,
+{
+    fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+        self(name).map(Cow::Owned)
+    }
+}
+
+fn get_pair<'a>(pairs: &[(&str, &'a str)], name: &str) -> Option<Cow<'a, str>> {
+    pairs
+        .iter()
+        .rev()
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| Cow::Borrowed(*value))
+}
+
+// The first name, in order, that appears again in a later pair
+fn duplicate_pair<'a>(pairs: &[(&'a str, &str)]) -> Option<Cow<'a, str>> {
+    pairs
+        .iter()
+        .enumerate()
+        .find(|(at, (name, _))| pairs[at + 1..].iter().any(|(other, _)| other == name))
+        .map(|(_, (name, _))| Cow::Borrowed(*name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render;
+    use std::sync::{Arc, RwLock};
+
+    #[test]
+    fn btree_map() {
+        let before = String::from("{start} {middle} {end}");
+        let after = String::from("Hello beautiful world");
+
+        let mut values = BTreeMap::new();
+        values.insert(String::from("start"), String::from("Hello"));
+        values.insert(String::from("middle"), String::from("beautiful"));
+        values.insert(String::from("end"), String::from("world"));
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn shared_values() {
+        let before = String::from("{start} {middle} {end}");
+        let after = String::from("Hello beautiful world");
+
+        let mut values: HashMap<String, Arc<str>> = HashMap::new();
+        values.insert(String::from("start"), Arc::from("Hello"));
+        values.insert(String::from("middle"), Arc::from("beautiful"));
+        values.insert(String::from("end"), Arc::from("world"));
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn borrowed_values() {
+        let before = String::from("{start} {middle} {end}");
+        let after = String::from("Hello beautiful world");
+
+        let mut values = BTreeMap::new();
+        values.insert("start", "Hello");
+        values.insert("middle", "beautiful");
+        values.insert("end", "world");
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+
+    #[test]
+    fn concurrent_map() {
+        struct Config(RwLock<HashMap<String, String>>);
+
+        impl Lookup for Config {
+            fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+                self.0.read().unwrap().get(name).cloned().map(Cow::Owned)
+            }
+        }
+
+        let before = String::from("{start} {end}");
+        let config = Config(RwLock::new(HashMap::new()));
+
+        config
+            .0
+            .write()
+            .unwrap()
+            .insert(String::from("start"), String::from("Hello"));
+
+        assert!(render(&before, &config) == Err(String::from("end")));
+
+        config
+            .0
+            .write()
+            .unwrap()
+            .insert(String::from("end"), String::from("world"));
+
+        assert!(render(&before, &config) == Ok(String::from("Hello world")));
+
+        config
+            .0
+            .write()
+            .unwrap()
+            .insert(String::from("end"), String::from("everyone"));
+
+        assert!(render(&before, &config) == Ok(String::from("Hello everyone")));
+    }
+
+    #[test]
+    fn expansion_point() {
+        struct Report<'a> {
+            title: &'a str,
+            rows: Vec<(&'a str, u32)>,
+        }
+
+        impl Lookup for Report<'_> {
+            fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+                match name {
+                    "title" => Some(Cow::Borrowed(self.title)),
+                    "rows" => {
+                        let row = crate::Template::parse("* {name}: {count}\n");
+
+                        Some(Cow::Owned(
+                            self.rows
+                                .iter()
+                                .map(|(name, count)| {
+                                    let count = count.to_string();
+                                    row.render(&[("name", *name), ("count", count.as_str())])
+                                        .unwrap_or_default()
+                                })
+                                .collect(),
+                        ))
+                    }
+                    _ => None,
+                }
+            }
+        }
+
+        let before = String::from("# {title}\n{rows}-- end --");
+
+        let report = Report {
+            title: "Donuts",
+            rows: vec![("Homer", 42), ("{title}", 1), ("Marge", 0)],
+        };
+
+        assert!(
+            render(&before, &report)
+                == Ok(String::from(
+                    "# Donuts\n* Homer: 42\n* {title}: 1\n* Marge: 0\n-- end --"
+                ))
+        );
+
+        let report = Report {
+            title: "Nothing",
+            rows: Vec::new(),
+        };
+
+        assert!(render(&before, &report) == Ok(String::from("# Nothing\n-- end --")));
+    }
+
+    #[test]
+    fn layered_sources() {
+        struct Env(BTreeMap<&'static str, &'static str>);
+
+        impl Lookup for Env {
+            fn get(&self, name: &str) -> Option<Cow<'_, str>> {
+                let var = format!("APP_{}", name.to_uppercase());
+                self.0.get(var.as_str()).map(|value| Cow::Borrowed(*value))
+            }
+        }
+
+        let before = String::from("{start} {middle} {end}");
+
+        let mut overrides = HashMap::new();
+        overrides.insert(String::from("middle"), String::from("cruel"));
+
+        let env = Env(BTreeMap::from([
+            ("APP_START", "Hello"),
+            ("APP_MIDDLE", "beautiful"),
+        ]));
+
+        let fallback = |name: &str| Some(format!("<{}>", name));
+
+        assert!(
+            crate::render_layered(&before, &[&overrides, &env, &fallback])
+                == Ok(String::from("Hello cruel <end>"))
+        );
+        assert!(crate::render_layered(&before, &[&env, &overrides]) == Err(String::from("end")));
+        assert!(
+            render(&before, &[&fallback as &dyn Lookup, &env])
+                == Ok(String::from("<start> <middle> <end>"))
+        );
+        assert!(crate::render_layered(&before, &[]) == Err(String::from("start")));
+    }
+
+    #[test]
+    fn pairs() {
+        let before = String::from("{start} {middle} {end}");
+        let after = String::from("Hello beautiful world");
+
+        let values = [
+            ("start", "Hello"),
+            ("middle", "beautiful"),
+            ("end", "world"),
+        ];
+
+        assert!(render(&before, &values) == Ok(after.clone()));
+        assert!(render(&before, &values[..]) == Ok(after));
+    }
+
+    #[test]
+    fn pairs_missing_value() {
+        let before = String::from("{start} {middle} {end}");
+
+        assert!(
+            render(&before, &[("start", "Hello"), ("end", "world")]) == Err(String::from("middle"))
+        );
+    }
+
+    #[test]
+    fn pairs_reject_duplicates() {
+        let before = String::from("{start} {middle} {end}");
+        let renderer = crate::Renderer::new().reject_duplicates(true);
+
+        let values = [
+            ("start", "Hello"),
+            ("end", "world"),
+            ("middle", "beautiful"),
+            ("end", "everyone"),
+            ("middle", "cruel"),
+        ];
+
+        assert!(
+            renderer.render(&before, &values)
+                == Err(crate::RenderError::DuplicateValue {
+                    name: String::from("end")
+                })
+        );
+        assert!(
+            renderer.render(&before, &values[..3]) == Ok(String::from("Hello beautiful world"))
+        );
+
+        // Duplicates are rejected even when no placeholder uses them
+        assert!(
+            renderer.render("{start}", &[("start", "Hello"), ("x", "1"), ("x", "2")])
+                == Err(crate::RenderError::DuplicateValue {
+                    name: String::from("x")
+                })
+        );
+
+        let mut map = HashMap::new();
+        map.insert("start", "Hello");
+
+        assert!(renderer.render("{start}", &map) == Ok(String::from("Hello")));
+    }
+
+    #[test]
+    fn pairs_last_wins() {
+        let before = String::from("{start} {middle} {end}");
+        let after = String::from("Hello cruel world");
+
+        let values = [
+            ("start", "Hello"),
+            ("middle", "beautiful"),
+            ("end", "world"),
+            ("middle", "cruel"),
+        ];
+
+        assert!(render(&before, &values) == Ok(after));
+    }
+}